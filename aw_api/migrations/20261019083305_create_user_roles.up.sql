@@ -0,0 +1,12 @@
+-- Add up migration script here
+CREATE TYPE user_role AS ENUM ('moderator', 'admin');
+
+CREATE TABLE user_roles (
+    user_id UUID NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    role user_role NOT NULL,
+    PRIMARY KEY (user_id, role),
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
+
+SELECT sqlx_manage_updated_at('user_roles');
\ No newline at end of file
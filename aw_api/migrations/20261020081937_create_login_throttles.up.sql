@@ -0,0 +1,12 @@
+-- Add up migration script here
+-- One row per email address and per IP address that failed to login, e.g. `email:jane@example.com` or `ip:10.0.0.1`
+CREATE TABLE login_throttles (
+    key TEXT PRIMARY KEY,
+    failures INTEGER NOT NULL DEFAULT 0,
+    last_failure_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    locked_until TIMESTAMP,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
+
+SELECT sqlx_manage_updated_at('login_throttles');
\ No newline at end of file
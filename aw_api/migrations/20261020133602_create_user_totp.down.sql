@@ -0,0 +1,3 @@
+-- Add down migration script here
+DROP TABLE totp_recovery_codes;
+DROP TABLE user_totp;
\ No newline at end of file
@@ -0,0 +1,14 @@
+-- Add up migration script here
+CREATE TABLE magic_links (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    token_hash TEXT UNIQUE NOT NULL,
+    expires_at TIMESTAMP NOT NULL,
+    used_at TIMESTAMP,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
+
+CREATE INDEX magic_links_user_id_created_at_idx ON magic_links (user_id, created_at);
+
+SELECT sqlx_manage_updated_at('magic_links');
\ No newline at end of file
@@ -0,0 +1,3 @@
+-- Add down migration script here
+DROP TABLE api_keys;
+DROP TYPE api_key_scope;
\ No newline at end of file
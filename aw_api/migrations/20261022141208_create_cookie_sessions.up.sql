@@ -0,0 +1,12 @@
+-- Add up migration script here
+CREATE TABLE cookie_sessions (
+    token_hash TEXT PRIMARY KEY,
+    session_id UUID NOT NULL REFERENCES sessions (id) ON DELETE CASCADE,
+    expires_at TIMESTAMP NOT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
+
+CREATE INDEX cookie_sessions_expires_at_idx ON cookie_sessions (expires_at);
+
+SELECT sqlx_manage_updated_at('cookie_sessions');
\ No newline at end of file
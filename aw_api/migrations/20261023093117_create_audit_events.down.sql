@@ -0,0 +1,4 @@
+-- Add down migration script here
+DROP TABLE audit_events;
+DROP FUNCTION reject_audit_event_change;
+DROP TYPE audit_event_kind;
\ No newline at end of file
@@ -0,0 +1,35 @@
+-- Add up migration script here
+CREATE TYPE audit_event_kind AS ENUM (
+    'login',
+    'login_failed',
+    'password_changed',
+    'role_granted',
+    'role_revoked',
+    'forbidden',
+    'account_deleted'
+);
+
+-- No foreign key on actor_id, the events have to outlive the account they are about
+CREATE TABLE audit_events (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    kind audit_event_kind NOT NULL,
+    actor_id UUID,
+    ip_address TEXT,
+    user_agent TEXT,
+    details JSONB NOT NULL DEFAULT '{}',
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
+
+CREATE INDEX audit_events_created_at_idx ON audit_events (created_at);
+CREATE INDEX audit_events_actor_id_created_at_idx ON audit_events (actor_id, created_at);
+CREATE INDEX audit_events_kind_created_at_idx ON audit_events (kind, created_at);
+
+CREATE FUNCTION reject_audit_event_change() RETURNS trigger AS $$
+BEGIN
+    RAISE EXCEPTION 'audit_events is append-only';
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER audit_events_append_only
+    BEFORE UPDATE OR DELETE ON audit_events
+    FOR EACH ROW EXECUTE FUNCTION reject_audit_event_change();
\ No newline at end of file
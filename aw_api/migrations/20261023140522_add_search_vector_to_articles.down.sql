@@ -0,0 +1,4 @@
+-- Add down migration script here
+DROP INDEX articles_search_vector_idx;
+ALTER TABLE articles DROP COLUMN search_vector;
+ALTER TABLE articles DROP COLUMN search_language;
\ No newline at end of file
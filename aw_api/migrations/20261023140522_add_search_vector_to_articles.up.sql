@@ -0,0 +1,12 @@
+-- Add up migration script here
+-- Text search configuration the article is written in, e.g. 'indonesian'
+ALTER TABLE articles ADD COLUMN search_language regconfig NOT NULL DEFAULT 'english';
+
+-- The title ranks above the description, which ranks above the body
+ALTER TABLE articles ADD COLUMN search_vector tsvector GENERATED ALWAYS AS (
+    setweight(to_tsvector(search_language, title), 'A') ||
+    setweight(to_tsvector(search_language, description), 'B') ||
+    setweight(to_tsvector(search_language, body), 'C')
+) STORED;
+
+CREATE INDEX articles_search_vector_idx ON articles USING GIN (search_vector);
\ No newline at end of file
@@ -0,0 +1,3 @@
+-- Add down migration script here
+DROP INDEX IF EXISTS articles_author_id_created_at_id_idx;
+DROP INDEX IF EXISTS articles_created_at_id_idx;
\ No newline at end of file
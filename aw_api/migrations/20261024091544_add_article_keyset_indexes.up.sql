@@ -0,0 +1,3 @@
+-- Add up migration script here
+CREATE INDEX articles_created_at_id_idx ON articles (created_at DESC, id DESC);
+CREATE INDEX articles_author_id_created_at_id_idx ON articles (author_id, created_at DESC, id DESC);
\ No newline at end of file
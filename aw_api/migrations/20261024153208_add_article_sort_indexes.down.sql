@@ -0,0 +1,4 @@
+-- Add down migration script here
+DROP INDEX IF EXISTS comments_article_id_created_at_idx;
+DROP INDEX IF EXISTS favorite_articles_article_id_created_at_idx;
+DROP INDEX IF EXISTS articles_updated_at_id_idx;
\ No newline at end of file
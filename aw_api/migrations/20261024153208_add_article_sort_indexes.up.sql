@@ -0,0 +1,6 @@
+-- Add up migration script here
+CREATE INDEX articles_updated_at_id_idx ON articles (updated_at DESC, id DESC);
+
+-- Counting favorites and comments per article, and only the recent ones for trending
+CREATE INDEX favorite_articles_article_id_created_at_idx ON favorite_articles (article_id, created_at);
+CREATE INDEX comments_article_id_created_at_idx ON comments (article_id, created_at);
\ No newline at end of file
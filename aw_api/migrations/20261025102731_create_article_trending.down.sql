@@ -0,0 +1,5 @@
+-- Add down migration script here
+DROP INDEX IF EXISTS comments_created_at_idx;
+DROP INDEX IF EXISTS favorite_articles_created_at_idx;
+DROP TABLE IF EXISTS article_trending_scores;
+DROP TABLE IF EXISTS article_daily_views;
\ No newline at end of file
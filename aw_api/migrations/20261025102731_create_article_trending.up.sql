@@ -0,0 +1,25 @@
+-- Add up migration script here
+CREATE TABLE article_daily_views (
+    article_id UUID NOT NULL REFERENCES articles (id) ON DELETE CASCADE,
+    day DATE NOT NULL,
+    views INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (article_id, day),
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
+
+CREATE INDEX article_daily_views_day_idx ON article_daily_views (day);
+
+SELECT sqlx_manage_updated_at('article_daily_views');
+
+-- Rewritten as a whole by the periodic refresh, never per request
+CREATE TABLE article_trending_scores (
+    article_id UUID PRIMARY KEY REFERENCES articles (id) ON DELETE CASCADE,
+    score DOUBLE PRECISION NOT NULL,
+    computed_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
+
+CREATE INDEX article_trending_scores_score_idx ON article_trending_scores (score DESC);
+
+CREATE INDEX favorite_articles_created_at_idx ON favorite_articles (created_at);
+CREATE INDEX comments_created_at_idx ON comments (created_at);
\ No newline at end of file
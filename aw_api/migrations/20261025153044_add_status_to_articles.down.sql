@@ -0,0 +1,11 @@
+-- Add down migration script here
+DROP INDEX IF EXISTS articles_scheduled_idx;
+DROP INDEX IF EXISTS articles_author_id_published_at_id_idx;
+DROP INDEX IF EXISTS articles_published_at_id_idx;
+CREATE INDEX articles_created_at_id_idx ON articles (created_at DESC, id DESC);
+CREATE INDEX articles_author_id_created_at_id_idx ON articles (author_id, created_at DESC, id DESC);
+
+ALTER TABLE articles DROP CONSTRAINT IF EXISTS articles_published_at_chk;
+ALTER TABLE articles DROP COLUMN IF EXISTS published_at;
+ALTER TABLE articles DROP COLUMN IF EXISTS status;
+DROP TYPE IF EXISTS article_status;
\ No newline at end of file
@@ -0,0 +1,20 @@
+-- Add up migration script here
+CREATE TYPE article_status AS ENUM ('draft', 'published', 'archived');
+
+-- The articles written so far were published right away
+ALTER TABLE articles ADD COLUMN status article_status NOT NULL DEFAULT 'published';
+ALTER TABLE articles ALTER COLUMN status SET DEFAULT 'draft';
+
+-- When the article went out, or for a draft when it is scheduled to
+ALTER TABLE articles ADD COLUMN published_at TIMESTAMP;
+UPDATE articles SET published_at = created_at;
+
+ALTER TABLE articles ADD CONSTRAINT articles_published_at_chk CHECK (status <> 'published' OR published_at IS NOT NULL);
+
+-- The lists are now ordered by publication time and only show published articles
+DROP INDEX IF EXISTS articles_created_at_id_idx;
+DROP INDEX IF EXISTS articles_author_id_created_at_id_idx;
+CREATE INDEX articles_published_at_id_idx ON articles (published_at DESC, id DESC) WHERE status = 'published';
+CREATE INDEX articles_author_id_published_at_id_idx ON articles (author_id, published_at DESC, id DESC) WHERE status = 'published';
+
+CREATE INDEX articles_scheduled_idx ON articles (published_at) WHERE status = 'draft' AND published_at IS NOT NULL;
\ No newline at end of file
@@ -0,0 +1,18 @@
+-- Add up migration script here
+CREATE TABLE article_revisions (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    article_id UUID NOT NULL REFERENCES articles (id) ON DELETE CASCADE,
+    revision INTEGER NOT NULL,
+    title TEXT NOT NULL,
+    description TEXT NOT NULL,
+    body TEXT NOT NULL,
+    edited_by UUID REFERENCES users (id) ON DELETE SET NULL,
+    -- The revision brought back by a restore
+    restored_from INTEGER,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    UNIQUE (article_id, revision)
+);
+
+-- The articles written so far start their history with their current version
+INSERT INTO article_revisions (article_id, revision, title, description, body, edited_by, created_at)
+SELECT id, 1, title, description, body, author_id, updated_at FROM articles;
\ No newline at end of file
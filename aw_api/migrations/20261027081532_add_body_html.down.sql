@@ -0,0 +1,3 @@
+-- Add down migration script here
+ALTER TABLE comments DROP COLUMN body_html;
+ALTER TABLE articles DROP COLUMN body_html;
\ No newline at end of file
@@ -0,0 +1,4 @@
+-- Add up migration script here
+-- Markdown bodies rendered to sanitized HTML, NULL until the row is rendered so clearing it re-renders
+ALTER TABLE articles ADD COLUMN body_html TEXT;
+ALTER TABLE comments ADD COLUMN body_html TEXT;
\ No newline at end of file
@@ -0,0 +1,4 @@
+-- Add down migration script here
+DROP TRIGGER IF EXISTS articles_slug_history ON articles;
+DROP FUNCTION IF EXISTS record_article_slug_change();
+DROP TABLE IF EXISTS article_slug_history;
\ No newline at end of file
@@ -0,0 +1,28 @@
+-- Add up migration script here
+-- Slugs an article was reachable under before its current one, so old links can redirect
+CREATE TABLE article_slug_history (
+    slug TEXT PRIMARY KEY,
+    article_id UUID NOT NULL REFERENCES articles(id) ON DELETE CASCADE,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX article_slug_history_article_id_idx ON article_slug_history (article_id);
+
+-- Every way of renaming an article goes through here, an article taking back an old slug drops it from the history
+CREATE FUNCTION record_article_slug_change() RETURNS trigger AS $$
+BEGIN
+    DELETE FROM article_slug_history WHERE slug = NEW.slug;
+
+    INSERT INTO article_slug_history (slug, article_id)
+    VALUES (OLD.slug, OLD.id)
+    ON CONFLICT (slug) DO UPDATE SET article_id = EXCLUDED.article_id, created_at = CURRENT_TIMESTAMP;
+
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER articles_slug_history
+    AFTER UPDATE OF slug ON articles
+    FOR EACH ROW
+    WHEN (OLD.slug IS DISTINCT FROM NEW.slug)
+    EXECUTE FUNCTION record_article_slug_change();
\ No newline at end of file
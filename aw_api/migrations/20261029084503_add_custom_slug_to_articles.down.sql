@@ -0,0 +1,2 @@
+-- Add down migration script here
+ALTER TABLE articles DROP COLUMN custom_slug;
\ No newline at end of file
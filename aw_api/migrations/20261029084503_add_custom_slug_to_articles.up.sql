@@ -0,0 +1,3 @@
+-- Add up migration script here
+-- A slug the author chose, kept when the title changes
+ALTER TABLE articles ADD COLUMN custom_slug BOOLEAN NOT NULL DEFAULT FALSE;
\ No newline at end of file
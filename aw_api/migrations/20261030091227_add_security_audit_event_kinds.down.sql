@@ -0,0 +1,21 @@
+-- Add down migration script here
+-- Values cannot be dropped from an enum, the type is built again without them
+ALTER TABLE audit_events DISABLE TRIGGER audit_events_append_only;
+DELETE FROM audit_events WHERE kind IN ('account_locked', 'passkey_counter_regressed');
+ALTER TABLE audit_events ENABLE TRIGGER audit_events_append_only;
+
+ALTER TYPE audit_event_kind RENAME TO audit_event_kind_old;
+
+CREATE TYPE audit_event_kind AS ENUM (
+    'login',
+    'login_failed',
+    'password_changed',
+    'role_granted',
+    'role_revoked',
+    'forbidden',
+    'account_deleted'
+);
+
+ALTER TABLE audit_events ALTER COLUMN kind TYPE audit_event_kind USING kind::text::audit_event_kind;
+
+DROP TYPE audit_event_kind_old;
\ No newline at end of file
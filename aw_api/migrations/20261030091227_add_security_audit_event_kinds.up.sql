@@ -0,0 +1,3 @@
+-- Add up migration script here
+ALTER TYPE audit_event_kind ADD VALUE 'account_locked';
+ALTER TYPE audit_event_kind ADD VALUE 'passkey_counter_regressed';
\ No newline at end of file
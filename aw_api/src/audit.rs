@@ -0,0 +1,79 @@
+use actix_web::{dev::Payload, web, FromRequest, HttpRequest};
+use futures::future::{ready, Ready};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use sqlx::PgPool;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::auth::SessionClient;
+use crate::errors::Error as AppError;
+
+/// What happened, stored as the `audit_event_kind` type
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema, sqlx::Type)]
+#[sqlx(type_name = "audit_event_kind", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum AuditEventKind {
+    Login,
+    LoginFailed,
+    PasswordChanged,
+    RoleGranted,
+    RoleRevoked,
+    Forbidden,
+    AccountDeleted,
+    // Too many failed logins for an account or an address
+    AccountLocked,
+    // A passkey signed with a counter that did not move forward, as a cloned authenticator would
+    PasskeyCounterRegressed,
+}
+
+/// Records security relevant events into the append-only `audit_events` table
+///
+/// Taken as an extractor, it remembers where the request comes from
+pub struct AuditContext {
+    client: SessionClient,
+    pool: PgPool,
+}
+
+impl AuditContext {
+    pub fn new(req: &HttpRequest, pool: &PgPool) -> Self {
+        AuditContext {
+            client: SessionClient::from_request(req),
+            pool: pool.clone(),
+        }
+    }
+
+    /// Append an event, `actor_id` is the user who did it when known
+    ///
+    /// A failure to write is logged rather than failing the request it is about
+    pub async fn record(&self, kind: AuditEventKind, actor_id: Option<Uuid>, details: JsonValue) {
+        let result = sqlx::query(r#"
+            INSERT INTO audit_events (kind, actor_id, ip_address, user_agent, details)
+            VALUES ($1, $2, $3, $4, $5)
+        "#)
+        .bind(kind)
+        .bind(actor_id)
+        .bind(&self.client.ip_address)
+        .bind(&self.client.user_agent)
+        .bind(&details)
+        .execute(&self.pool)
+        .await;
+
+        if let Err(err) = result {
+            eprintln!("Audit Error: failed to record {:?} for {:?}: {:?}", kind, actor_id, err);
+        }
+    }
+}
+
+impl FromRequest for AuditContext {
+    type Error = AppError;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(
+            req.app_data::<web::Data<PgPool>>()
+                .map(|pool| AuditContext::new(req, pool.get_ref()))
+                .ok_or(AppError::InternalServerError),
+        )
+    }
+}
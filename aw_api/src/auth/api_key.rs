@@ -0,0 +1,162 @@
+use chrono::NaiveDateTime;
+use rand::{rngs::OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use sqlx::postgres::{PgHasArrayType, PgTypeInfo};
+use sqlx::PgPool;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::errors::Error as AppError;
+
+use super::{generate_opaque_token, hash_opaque_token, Role};
+
+/// Every API key starts with this, so leaked keys are easy to spot in logs and by secret scanners
+pub const API_KEY_PREFIX: &str = "aw_";
+
+/// What an API key is allowed to do, stored as the `api_key_scope` type
+///
+/// Login sessions are not limited by scopes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema, sqlx::Type)]
+#[sqlx(type_name = "api_key_scope")]
+pub enum ApiScope {
+    #[sqlx(rename = "articles:read")]
+    #[serde(rename = "articles:read")]
+    ArticlesRead,
+    #[sqlx(rename = "articles:write")]
+    #[serde(rename = "articles:write")]
+    ArticlesWrite,
+    #[sqlx(rename = "comments:write")]
+    #[serde(rename = "comments:write")]
+    CommentsWrite,
+    #[sqlx(rename = "profiles:write")]
+    #[serde(rename = "profiles:write")]
+    ProfilesWrite,
+}
+
+impl ApiScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ApiScope::ArticlesRead => "articles:read",
+            ApiScope::ArticlesWrite => "articles:write",
+            ApiScope::CommentsWrite => "comments:write",
+            ApiScope::ProfilesWrite => "profiles:write",
+        }
+    }
+}
+
+impl PgHasArrayType for ApiScope {
+    fn array_type_info() -> PgTypeInfo {
+        PgTypeInfo::with_name("_api_key_scope")
+    }
+}
+
+/// A freshly created key, `key` is only ever shown in this response
+pub struct NewApiKey {
+    pub id: Uuid,
+    pub prefix: String,
+    pub expires_at: Option<NaiveDateTime>,
+    pub key: String,
+}
+
+/// The owner of a valid API key, see `authenticate_api_key`
+pub struct ApiKeyOwner {
+    pub key_id: Uuid,
+    pub user_id: Uuid,
+    pub username: String,
+    pub roles: Vec<Role>,
+    pub email_verified: bool,
+    pub scopes: Vec<ApiScope>,
+}
+
+/// Create an API key for the user, it never expires when `expires_in_days` is `None`
+///
+/// The key looks like `aw_<prefix>_<secret>`, the prefix is stored as is to find the key and tell
+/// keys apart in the list while only the hash of the whole key is stored
+pub async fn create_api_key(
+    user_id: Uuid,
+    name: &str,
+    scopes: &[ApiScope],
+    expires_in_days: Option<i32>,
+    pool: &PgPool,
+) -> Result<NewApiKey, AppError> {
+    let mut prefix_bytes = [0u8; 4];
+    OsRng.fill_bytes(&mut prefix_bytes);
+
+    let prefix = hex::encode(prefix_bytes);
+    let key = format!("{}{}_{}", API_KEY_PREFIX, prefix, generate_opaque_token());
+
+    let (id, expires_at): (Uuid, Option<NaiveDateTime>) = sqlx::query_as(r#"
+        INSERT INTO api_keys (user_id, name, prefix, key_hash, scopes, expires_at)
+        VALUES ($1, $2, $3, $4, $5, CURRENT_TIMESTAMP + make_interval(days => $6))
+        RETURNING id, expires_at
+    "#)
+    .bind(user_id)
+    .bind(name)
+    .bind(&prefix)
+    .bind(hash_opaque_token(&key))
+    .bind(scopes)
+    .bind(expires_in_days)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(NewApiKey { id, prefix, expires_at, key })
+}
+
+/// Resolve an `X-Api-Key` header to its owner and record the use
+///
+/// Unknown, revoked and expired keys are all rejected with the same 401
+pub async fn authenticate_api_key(key: &str, pool: &PgPool) -> Result<ApiKeyOwner, AppError> {
+    let invalid_key = || AppError::Unauthorized(serde_json::json!({"error": "Invalid or expired API key"}));
+
+    let prefix = key
+        .strip_prefix(API_KEY_PREFIX)
+        .and_then(|rest| rest.split_once('_'))
+        .map(|(prefix, _)| prefix)
+        .ok_or_else(invalid_key)?;
+
+    let owner = sqlx::query_as::<_, (Uuid, Uuid, String, Vec<Role>, bool, Vec<ApiScope>)>(r#"
+        UPDATE api_keys SET last_used_at = CURRENT_TIMESTAMP
+        FROM users
+        WHERE api_keys.prefix = $1
+            AND api_keys.key_hash = $2
+            AND api_keys.revoked_at IS NULL
+            AND (api_keys.expires_at IS NULL OR api_keys.expires_at > CURRENT_TIMESTAMP)
+            AND users.id = api_keys.user_id
+        RETURNING
+            api_keys.id,
+            users.id,
+            users.username,
+            ARRAY(SELECT role FROM user_roles WHERE user_roles.user_id = users.id),
+            users.email_verified_at IS NOT NULL,
+            api_keys.scopes
+    "#)
+    .bind(prefix)
+    .bind(hash_opaque_token(key))
+    .fetch_optional(pool)
+    .await?;
+
+    match owner {
+        Some((key_id, user_id, username, roles, email_verified, scopes)) => Ok(ApiKeyOwner {
+            key_id,
+            user_id,
+            username,
+            roles,
+            email_verified,
+            scopes,
+        }),
+        None => Err(invalid_key()),
+    }
+}
+
+/// Revoke an API key of the user, returns `false` when there is no such active key
+pub async fn revoke_api_key(id: Uuid, user_id: Uuid, pool: &PgPool) -> Result<bool, AppError> {
+    let result = sqlx::query(
+        "UPDATE api_keys SET revoked_at = CURRENT_TIMESTAMP WHERE id = $1 AND user_id = $2 AND revoked_at IS NULL"
+    )
+    .bind(id)
+    .bind(user_id)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
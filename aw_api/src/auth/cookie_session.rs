@@ -0,0 +1,120 @@
+use actix_web::cookie::{time::Duration, Cookie, SameSite};
+use actix_web::{http::Method, HttpRequest};
+use uuid::Uuid;
+
+use crate::errors::Error as AppError;
+use crate::session_store::SessionStore;
+use crate::settings::SessionSettings;
+
+use super::{generate_opaque_token, hash_opaque_token};
+
+/// The cookies set on a successful login in cookie mode
+///
+/// `csrf_token` is also returned in the body so the frontend does not have to read it from the cookie
+pub struct CookieSession {
+    pub session_cookie: Cookie<'static>,
+    pub csrf_cookie: Cookie<'static>,
+    pub csrf_token: String,
+}
+
+/// Open a cookie session for a login already recorded in `sessions`
+pub async fn start_cookie_session(
+    session_id: Uuid,
+    store: &dyn SessionStore,
+    settings: &SessionSettings,
+) -> Result<CookieSession, AppError> {
+    let token = generate_opaque_token();
+    let csrf_token = generate_opaque_token();
+
+    store.insert(&hash_opaque_token(&token), session_id, settings.lifetime_hours).await?;
+
+    Ok(CookieSession {
+        session_cookie: build_cookie(settings.cookie_name.clone(), token, true, settings),
+        csrf_cookie: build_cookie(settings.csrf_cookie_name.clone(), csrf_token.clone(), false, settings),
+        csrf_token,
+    })
+}
+
+/// The session behind the cookie of the request, `None` when there is no cookie
+///
+/// Requests that change something must also pass `check_csrf`
+pub async fn cookie_session_id(
+    req: &HttpRequest,
+    store: &dyn SessionStore,
+    settings: &SessionSettings,
+) -> Result<Option<Uuid>, AppError> {
+    let cookie = match req.cookie(&settings.cookie_name) {
+        Some(cookie) => cookie,
+        None => return Ok(None),
+    };
+
+    check_csrf(req, settings)?;
+
+    match store.get(&hash_opaque_token(cookie.value())).await? {
+        Some(session_id) => Ok(Some(session_id)),
+        None => Err(AppError::Unauthorized(serde_json::json!({
+            "error": "Session has expired",
+        }))),
+    }
+}
+
+/// End the cookie session of the request and return the session to revoke
+pub async fn end_cookie_session(
+    req: &HttpRequest,
+    store: &dyn SessionStore,
+    settings: &SessionSettings,
+) -> Result<Option<Uuid>, AppError> {
+    let session_id = cookie_session_id(req, store, settings).await?;
+
+    if let Some(cookie) = req.cookie(&settings.cookie_name) {
+        store.remove(&hash_opaque_token(cookie.value())).await?;
+    }
+
+    Ok(session_id)
+}
+
+/// Cookies that clear the session and CSRF cookies in the browser
+pub fn removal_cookies(settings: &SessionSettings) -> [Cookie<'static>; 2] {
+    let mut session_cookie = build_cookie(settings.cookie_name.clone(), String::new(), true, settings);
+    let mut csrf_cookie = build_cookie(settings.csrf_cookie_name.clone(), String::new(), false, settings);
+    session_cookie.make_removal();
+    csrf_cookie.make_removal();
+
+    [session_cookie, csrf_cookie]
+}
+
+/// Double-submit check, the CSRF header has to repeat the CSRF cookie on every request that changes something
+///
+/// Another site can make the browser send the cookies but cannot read them to fill in the header
+pub fn check_csrf(req: &HttpRequest, settings: &SessionSettings) -> Result<(), AppError> {
+    if matches!(*req.method(), Method::GET | Method::HEAD | Method::OPTIONS) {
+        return Ok(());
+    }
+
+    let cookie = req.cookie(&settings.csrf_cookie_name);
+    let header = req
+        .headers()
+        .get(settings.csrf_header_name.as_str())
+        .and_then(|value| value.to_str().ok());
+
+    match (cookie, header) {
+        (Some(cookie), Some(header)) if !header.is_empty() && constant_time_eq(cookie.value(), header) => Ok(()),
+        _ => Err(AppError::Forbidden(serde_json::json!({
+            "error": "Missing or invalid CSRF token",
+        }))),
+    }
+}
+
+fn build_cookie(name: String, value: String, http_only: bool, settings: &SessionSettings) -> Cookie<'static> {
+    Cookie::build(name, value)
+        .path("/")
+        .http_only(http_only)
+        .secure(settings.cookie_secure)
+        .same_site(SameSite::Lax)
+        .max_age(Duration::hours(settings.lifetime_hours.into()))
+        .finish()
+}
+
+fn constant_time_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len() && a.bytes().zip(b.bytes()).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
@@ -0,0 +1,235 @@
+use actix_web::{dev::Payload, http::header, web, FromRequest, HttpRequest};
+use futures::future::LocalBoxFuture;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::errors::Error as AppError;
+use crate::session_store::SessionStore;
+use crate::settings::{ApplicationSettings, AuthMode, SessionSettings};
+
+use super::{authenticate_api_key, cookie_session_id, decode_token, ApiScope, Role};
+
+/// Header carrying a personal API key, see `create_api_key`
+pub const API_KEY_HEADER: &str = "X-Api-Key";
+
+/// The user making the request, resolved from the `Authorization: Bearer <token>` header,
+/// or the session cookie in cookie mode, or from an API key in the `X-Api-Key` header
+///
+/// Responds with 401 when all are missing or the credential is invalid, and with 403 when a
+/// cookie authenticated request that changes something fails the CSRF check
+#[derive(Debug, Clone)]
+pub struct AuthUser {
+    pub id: Uuid,
+    pub username: String,
+    pub credential: Credential,
+    pub roles: Vec<Role>,
+    pub email_verified: bool,
+}
+
+/// How the user authenticated
+#[derive(Debug, Clone)]
+pub enum Credential {
+    Session(Uuid),
+    ApiKey { id: Uuid, scopes: Vec<ApiScope> },
+}
+
+impl AuthUser {
+    /// The login session behind the access token or cookie, `None` for API keys
+    pub fn session_id(&self) -> Option<Uuid> {
+        match self.credential {
+            Credential::Session(session_id) => Some(session_id),
+            Credential::ApiKey { .. } => None,
+        }
+    }
+
+    /// Responds with 403 when an API key without the scope is used, sessions can do everything
+    pub fn require_scope(&self, scope: ApiScope) -> Result<(), AppError> {
+        match &self.credential {
+            Credential::ApiKey { scopes, .. } if !scopes.contains(&scope) => Err(AppError::Forbidden(serde_json::json!({
+                "error": format!("API key is missing the {} scope", scope.as_str()),
+            }))),
+            _ => Ok(()),
+        }
+    }
+
+    /// Responds with 403 for API keys, for account management like passwords, sessions and keys themselves
+    pub fn require_session(&self) -> Result<Uuid, AppError> {
+        self.session_id().ok_or_else(|| AppError::Forbidden(serde_json::json!({
+            "error": "API keys cannot be used for this endpoint",
+        })))
+    }
+}
+
+/// Same as `AuthUser` but for routes that can also be accessed anonymously
+///
+/// A missing credential gives `None`, an invalid one is still rejected with 401
+#[derive(Debug, Clone)]
+pub struct MaybeAuthUser(pub Option<AuthUser>);
+
+impl MaybeAuthUser {
+    pub fn id(&self) -> Option<Uuid> {
+        self.0.as_ref().map(|user| user.id)
+    }
+}
+
+impl FromRequest for AuthUser {
+    type Error = AppError;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let req = req.clone();
+
+        Box::pin(async move {
+            authenticate_request(&req).await?.ok_or_else(|| AppError::Unauthorized(serde_json::json!({
+                "error": "Missing authorization token",
+            })))
+        })
+    }
+}
+
+impl FromRequest for MaybeAuthUser {
+    type Error = AppError;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let req = req.clone();
+
+        Box::pin(async move { Ok(MaybeAuthUser(authenticate_request(&req).await?)) })
+    }
+}
+
+/// API keys are always accepted, then depending on `SessionSettings::mode` either the bearer token
+/// or the session cookie, `None` when the request carries none of them
+async fn authenticate_request(req: &HttpRequest) -> Result<Option<AuthUser>, AppError> {
+    if let Some(key) = api_key(req)? {
+        return Ok(Some(authenticate_with_api_key(req, &key).await?));
+    }
+
+    let session_settings = req
+        .app_data::<web::Data<SessionSettings>>()
+        .ok_or(AppError::InternalServerError)?;
+
+    match session_settings.mode {
+        AuthMode::Bearer => match bearer_token(req)? {
+            Some(token) => Ok(Some(authenticate(req, &token).await?)),
+            None => Ok(None),
+        },
+        AuthMode::Cookie => {
+            let store = req
+                .app_data::<web::Data<dyn SessionStore>>()
+                .ok_or(AppError::InternalServerError)?;
+
+            match cookie_session_id(req, store.get_ref(), session_settings).await? {
+                Some(session_id) => Ok(Some(session_user(req, session_id, None).await?)),
+                None => Ok(None),
+            }
+        }
+    }
+}
+
+fn bearer_token(req: &HttpRequest) -> Result<Option<String>, AppError> {
+    let header_value = match req.headers().get(header::AUTHORIZATION) {
+        Some(value) => value,
+        None => return Ok(None),
+    };
+
+    let token = header_value
+        .to_str()
+        .ok()
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(|token| token.trim().to_owned())
+        .filter(|token| !token.is_empty())
+        .ok_or_else(|| AppError::Unauthorized(serde_json::json!({
+            "error": "Authorization header must use the Bearer scheme",
+        })))?;
+
+    Ok(Some(token))
+}
+
+fn api_key(req: &HttpRequest) -> Result<Option<String>, AppError> {
+    let header_value = match req.headers().get(API_KEY_HEADER) {
+        Some(value) => value,
+        None => return Ok(None),
+    };
+
+    let key = header_value
+        .to_str()
+        .ok()
+        .map(|key| key.trim().to_owned())
+        .filter(|key| !key.is_empty())
+        .ok_or_else(|| AppError::Unauthorized(serde_json::json!({
+            "error": "Invalid or expired API key",
+        })))?;
+
+    Ok(Some(key))
+}
+
+async fn authenticate_with_api_key(req: &HttpRequest, key: &str) -> Result<AuthUser, AppError> {
+    let pool = req
+        .app_data::<web::Data<PgPool>>()
+        .ok_or(AppError::InternalServerError)?;
+
+    let owner = authenticate_api_key(key, pool.get_ref()).await?;
+
+    Ok(AuthUser {
+        id: owner.user_id,
+        username: owner.username,
+        credential: Credential::ApiKey { id: owner.key_id, scopes: owner.scopes },
+        roles: owner.roles,
+        email_verified: owner.email_verified,
+    })
+}
+
+async fn authenticate(req: &HttpRequest, token: &str) -> Result<AuthUser, AppError> {
+    let settings = req
+        .app_data::<web::Data<ApplicationSettings>>()
+        .ok_or(AppError::InternalServerError)?;
+
+    let claims = decode_token(token, settings)?;
+    let (user_id, session_id) = match (Uuid::parse_str(&claims.sub), Uuid::parse_str(&claims.sid)) {
+        (Ok(user_id), Ok(session_id)) => (user_id, session_id),
+        _ => return Err(AppError::Unauthorized(serde_json::json!({"error": "Invalid or expired token"}))),
+    };
+
+    session_user(req, session_id, Some(user_id)).await
+}
+
+/// The user of an active session, `user_id` is checked against the session when the credential names one
+async fn session_user(req: &HttpRequest, session_id: Uuid, user_id: Option<Uuid>) -> Result<AuthUser, AppError> {
+    let pool = req
+        .app_data::<web::Data<PgPool>>()
+        .ok_or(AppError::InternalServerError)?;
+
+    // The credential may outlive its session or the account itself, so both are checked
+    // while recording the activity for the session list
+    let user = sqlx::query_as::<_, (Uuid, String, Vec<Role>, bool)>(r#"
+        UPDATE sessions SET last_seen_at = CURRENT_TIMESTAMP
+        FROM users
+        WHERE sessions.id = $1
+            AND sessions.user_id = COALESCE($2, sessions.user_id)
+            AND sessions.revoked_at IS NULL
+            AND users.id = sessions.user_id
+        RETURNING
+            users.id,
+            users.username,
+            ARRAY(SELECT role FROM user_roles WHERE user_roles.user_id = users.id),
+            users.email_verified_at IS NOT NULL
+    "#)
+    .bind(session_id)
+    .bind(user_id)
+    .fetch_optional(pool.get_ref())
+    .await?;
+
+    match user {
+        Some((id, username, roles, email_verified)) => Ok(AuthUser {
+            id,
+            username,
+            credential: Credential::Session(session_id),
+            roles,
+            email_verified,
+        }),
+        None => Err(AppError::Unauthorized(serde_json::json!({
+            "error": "Session has been revoked",
+        }))),
+    }
+}
@@ -0,0 +1,134 @@
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::audit::{AuditContext, AuditEventKind};
+use crate::errors::Error as AppError;
+use crate::settings::ApplicationSettings;
+
+/// The counters a login attempt is tracked under, one for the account and one for the client address
+pub struct LoginThrottleKeys {
+    account: String,
+    ip_address: Option<String>,
+}
+
+impl LoginThrottleKeys {
+    pub fn new(email: &str, ip_address: Option<&str>) -> Self {
+        LoginThrottleKeys {
+            account: format!("email:{}", email.to_lowercase()),
+            ip_address: ip_address.map(|ip_address| format!("ip:{}", ip_address)),
+        }
+    }
+
+    /// Keys of a password check by a signed in user, e.g. the current password before changing it
+    ///
+    /// Counted per account only, a stolen token must not allow more guesses than the login does
+    pub fn for_user(user_id: Uuid) -> Self {
+        LoginThrottleKeys {
+            account: format!("user:{}", user_id),
+            ip_address: None,
+        }
+    }
+
+    fn all(&self) -> Vec<String> {
+        std::iter::once(self.account.clone())
+            .chain(self.ip_address.clone())
+            .collect()
+    }
+}
+
+/// Reject the attempt with 429 while the account or the address is backing off or locked out
+///
+/// Runs before the password is verified so a locked out client cannot keep the server hashing
+pub async fn check_login_throttle(keys: &LoginThrottleKeys, pool: &PgPool) -> Result<(), AppError> {
+    let (retry_after,): (Option<i64>,) = sqlx::query_as(r#"
+        SELECT CEIL(EXTRACT(EPOCH FROM MAX(locked_until) - CURRENT_TIMESTAMP))::BIGINT
+        FROM login_throttles
+        WHERE key = ANY($1) AND locked_until > CURRENT_TIMESTAMP
+    "#)
+    .bind(keys.all())
+    .fetch_one(pool)
+    .await?;
+
+    match retry_after {
+        Some(retry_after) => Err(AppError::TooManyRequests(
+            serde_json::json!({"error": "Too many failed login attempts, please try again later"}),
+            retry_after.max(1) as u64,
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Count a failed attempt and make the client wait before the next one
+///
+/// The wait doubles with every failure until the limit is reached and the key gets locked out.
+/// Failures older than the lockout period are forgotten, a lockout is recorded in the audit log against `user_id`
+pub async fn record_login_failure(
+    keys: &LoginThrottleKeys,
+    user_id: Option<Uuid>,
+    audit: &AuditContext,
+    pool: &PgPool,
+    settings: &ApplicationSettings,
+) -> Result<(), AppError> {
+    let mut limits = vec![(&keys.account, settings.login_max_failures)];
+    if let Some(ip_address) = &keys.ip_address {
+        limits.push((ip_address, settings.login_max_failures_per_ip));
+    }
+
+    for (key, max_failures) in limits {
+        let (failures,): (i32,) = sqlx::query_as(r#"
+            INSERT INTO login_throttles (key, failures) VALUES ($1, 1)
+            ON CONFLICT (key) DO UPDATE SET
+                failures = CASE
+                    WHEN login_throttles.last_failure_at < CURRENT_TIMESTAMP - make_interval(mins => $2) THEN 1
+                    ELSE login_throttles.failures + 1
+                END,
+                last_failure_at = CURRENT_TIMESTAMP
+            RETURNING failures
+        "#)
+        .bind(key)
+        .bind(settings.login_lockout_minutes)
+        .fetch_one(pool)
+        .await?;
+
+        let lockout_seconds = settings.login_lockout_minutes * 60;
+        let wait_seconds = if failures >= max_failures {
+            eprintln!(
+                "Security Event: {} locked out for {} minutes after {} failed logins",
+                key, settings.login_lockout_minutes, failures
+            );
+
+            audit
+                .record(AuditEventKind::AccountLocked, user_id, serde_json::json!({
+                    "key": key,
+                    "failures": failures,
+                    "lockout_minutes": settings.login_lockout_minutes,
+                }))
+                .await;
+
+            lockout_seconds
+        } else {
+            let backoff = settings.login_backoff_base_seconds.saturating_mul(1 << (failures - 1).min(30));
+            backoff.min(lockout_seconds)
+        };
+
+        sqlx::query("UPDATE login_throttles SET locked_until = CURRENT_TIMESTAMP + make_interval(secs => $2) WHERE key = $1")
+            .bind(key)
+            .bind(wait_seconds as f64)
+            .execute(pool)
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// Forget the failures of the account after a successful login
+///
+/// The address keeps its count, a valid account of their own must not let an attacker reset it
+pub async fn clear_login_failures(keys: &LoginThrottleKeys, pool: &PgPool) -> Result<(), AppError> {
+    sqlx::query("DELETE FROM login_throttles WHERE key = $1")
+        .bind(&keys.account)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
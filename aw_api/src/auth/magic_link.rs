@@ -0,0 +1,121 @@
+use chrono::Duration;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::errors::Error as AppError;
+use crate::mailer::{Email, Mailer};
+use crate::settings::ApplicationSettings;
+
+use super::{decode_email_token, generate_email_token, hash_opaque_token, MAGIC_LINK_PURPOSE};
+
+/// Mail a one time login token to the address, if it belongs to a user
+///
+/// Unknown addresses are ignored like for password resets, known ones get at most
+/// `magic_link_max_per_hour` links before answering 429
+pub async fn send_magic_link(
+    email: &str,
+    pool: &PgPool,
+    mailer: &dyn Mailer,
+    settings: &ApplicationSettings,
+) -> Result<(), AppError> {
+    let user = sqlx::query_as::<_, (Uuid,)>("SELECT id FROM users WHERE email = $1")
+        .bind(email)
+        .fetch_optional(pool)
+        .await?;
+
+    let user_id = match user {
+        Some((user_id,)) => user_id,
+        None => return Ok(()),
+    };
+
+    // Seconds until the oldest link of the last hour leaves the window, when the limit is reached
+    let (retry_after,): (Option<i64>,) = sqlx::query_as(r#"
+        SELECT CEIL(EXTRACT(EPOCH FROM MIN(created_at) + INTERVAL '1 hour' - CURRENT_TIMESTAMP))::BIGINT
+        FROM magic_links
+        WHERE user_id = $1 AND created_at > CURRENT_TIMESTAMP - INTERVAL '1 hour'
+        HAVING COUNT(*) >= $2
+    "#)
+    .bind(user_id)
+    .bind(settings.magic_link_max_per_hour)
+    .fetch_optional(pool)
+    .await?
+    .unwrap_or((None,));
+
+    if let Some(retry_after) = retry_after {
+        return Err(AppError::TooManyRequests(
+            serde_json::json!({"error": "Too many login links requested, please try again later"}),
+            retry_after.max(1) as u64,
+        ));
+    }
+
+    let lifetime = Duration::minutes(settings.magic_link_expiration_minutes.into());
+    let token = generate_email_token(&user_id, email, MAGIC_LINK_PURPOSE, lifetime, settings)?;
+
+    sqlx::query(r#"
+        INSERT INTO magic_links (user_id, token_hash, expires_at)
+        VALUES ($1, $2, CURRENT_TIMESTAMP + make_interval(mins => $3))
+    "#)
+    .bind(user_id)
+    .bind(hash_opaque_token(&token))
+    .bind(settings.magic_link_expiration_minutes)
+    .execute(pool)
+    .await?;
+
+    mailer
+        .send(Email {
+            to: email.to_owned(),
+            subject: "Your login link".to_owned(),
+            body: format!(
+                "Send the token below to {}/api/v1/users/magic-link/consume to login without your password:\n\n\
+                token={}\n\nThe token works once and expires in {} minutes, you can ignore this email if you did not ask for it.",
+                settings.base_url, token, settings.magic_link_expiration_minutes
+            ),
+        })
+        .await
+}
+
+/// Spend a login token and return the user it logs in
+///
+/// The token has to still match the address of the user, so it proves they own that mailbox
+/// and the address is marked as verified on the way
+pub async fn redeem_magic_link(
+    token: &str,
+    pool: &PgPool,
+    settings: &ApplicationSettings,
+) -> Result<(Uuid, String), AppError> {
+    let invalid = || AppError::Unauthorized(serde_json::json!({"error": "Invalid or expired login link"}));
+
+    let claims = decode_email_token(token, MAGIC_LINK_PURPOSE, settings).map_err(|_| invalid())?;
+    let user_id = Uuid::parse_str(&claims.sub).map_err(|_| invalid())?;
+
+    let mut tx = pool.begin().await?;
+
+    let spent = sqlx::query(r#"
+        UPDATE magic_links SET used_at = CURRENT_TIMESTAMP
+        WHERE token_hash = $1 AND user_id = $2 AND used_at IS NULL AND expires_at > CURRENT_TIMESTAMP
+    "#)
+    .bind(hash_opaque_token(token))
+    .bind(user_id)
+    .execute(&mut *tx)
+    .await?
+    .rows_affected() > 0;
+
+    if !spent {
+        return Err(invalid());
+    }
+
+    let (username,): (String,) = sqlx::query_as(r#"
+        UPDATE users SET email_verified_at = COALESCE(email_verified_at, CURRENT_TIMESTAMP)
+        WHERE id = $1 AND email = $2
+        RETURNING username
+    "#)
+    .bind(user_id)
+    .bind(&claims.email)
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or_else(invalid)?;
+
+    tx.commit().await?;
+
+    Ok((user_id, username))
+}
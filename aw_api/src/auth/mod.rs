@@ -0,0 +1,29 @@
+mod token;
+mod opaque_token;
+mod refresh;
+mod session;
+mod extractor;
+mod role;
+mod verification;
+mod password_reset;
+mod login_throttle;
+mod totp;
+mod magic_link;
+mod webauthn;
+mod api_key;
+mod cookie_session;
+
+pub use token::*;
+pub use opaque_token::*;
+pub use refresh::*;
+pub use session::*;
+pub use extractor::*;
+pub use role::*;
+pub use verification::*;
+pub use password_reset::*;
+pub use login_throttle::*;
+pub use totp::*;
+pub use magic_link::*;
+pub use webauthn::*;
+pub use api_key::*;
+pub use cookie_session::*;
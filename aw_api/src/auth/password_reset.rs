@@ -0,0 +1,94 @@
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::errors::Error as AppError;
+use crate::mailer::{Email, Mailer};
+use crate::settings::ApplicationSettings;
+
+use super::{generate_opaque_token, hash_opaque_token, revoke_all_sessions};
+
+/// Mail a single use reset token to the address, if it belongs to a user
+///
+/// Unknown addresses are ignored so the endpoint does not tell which emails are registered
+pub async fn request_password_reset(
+    email: &str,
+    pool: &PgPool,
+    mailer: &dyn Mailer,
+    settings: &ApplicationSettings,
+) -> Result<(), AppError> {
+    let user = sqlx::query_as::<_, (Uuid,)>("SELECT id FROM users WHERE email = $1")
+        .bind(email)
+        .fetch_optional(pool)
+        .await?;
+
+    let user_id = match user {
+        Some((user_id,)) => user_id,
+        None => return Ok(()),
+    };
+
+    let token = generate_opaque_token();
+
+    sqlx::query(r#"
+        INSERT INTO password_reset_tokens (user_id, token_hash, expires_at)
+        VALUES ($1, $2, CURRENT_TIMESTAMP + make_interval(mins => $3))
+    "#)
+    .bind(user_id)
+    .bind(hash_opaque_token(&token))
+    .bind(settings.password_reset_expiration_minutes)
+    .execute(pool)
+    .await?;
+
+    mailer
+        .send(Email {
+            to: email.to_owned(),
+            subject: "Reset your password".to_owned(),
+            body: format!(
+                "Someone asked to reset the password of your account. If it was you, send the token below \
+                together with your new password to {}/api/v1/users/password/reset:\n\ntoken={}\n\n\
+                The token expires in {} minutes, you can ignore this email otherwise.",
+                settings.base_url, token, settings.password_reset_expiration_minutes
+            ),
+        })
+        .await
+}
+
+/// Set a new password with a reset token, log the user out everywhere and revoke the API keys
+///
+/// Using a token spends every other pending token of the user as well
+pub async fn reset_password(token: &str, password_hash: &str, pool: &PgPool) -> Result<Uuid, AppError> {
+    let mut tx = pool.begin().await?;
+
+    let user_id = sqlx::query_as::<_, (Uuid,)>(r#"
+        SELECT user_id FROM password_reset_tokens
+        WHERE token_hash = $1 AND used_at IS NULL AND expires_at > CURRENT_TIMESTAMP
+        FOR UPDATE
+    "#)
+    .bind(hash_opaque_token(token))
+    .fetch_optional(&mut *tx)
+    .await?
+    .map(|(user_id,)| user_id)
+    .ok_or_else(|| AppError::BadRequest(serde_json::json!({"error": "Invalid or expired token"})))?;
+
+    sqlx::query("UPDATE password_reset_tokens SET used_at = CURRENT_TIMESTAMP WHERE user_id = $1 AND used_at IS NULL")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+
+    sqlx::query("UPDATE users SET password = $1 WHERE id = $2")
+        .bind(password_hash)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+
+    revoke_all_sessions(user_id, None, &mut tx).await?;
+
+    // Keys made with the old password would outlive the reset otherwise
+    sqlx::query("UPDATE api_keys SET revoked_at = CURRENT_TIMESTAMP WHERE user_id = $1 AND revoked_at IS NULL")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+
+    Ok(user_id)
+}
@@ -0,0 +1,149 @@
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+use crate::errors::Error as AppError;
+use crate::settings::ApplicationSettings;
+
+use super::{generate_opaque_token, hash_opaque_token};
+
+/// A refresh token that was rotated, together with the user it belongs to
+pub struct RotatedRefreshToken {
+    pub user_id: Uuid,
+    pub session_id: Uuid,
+    pub username: String,
+    pub refresh_token: String,
+}
+
+/// Start the token family of a new session, used on login
+///
+/// The family id is the id of the session so revoking one revokes the other
+pub async fn issue_refresh_token(
+    user_id: Uuid,
+    session_id: Uuid,
+    pool: &PgPool,
+    settings: &ApplicationSettings,
+) -> Result<String, AppError> {
+    let mut tx = pool.begin().await?;
+    let token = insert_refresh_token(user_id, session_id, &mut tx, settings).await?;
+    tx.commit().await?;
+
+    Ok(token)
+}
+
+/// Exchange a refresh token for a new one of the same family
+///
+/// Presenting a token that was already rotated means it leaked, so the whole family gets revoked
+pub async fn rotate_refresh_token(
+    token: &str,
+    pool: &PgPool,
+    settings: &ApplicationSettings,
+) -> Result<RotatedRefreshToken, AppError> {
+    let mut tx = pool.begin().await?;
+
+    let stored = sqlx::query_as::<_, (Uuid, Uuid, Uuid, String, bool, bool)>(r#"
+        SELECT
+            refresh_tokens.id,
+            refresh_tokens.user_id,
+            refresh_tokens.family_id,
+            users.username,
+            refresh_tokens.revoked_at IS NOT NULL,
+            refresh_tokens.expires_at <= CURRENT_TIMESTAMP OR sessions.revoked_at IS NOT NULL
+        FROM
+            refresh_tokens
+        INNER JOIN
+            users ON users.id = refresh_tokens.user_id
+        INNER JOIN
+            sessions ON sessions.id = refresh_tokens.family_id
+        WHERE
+            refresh_tokens.token_hash = $1
+        FOR UPDATE OF refresh_tokens
+    "#)
+    .bind(hash_opaque_token(token))
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let (token_id, user_id, family_id, username, revoked, expired) = match stored {
+        Some(stored) => stored,
+        None => return Err(invalid_refresh_token()),
+    };
+
+    if revoked {
+        revoke_family(family_id, &mut tx).await?;
+        tx.commit().await?;
+
+        return Err(AppError::Unauthorized(serde_json::json!({
+            "error": "Refresh token reuse detected, please login again",
+        })));
+    }
+
+    if expired {
+        return Err(invalid_refresh_token());
+    }
+
+    sqlx::query("UPDATE refresh_tokens SET revoked_at = CURRENT_TIMESTAMP WHERE id = $1")
+        .bind(token_id)
+        .execute(&mut *tx)
+        .await?;
+
+    let refresh_token = insert_refresh_token(user_id, family_id, &mut tx, settings).await?;
+    tx.commit().await?;
+
+    Ok(RotatedRefreshToken { user_id, session_id: family_id, username, refresh_token })
+}
+
+/// Revoke every token of the family the given refresh token belongs to, and its session
+pub async fn revoke_refresh_token_family(token: &str, pool: &PgPool) -> Result<(), AppError> {
+    let mut tx = pool.begin().await?;
+
+    let family_id = sqlx::query_as::<_, (Uuid,)>("SELECT family_id FROM refresh_tokens WHERE token_hash = $1")
+        .bind(hash_opaque_token(token))
+        .fetch_optional(&mut *tx)
+        .await?
+        .map(|(family_id,)| family_id)
+        .ok_or_else(invalid_refresh_token)?;
+
+    revoke_family(family_id, &mut tx).await?;
+    tx.commit().await?;
+
+    Ok(())
+}
+
+async fn insert_refresh_token(
+    user_id: Uuid,
+    family_id: Uuid,
+    tx: &mut Transaction<'_, Postgres>,
+    settings: &ApplicationSettings,
+) -> Result<String, AppError> {
+    let token = generate_opaque_token();
+
+    sqlx::query(r#"
+        INSERT INTO refresh_tokens (user_id, family_id, token_hash, expires_at)
+        VALUES ($1, $2, $3, CURRENT_TIMESTAMP + make_interval(days => $4))
+    "#)
+    .bind(user_id)
+    .bind(family_id)
+    .bind(hash_opaque_token(&token))
+    .bind(settings.refresh_token_expiration_days)
+    .execute(&mut **tx)
+    .await?;
+
+    Ok(token)
+}
+
+pub(crate) async fn revoke_family(family_id: Uuid, tx: &mut Transaction<'_, Postgres>) -> Result<(), AppError> {
+    sqlx::query("UPDATE refresh_tokens SET revoked_at = CURRENT_TIMESTAMP WHERE family_id = $1 AND revoked_at IS NULL")
+        .bind(family_id)
+        .execute(&mut **tx)
+        .await?;
+
+    sqlx::query("UPDATE sessions SET revoked_at = CURRENT_TIMESTAMP WHERE id = $1 AND revoked_at IS NULL")
+        .bind(family_id)
+        .execute(&mut **tx)
+        .await?;
+
+    Ok(())
+}
+
+fn invalid_refresh_token() -> AppError {
+    AppError::Unauthorized(serde_json::json!({"error": "Invalid or expired refresh token"}))
+}
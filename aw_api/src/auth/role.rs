@@ -0,0 +1,126 @@
+use std::marker::PhantomData;
+use std::ops::Deref;
+
+use actix_web::{dev::Payload, FromRequest, HttpRequest};
+use futures::future::LocalBoxFuture;
+use serde::{Deserialize, Serialize};
+use sqlx::postgres::{PgHasArrayType, PgTypeInfo};
+use utoipa::ToSchema;
+
+use crate::audit::{AuditContext, AuditEventKind};
+use crate::errors::Error as AppError;
+
+use super::AuthUser;
+
+/// Roles on top of a regular user, stored in the `user_roles` table
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema, sqlx::Type)]
+#[sqlx(type_name = "user_role", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    Moderator,
+    Admin,
+}
+
+impl PgHasArrayType for Role {
+    fn array_type_info() -> PgTypeInfo {
+        PgTypeInfo::with_name("_user_role")
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Permission {
+    DeleteAnyArticle,
+    DeleteAnyComment,
+    ManageRoles,
+}
+
+impl Role {
+    pub fn permissions(&self) -> &'static [Permission] {
+        match self {
+            Role::Moderator => &[Permission::DeleteAnyArticle, Permission::DeleteAnyComment],
+            Role::Admin => &[Permission::DeleteAnyArticle, Permission::DeleteAnyComment, Permission::ManageRoles],
+        }
+    }
+
+    /// Admins can do everything a moderator can
+    pub fn includes(&self, other: Role) -> bool {
+        *self == other || *self == Role::Admin
+    }
+}
+
+impl AuthUser {
+    pub fn has_role(&self, role: Role) -> bool {
+        self.roles.iter().any(|own_role| own_role.includes(role))
+    }
+
+    pub fn has_permission(&self, permission: Permission) -> bool {
+        self.roles
+            .iter()
+            .any(|role| role.permissions().contains(&permission))
+    }
+}
+
+pub trait RoleRequirement {
+    const ROLE: Role;
+}
+
+pub struct Admin;
+
+impl RoleRequirement for Admin {
+    const ROLE: Role = Role::Admin;
+}
+
+pub struct Moderator;
+
+impl RoleRequirement for Moderator {
+    const ROLE: Role = Role::Moderator;
+}
+
+/// An `AuthUser` that has the role `R`, e.g. `RequireRole<Admin>`
+///
+/// Responds with 401 like `AuthUser` and with 403 when the role is missing or an API key is used
+pub struct RequireRole<R: RoleRequirement> {
+    pub user: AuthUser,
+    role: PhantomData<R>,
+}
+
+impl<R: RoleRequirement> Deref for RequireRole<R> {
+    type Target = AuthUser;
+
+    fn deref(&self) -> &Self::Target {
+        &self.user
+    }
+}
+
+impl<R: RoleRequirement + 'static> FromRequest for RequireRole<R> {
+    type Error = AppError;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let user = AuthUser::from_request(req, payload);
+        let audit = AuditContext::from_request(req, payload);
+        let path = req.path().to_owned();
+
+        Box::pin(async move {
+            let user = user.await?;
+            user.require_session()?;
+
+            if !user.has_role(R::ROLE) {
+                audit
+                    .await?
+                    .record(AuditEventKind::Forbidden, Some(user.id), serde_json::json!({
+                        "path": path,
+                        "required_role": R::ROLE,
+                        "reason": "missing_role",
+                    }))
+                    .await;
+
+                return Err(AppError::Forbidden(serde_json::json!({
+                    "error": "user does not have the required role",
+                })));
+            }
+
+            Ok(RequireRole { user, role: PhantomData })
+        })
+    }
+}
@@ -0,0 +1,84 @@
+use actix_web::{http::header, HttpRequest};
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+use crate::errors::Error as AppError;
+
+use super::revoke_family;
+
+/// Where a login comes from, shown to the user when listing their sessions
+#[derive(Debug, Clone)]
+pub struct SessionClient {
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+}
+
+impl SessionClient {
+    pub fn from_request(req: &HttpRequest) -> Self {
+        let user_agent = req
+            .headers()
+            .get(header::USER_AGENT)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.to_owned());
+
+        // The peer address, `X-Forwarded-For` and `Forwarded` are set by the client and can be spoofed
+        let ip_address = req
+            .connection_info()
+            .peer_addr()
+            .map(|addr| addr.to_owned());
+
+        SessionClient { user_agent, ip_address }
+    }
+}
+
+pub async fn create_session(user_id: Uuid, client: &SessionClient, pool: &PgPool) -> Result<Uuid, AppError> {
+    let (session_id,): (Uuid,) = sqlx::query_as(
+        "INSERT INTO sessions (user_id, user_agent, ip_address) VALUES ($1, $2, $3) RETURNING id"
+    )
+    .bind(user_id)
+    .bind(&client.user_agent)
+    .bind(&client.ip_address)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(session_id)
+}
+
+/// Revoke a session of the user together with its refresh tokens
+///
+/// Returns `false` when the user has no active session with this id
+pub async fn revoke_session(session_id: Uuid, user_id: Uuid, pool: &PgPool) -> Result<bool, AppError> {
+    let mut tx = pool.begin().await?;
+
+    let active = sqlx::query("SELECT 1 FROM sessions WHERE id = $1 AND user_id = $2 AND revoked_at IS NULL FOR UPDATE")
+        .bind(session_id)
+        .bind(user_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .is_some();
+
+    if active {
+        revoke_family(session_id, &mut tx).await?;
+    }
+
+    tx.commit().await?;
+
+    Ok(active)
+}
+
+/// Revoke every active session of the user together with their refresh tokens, except the `keep` one
+pub(crate) async fn revoke_all_sessions(user_id: Uuid, keep: Option<Uuid>, tx: &mut Transaction<'_, Postgres>) -> Result<(), AppError> {
+    sqlx::query("UPDATE refresh_tokens SET revoked_at = CURRENT_TIMESTAMP WHERE user_id = $1 AND family_id IS DISTINCT FROM $2 AND revoked_at IS NULL")
+        .bind(user_id)
+        .bind(keep)
+        .execute(&mut **tx)
+        .await?;
+
+    sqlx::query("UPDATE sessions SET revoked_at = CURRENT_TIMESTAMP WHERE user_id = $1 AND id IS DISTINCT FROM $2 AND revoked_at IS NULL")
+        .bind(user_id)
+        .bind(keep)
+        .execute(&mut **tx)
+        .await?;
+
+    Ok(())
+}
@@ -0,0 +1,165 @@
+use chrono::{Duration, Utc};
+use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::errors::Error as AppError;
+use crate::settings::ApplicationSettings;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Claims {
+    // user id
+    pub sub: String,
+    pub username: String,
+    // session id
+    pub sid: String,
+    pub iat: i64,
+    pub exp: i64,
+}
+
+/// Sign an access token for the given user and session with the secret from `Settings`
+pub fn generate_token(
+    user_id: &Uuid,
+    username: &str,
+    session_id: &Uuid,
+    settings: &ApplicationSettings,
+) -> Result<String, AppError> {
+    let now = Utc::now();
+    let claims = Claims {
+        sub: user_id.to_string(),
+        username: username.to_owned(),
+        sid: session_id.to_string(),
+        iat: now.timestamp(),
+        exp: (now + Duration::minutes(settings.jwt_expiration_minutes)).timestamp(),
+    };
+
+    encode(
+        &Header::default(),
+        &claims,
+        &EncodingKey::from_secret(settings.jwt_secret.as_bytes()),
+    )
+    .map_err(|_| AppError::InternalServerError)
+}
+
+/// Verify the signature and expiry of an access token and return its claims
+pub fn decode_token(token: &str, settings: &ApplicationSettings) -> Result<Claims, AppError> {
+    decode::<Claims>(
+        token,
+        &DecodingKey::from_secret(settings.jwt_secret.as_bytes()),
+        &Validation::default(),
+    )
+    .map(|data| data.claims)
+    .map_err(|_| AppError::Unauthorized(serde_json::json!({"error": "Invalid or expired token"})))
+}
+
+/// Claims of the single purpose tokens sent by email, bound to the address they were sent to
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EmailClaims {
+    // user id
+    pub sub: String,
+    pub email: String,
+    pub purpose: String,
+    // random id, so two tokens issued in the same second still differ
+    pub jti: String,
+    pub iat: i64,
+    pub exp: i64,
+}
+
+pub const VERIFY_EMAIL_PURPOSE: &str = "verify_email";
+pub const MAGIC_LINK_PURPOSE: &str = "magic_link";
+
+pub fn generate_email_token(
+    user_id: &Uuid,
+    email: &str,
+    purpose: &str,
+    lifetime: Duration,
+    settings: &ApplicationSettings,
+) -> Result<String, AppError> {
+    let now = Utc::now();
+    let claims = EmailClaims {
+        sub: user_id.to_string(),
+        email: email.to_owned(),
+        purpose: purpose.to_owned(),
+        jti: Uuid::new_v4().to_string(),
+        iat: now.timestamp(),
+        exp: (now + lifetime).timestamp(),
+    };
+
+    encode(
+        &Header::default(),
+        &claims,
+        &EncodingKey::from_secret(settings.jwt_secret.as_bytes()),
+    )
+    .map_err(|_| AppError::InternalServerError)
+}
+
+/// Verify an email token and check it was issued for `purpose`
+pub fn decode_email_token(
+    token: &str,
+    purpose: &str,
+    settings: &ApplicationSettings,
+) -> Result<EmailClaims, AppError> {
+    let invalid = || AppError::BadRequest(serde_json::json!({"error": "Invalid or expired token"}));
+
+    let claims = decode::<EmailClaims>(
+        token,
+        &DecodingKey::from_secret(settings.jwt_secret.as_bytes()),
+        &Validation::default(),
+    )
+    .map(|data| data.claims)
+    .map_err(|_| invalid())?;
+
+    if claims.purpose != purpose {
+        return Err(invalid());
+    }
+
+    Ok(claims)
+}
+
+/// Claims of the token handed out by the first step of a login that needs a second factor
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ChallengeClaims {
+    // user id
+    pub sub: String,
+    pub purpose: String,
+    pub iat: i64,
+    pub exp: i64,
+}
+
+pub const TWO_FACTOR_PURPOSE: &str = "two_factor";
+
+pub fn generate_challenge_token(user_id: &Uuid, settings: &ApplicationSettings) -> Result<String, AppError> {
+    let now = Utc::now();
+    let claims = ChallengeClaims {
+        sub: user_id.to_string(),
+        purpose: TWO_FACTOR_PURPOSE.to_owned(),
+        iat: now.timestamp(),
+        exp: (now + Duration::minutes(settings.two_factor_challenge_minutes)).timestamp(),
+    };
+
+    encode(
+        &Header::default(),
+        &claims,
+        &EncodingKey::from_secret(settings.jwt_secret.as_bytes()),
+    )
+    .map_err(|_| AppError::InternalServerError)
+}
+
+/// Verify a challenge token and return the id of the user who passed the first step
+pub fn decode_challenge_token(token: &str, settings: &ApplicationSettings) -> Result<Uuid, AppError> {
+    let invalid = || AppError::Unauthorized(serde_json::json!({"error": "Invalid or expired challenge token"}));
+
+    let claims = decode::<ChallengeClaims>(
+        token,
+        &DecodingKey::from_secret(settings.jwt_secret.as_bytes()),
+        &Validation::default(),
+    )
+    .map(|data| data.claims)
+    .map_err(|_| invalid())?;
+
+    if claims.purpose != TWO_FACTOR_PURPOSE {
+        return Err(invalid());
+    }
+
+    Uuid::parse_str(&claims.sub).map_err(|_| invalid())
+}
@@ -0,0 +1,247 @@
+use chrono::{DateTime, Utc};
+use rand::{rngs::OsRng, RngCore};
+use sqlx::PgPool;
+use totp_rs::{Algorithm, Secret, TOTP};
+use uuid::Uuid;
+
+use crate::errors::Error as AppError;
+use crate::settings::ApplicationSettings;
+use crate::utils::{hash_password, verify_password};
+
+use super::generate_opaque_token;
+
+const TOTP_DIGITS: usize = 6;
+const TOTP_STEP_SECONDS: u64 = 30;
+const RECOVERY_CODE_COUNT: usize = 10;
+
+/// A TOTP secret waiting for its first code, and the URI to show as a QR code
+pub struct TotpEnrollment {
+    pub secret: String,
+    pub otpauth_uri: String,
+}
+
+fn build_totp(secret: &str, account_name: &str, settings: &ApplicationSettings) -> Result<TOTP, AppError> {
+    let secret = Secret::Encoded(secret.to_owned())
+        .to_bytes()
+        .map_err(|_| AppError::InternalServerError)?;
+
+    // RFC 6238 defaults, which is what every authenticator app supports
+    TOTP::new(
+        Algorithm::SHA1,
+        TOTP_DIGITS,
+        1,
+        TOTP_STEP_SECONDS,
+        secret,
+        Some(settings.totp_issuer.clone()),
+        account_name.to_owned(),
+    )
+    .map_err(|_| AppError::InternalServerError)
+}
+
+/// The code an authenticator app shows for `secret` at `time`
+pub fn totp_code(secret: &str, time: DateTime<Utc>, settings: &ApplicationSettings) -> Result<String, AppError> {
+    Ok(build_totp(secret, "", settings)?.generate(time.timestamp() as u64))
+}
+
+/// Find the time step `code` was generated for, allowing one step of drift either way
+fn matching_step(secret: &str, code: &str, now: DateTime<Utc>, settings: &ApplicationSettings) -> Result<Option<i64>, AppError> {
+    let totp = build_totp(secret, "", settings)?;
+    let current_step = now.timestamp() / TOTP_STEP_SECONDS as i64;
+
+    Ok((current_step - 1..=current_step + 1)
+        .find(|step| totp.generate(*step as u64 * TOTP_STEP_SECONDS) == code.trim()))
+}
+
+/// Start (or restart) the enrollment of the user with a new secret
+///
+/// 2FA stays off until a first code is confirmed with `confirm_totp_enrollment`
+pub async fn begin_totp_enrollment(
+    user_id: Uuid,
+    email: &str,
+    pool: &PgPool,
+    settings: &ApplicationSettings,
+) -> Result<TotpEnrollment, AppError> {
+    let mut bytes = [0u8; 20];
+    OsRng.fill_bytes(&mut bytes);
+    let secret = match Secret::Raw(bytes.to_vec()).to_encoded() {
+        Secret::Encoded(secret) => secret,
+        Secret::Raw(_) => return Err(AppError::InternalServerError),
+    };
+
+    let result = sqlx::query(r#"
+        INSERT INTO user_totp (user_id, secret) VALUES ($1, $2)
+        ON CONFLICT (user_id) DO UPDATE SET secret = EXCLUDED.secret, last_used_step = NULL
+        WHERE user_totp.confirmed_at IS NULL
+    "#)
+    .bind(user_id)
+    .bind(&secret)
+    .execute(pool)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::UnprocessableEntity(serde_json::json!({
+            "error": "Two-factor authentication is already enabled",
+        })));
+    }
+
+    let otpauth_uri = build_totp(&secret, email, settings)?.get_url();
+
+    Ok(TotpEnrollment { secret, otpauth_uri })
+}
+
+/// Turn 2FA on once the user proves their app works, and hand out the recovery codes
+///
+/// The codes are only stored hashed, with argon2 as they are short enough to guess offline
+/// from plain hashes, so this is the one time they can be shown
+pub async fn confirm_totp_enrollment(
+    user_id: Uuid,
+    code: &str,
+    now: DateTime<Utc>,
+    pool: &PgPool,
+    settings: &ApplicationSettings,
+) -> Result<Vec<String>, AppError> {
+    let mut tx = pool.begin().await?;
+
+    let secret = sqlx::query_as::<_, (String,)>(
+        "SELECT secret FROM user_totp WHERE user_id = $1 AND confirmed_at IS NULL FOR UPDATE"
+    )
+    .bind(user_id)
+    .fetch_optional(&mut *tx)
+    .await?
+    .map(|(secret,)| secret)
+    .ok_or_else(|| AppError::UnprocessableEntity(serde_json::json!({
+        "error": "No two-factor enrollment is pending",
+    })))?;
+
+    let step = matching_step(&secret, code, now, settings)?.ok_or_else(invalid_code)?;
+
+    sqlx::query("UPDATE user_totp SET confirmed_at = CURRENT_TIMESTAMP, last_used_step = $2 WHERE user_id = $1")
+        .bind(user_id)
+        .bind(step)
+        .execute(&mut *tx)
+        .await?;
+
+    sqlx::query("DELETE FROM totp_recovery_codes WHERE user_id = $1")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+
+    let mut recovery_codes = Vec::with_capacity(RECOVERY_CODE_COUNT);
+    for _ in 0..RECOVERY_CODE_COUNT {
+        // 10 hex characters split in two so they are easy to copy by hand
+        let token = generate_opaque_token();
+        let recovery_code = format!("{}-{}", &token[..5], &token[5..10]);
+
+        sqlx::query("INSERT INTO totp_recovery_codes (user_id, code_hash) VALUES ($1, $2)")
+            .bind(user_id)
+            .bind(hash_password(recovery_code.as_bytes())?)
+            .execute(&mut *tx)
+            .await?;
+
+        recovery_codes.push(recovery_code);
+    }
+
+    tx.commit().await?;
+
+    Ok(recovery_codes)
+}
+
+pub async fn totp_enabled(user_id: Uuid, pool: &PgPool) -> Result<bool, AppError> {
+    let enabled = sqlx::query("SELECT 1 FROM user_totp WHERE user_id = $1 AND confirmed_at IS NOT NULL")
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await?
+        .is_some();
+
+    Ok(enabled)
+}
+
+/// Check a second factor, either a TOTP code or an unused recovery code
+///
+/// Each TOTP code is accepted once and each recovery code is spent on use
+pub async fn verify_second_factor(
+    user_id: Uuid,
+    code: &str,
+    now: DateTime<Utc>,
+    pool: &PgPool,
+    settings: &ApplicationSettings,
+) -> Result<bool, AppError> {
+    let mut tx = pool.begin().await?;
+
+    let stored = sqlx::query_as::<_, (String, Option<i64>)>(
+        "SELECT secret, last_used_step FROM user_totp WHERE user_id = $1 AND confirmed_at IS NOT NULL FOR UPDATE"
+    )
+    .bind(user_id)
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let (secret, last_used_step) = match stored {
+        Some(stored) => stored,
+        None => return Ok(false),
+    };
+
+    if let Some(step) = matching_step(&secret, code, now, settings)? {
+        if last_used_step.is_some_and(|last_used_step| step <= last_used_step) {
+            return Ok(false);
+        }
+
+        sqlx::query("UPDATE user_totp SET last_used_step = $2 WHERE user_id = $1")
+            .bind(user_id)
+            .bind(step)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
+        return Ok(true);
+    }
+
+    // Salted hashes cannot be looked up, so the code is checked against each unused one
+    let unused_codes = sqlx::query_as::<_, (Uuid, String)>(
+        "SELECT id, code_hash FROM totp_recovery_codes WHERE user_id = $1 AND used_at IS NULL FOR UPDATE"
+    )
+    .bind(user_id)
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let recovery_code = code.trim().to_lowercase();
+    let matching_code = unused_codes
+        .into_iter()
+        .find(|(_, code_hash)| verify_password(recovery_code.as_bytes(), code_hash));
+
+    let recovery_code_id = match matching_code {
+        Some((recovery_code_id, _)) => recovery_code_id,
+        None => return Ok(false),
+    };
+
+    sqlx::query("UPDATE totp_recovery_codes SET used_at = CURRENT_TIMESTAMP WHERE id = $1")
+        .bind(recovery_code_id)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+
+    Ok(true)
+}
+
+/// Turn 2FA off, the recovery codes go with it
+pub async fn disable_totp(user_id: Uuid, pool: &PgPool) -> Result<(), AppError> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query("DELETE FROM totp_recovery_codes WHERE user_id = $1")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+
+    sqlx::query("DELETE FROM user_totp WHERE user_id = $1")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+fn invalid_code() -> AppError {
+    AppError::UnprocessableEntity(serde_json::json!({"error": "Invalid two-factor code"}))
+}
@@ -0,0 +1,39 @@
+use chrono::Duration;
+use uuid::Uuid;
+
+use crate::errors::Error as AppError;
+use crate::mailer::{Email, Mailer};
+use crate::settings::ApplicationSettings;
+
+use super::{generate_email_token, VERIFY_EMAIL_PURPOSE};
+
+/// Mail a link to `/api/v1/users/verify` to the address of the user
+///
+/// The token is bound to the address so changing the email invalidates older links
+pub async fn send_verification_email(
+    user_id: &Uuid,
+    email: &str,
+    mailer: &dyn Mailer,
+    settings: &ApplicationSettings,
+) -> Result<(), AppError> {
+    let token = generate_email_token(
+        user_id,
+        email,
+        VERIFY_EMAIL_PURPOSE,
+        Duration::hours(settings.email_verification_expiration_hours),
+        settings,
+    )?;
+
+    let link = format!("{}/api/v1/users/verify?token={}", settings.base_url, token);
+
+    mailer
+        .send(Email {
+            to: email.to_owned(),
+            subject: "Please verify your email address".to_owned(),
+            body: format!(
+                "Welcome! Open the link below to verify your email address:\n\n{}\n\nThe link expires in {} hours.",
+                link, settings.email_verification_expiration_hours
+            ),
+        })
+        .await
+}
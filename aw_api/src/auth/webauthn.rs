@@ -0,0 +1,380 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use ciborium::value::Value as CborValue;
+use p256::ecdsa::{signature::Verifier, Signature, VerifyingKey};
+use p256::EncodedPoint;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::audit::{AuditContext, AuditEventKind};
+use crate::errors::Error as AppError;
+use crate::settings::ApplicationSettings;
+
+use super::generate_opaque_token;
+
+// COSE identifiers of the only supported key type, ES256 on P-256
+const COSE_KEY_TYPE_EC2: i128 = 2;
+const COSE_ALG_ES256: i128 = -7;
+const COSE_CURVE_P256: i128 = 1;
+
+const FLAG_USER_PRESENT: u8 = 0x01;
+const FLAG_ATTESTED_CREDENTIAL_DATA: u8 = 0x40;
+
+const REGISTRATION_CEREMONY: &str = "registration";
+const AUTHENTICATION_CEREMONY: &str = "authentication";
+
+/// The user a verified login assertion belongs to
+pub struct PasskeyLogin {
+    pub user_id: Uuid,
+    pub username: String,
+}
+
+#[derive(Deserialize)]
+struct ClientData {
+    #[serde(rename = "type")]
+    ceremony_type: String,
+    challenge: String,
+    origin: String,
+}
+
+/// The fixed part of `authenticatorData` plus the credential added during a registration
+struct AuthenticatorData {
+    rp_id_hash: Vec<u8>,
+    flags: u8,
+    sign_count: u32,
+    credential: Option<(Vec<u8>, Vec<u8>)>,
+}
+
+/// Options for `navigator.credentials.create()`, the existing passkeys of the user are excluded
+pub async fn start_passkey_registration(
+    user_id: Uuid,
+    username: &str,
+    pool: &PgPool,
+    settings: &ApplicationSettings,
+) -> Result<serde_json::Value, AppError> {
+    let challenge = insert_challenge(Some(user_id), REGISTRATION_CEREMONY, pool, settings).await?;
+
+    let existing: Vec<(String,)> = sqlx::query_as("SELECT credential_id FROM webauthn_credentials WHERE user_id = $1")
+        .bind(user_id)
+        .fetch_all(pool)
+        .await?;
+
+    Ok(serde_json::json!({
+        "challenge": challenge,
+        "rp": { "id": settings.webauthn_rp_id, "name": settings.webauthn_rp_name },
+        "user": {
+            "id": URL_SAFE_NO_PAD.encode(user_id.as_bytes()),
+            "name": username,
+            "displayName": username,
+        },
+        "pubKeyCredParams": [{ "type": "public-key", "alg": COSE_ALG_ES256 as i64 }],
+        "timeout": settings.webauthn_challenge_minutes * 60 * 1000,
+        "attestation": "none",
+        "authenticatorSelection": { "residentKey": "preferred", "userVerification": "preferred" },
+        "excludeCredentials": existing
+            .into_iter()
+            .map(|(credential_id,)| serde_json::json!({ "type": "public-key", "id": credential_id }))
+            .collect::<Vec<_>>(),
+    }))
+}
+
+/// Check the attestation sent back by the authenticator and store its public key
+///
+/// Only `none` attestation is accepted, the server trusts the key without asking who made the authenticator
+pub async fn finish_passkey_registration(
+    user_id: Uuid,
+    client_data_json: &str,
+    attestation_object: &str,
+    name: &str,
+    pool: &PgPool,
+    settings: &ApplicationSettings,
+) -> Result<Uuid, AppError> {
+    let client_data_json = decode_base64url(client_data_json)?;
+    verify_client_data(&client_data_json, "webauthn.create", Some(user_id), REGISTRATION_CEREMONY, pool, settings).await?;
+
+    let attestation = ciborium::de::from_reader::<CborValue, _>(decode_base64url(attestation_object)?.as_slice())
+        .map_err(|_| invalid_passkey("Malformed attestation object"))?;
+
+    let fmt = cbor_map_get(&attestation, |key| key.as_text() == Some("fmt")).and_then(|value| value.as_text());
+    if fmt != Some("none") {
+        return Err(invalid_passkey("Only the none attestation format is supported"));
+    }
+
+    let auth_data = cbor_map_get(&attestation, |key| key.as_text() == Some("authData"))
+        .and_then(|value| value.as_bytes())
+        .ok_or_else(|| invalid_passkey("Malformed attestation object"))?;
+
+    let auth_data = parse_authenticator_data(auth_data)?;
+    verify_authenticator_data(&auth_data, settings)?;
+
+    let (credential_id, cose_key) = auth_data
+        .credential
+        .ok_or_else(|| invalid_passkey("No credential in the attestation"))?;
+    let public_key = parse_cose_key(&cose_key)?;
+
+    let (id,): (Uuid,) = sqlx::query_as(r#"
+        INSERT INTO webauthn_credentials (user_id, credential_id, public_key, sign_count, name)
+        VALUES ($1, $2, $3, $4, $5)
+        RETURNING id
+    "#)
+    .bind(user_id)
+    .bind(URL_SAFE_NO_PAD.encode(credential_id))
+    .bind(public_key)
+    .bind(i64::from(auth_data.sign_count))
+    .bind(name)
+    .fetch_one(pool)
+    .await
+    .map_err(|err| match err {
+        sqlx::Error::Database(db_err) if db_err.is_unique_violation() => {
+            AppError::UnprocessableEntity(serde_json::json!({"error": "This passkey is already registered"}))
+        }
+        err => err.into(),
+    })?;
+
+    Ok(id)
+}
+
+/// Options for `navigator.credentials.get()`
+///
+/// No credentials are listed so the authenticator offers every passkey it holds for the site
+pub async fn start_passkey_login(pool: &PgPool, settings: &ApplicationSettings) -> Result<serde_json::Value, AppError> {
+    let challenge = insert_challenge(None, AUTHENTICATION_CEREMONY, pool, settings).await?;
+
+    Ok(serde_json::json!({
+        "challenge": challenge,
+        "rpId": settings.webauthn_rp_id,
+        "timeout": settings.webauthn_challenge_minutes * 60 * 1000,
+        "userVerification": "preferred",
+        "allowCredentials": [],
+    }))
+}
+
+/// Verify an assertion with the stored public key of the credential
+///
+/// A signature counter that does not move forward means the authenticator was cloned, the login is refused
+pub async fn finish_passkey_login(
+    credential_id: &str,
+    client_data_json: &str,
+    authenticator_data: &str,
+    signature: &str,
+    audit: &AuditContext,
+    pool: &PgPool,
+    settings: &ApplicationSettings,
+) -> Result<PasskeyLogin, AppError> {
+    let client_data_json = decode_base64url(client_data_json)?;
+    verify_client_data(&client_data_json, "webauthn.get", None, AUTHENTICATION_CEREMONY, pool, settings).await?;
+
+    let raw_auth_data = decode_base64url(authenticator_data)?;
+    let auth_data = parse_authenticator_data(&raw_auth_data)?;
+    verify_authenticator_data(&auth_data, settings)?;
+
+    let mut tx = pool.begin().await?;
+
+    let (id, user_id, username, public_key, sign_count) = sqlx::query_as::<_, (Uuid, Uuid, String, Vec<u8>, i64)>(r#"
+        SELECT webauthn_credentials.id, users.id, users.username, webauthn_credentials.public_key, webauthn_credentials.sign_count
+        FROM webauthn_credentials
+        INNER JOIN users ON users.id = webauthn_credentials.user_id
+        WHERE webauthn_credentials.credential_id = $1
+        FOR UPDATE OF webauthn_credentials
+    "#)
+    .bind(credential_id)
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or_else(|| AppError::Unauthorized(serde_json::json!({"error": "Unknown passkey"})))?;
+
+    let verifying_key = VerifyingKey::from_sec1_bytes(&public_key).map_err(|_| AppError::InternalServerError)?;
+    let signature = Signature::from_der(&decode_base64url(signature)?)
+        .map_err(|_| invalid_passkey("Malformed signature"))?;
+
+    let mut signed_data = raw_auth_data.clone();
+    signed_data.extend_from_slice(&Sha256::digest(&client_data_json));
+
+    if verifying_key.verify(&signed_data, &signature).is_err() {
+        return Err(AppError::Unauthorized(serde_json::json!({"error": "Invalid passkey signature"})));
+    }
+
+    // Authenticators without a counter always send 0
+    let new_sign_count = i64::from(auth_data.sign_count);
+    if (new_sign_count != 0 || sign_count != 0) && new_sign_count <= sign_count {
+        audit
+            .record(AuditEventKind::PasskeyCounterRegressed, Some(user_id), serde_json::json!({
+                "passkey_id": id,
+                "stored_sign_count": sign_count,
+                "received_sign_count": new_sign_count,
+            }))
+            .await;
+
+        return Err(AppError::Unauthorized(serde_json::json!({"error": "Passkey signature counter did not increase"})));
+    }
+
+    sqlx::query("UPDATE webauthn_credentials SET sign_count = $2, last_used_at = CURRENT_TIMESTAMP WHERE id = $1")
+        .bind(id)
+        .bind(new_sign_count)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+
+    Ok(PasskeyLogin { user_id, username })
+}
+
+/// Remove a passkey of the user, returns `false` when they have none with this id
+pub async fn delete_passkey(id: Uuid, user_id: Uuid, pool: &PgPool) -> Result<bool, AppError> {
+    let deleted = sqlx::query("DELETE FROM webauthn_credentials WHERE id = $1 AND user_id = $2")
+        .bind(id)
+        .bind(user_id)
+        .execute(pool)
+        .await?
+        .rows_affected() > 0;
+
+    Ok(deleted)
+}
+
+async fn insert_challenge(
+    user_id: Option<Uuid>,
+    ceremony: &str,
+    pool: &PgPool,
+    settings: &ApplicationSettings,
+) -> Result<String, AppError> {
+    let challenge = URL_SAFE_NO_PAD.encode(hex::decode(generate_opaque_token()).map_err(|_| AppError::InternalServerError)?);
+
+    sqlx::query(r#"
+        INSERT INTO webauthn_challenges (user_id, challenge, ceremony, expires_at)
+        VALUES ($1, $2, $3, CURRENT_TIMESTAMP + make_interval(mins => $4))
+    "#)
+    .bind(user_id)
+    .bind(&challenge)
+    .bind(ceremony)
+    .bind(settings.webauthn_challenge_minutes)
+    .execute(pool)
+    .await?;
+
+    Ok(challenge)
+}
+
+/// Check the type and origin the browser signed, and spend the challenge it was answering
+async fn verify_client_data(
+    client_data_json: &[u8],
+    ceremony_type: &str,
+    user_id: Option<Uuid>,
+    ceremony: &str,
+    pool: &PgPool,
+    settings: &ApplicationSettings,
+) -> Result<(), AppError> {
+    let client_data: ClientData = serde_json::from_slice(client_data_json)
+        .map_err(|_| invalid_passkey("Malformed client data"))?;
+
+    if client_data.ceremony_type != ceremony_type {
+        return Err(invalid_passkey("Unexpected ceremony type"));
+    }
+
+    if client_data.origin != settings.webauthn_origin {
+        return Err(invalid_passkey("Unexpected origin"));
+    }
+
+    let spent = sqlx::query(r#"
+        DELETE FROM webauthn_challenges
+        WHERE challenge = $1 AND ceremony = $2 AND user_id IS NOT DISTINCT FROM $3 AND expires_at > CURRENT_TIMESTAMP
+    "#)
+    .bind(&client_data.challenge)
+    .bind(ceremony)
+    .bind(user_id)
+    .execute(pool)
+    .await?
+    .rows_affected() > 0;
+
+    if !spent {
+        return Err(invalid_passkey("Unknown or expired challenge"));
+    }
+
+    Ok(())
+}
+
+fn verify_authenticator_data(auth_data: &AuthenticatorData, settings: &ApplicationSettings) -> Result<(), AppError> {
+    if auth_data.rp_id_hash != Sha256::digest(settings.webauthn_rp_id.as_bytes()).as_slice() {
+        return Err(invalid_passkey("Passkey belongs to another site"));
+    }
+
+    if auth_data.flags & FLAG_USER_PRESENT == 0 {
+        return Err(invalid_passkey("User presence was not confirmed"));
+    }
+
+    Ok(())
+}
+
+/// Layout: rpIdHash (32) | flags (1) | signCount (4) | [aaguid (16) | idLength (2) | credentialId | COSE key]
+fn parse_authenticator_data(data: &[u8]) -> Result<AuthenticatorData, AppError> {
+    let malformed = || invalid_passkey("Malformed authenticator data");
+
+    if data.len() < 37 {
+        return Err(malformed());
+    }
+
+    let flags = data[32];
+    let sign_count = u32::from_be_bytes([data[33], data[34], data[35], data[36]]);
+
+    let credential = if flags & FLAG_ATTESTED_CREDENTIAL_DATA != 0 {
+        let rest = data.get(37 + 16..).ok_or_else(malformed)?;
+        let id_length = rest.get(..2).map(|len| u16::from_be_bytes([len[0], len[1]]) as usize).ok_or_else(malformed)?;
+        let credential_id = rest.get(2..2 + id_length).ok_or_else(malformed)?;
+        let cose_key = rest.get(2 + id_length..).ok_or_else(malformed)?;
+
+        Some((credential_id.to_vec(), cose_key.to_vec()))
+    } else {
+        None
+    };
+
+    Ok(AuthenticatorData {
+        rp_id_hash: data[..32].to_vec(),
+        flags,
+        sign_count,
+        credential,
+    })
+}
+
+/// Turn an ES256 COSE key into an uncompressed SEC1 point
+fn parse_cose_key(cose_key: &[u8]) -> Result<Vec<u8>, AppError> {
+    let unsupported = || invalid_passkey("Only ES256 passkeys are supported");
+
+    let key = ciborium::de::from_reader::<CborValue, _>(cose_key).map_err(|_| invalid_passkey("Malformed public key"))?;
+    let integer_label = |label: i128| move |key: &CborValue| key.as_integer().map(i128::from) == Some(label);
+    let integer_value = |label: i128| cbor_map_get(&key, integer_label(label)).and_then(|value| value.as_integer()).map(i128::from);
+    let bytes_value = |label: i128| cbor_map_get(&key, integer_label(label)).and_then(|value| value.as_bytes());
+
+    if integer_value(1) != Some(COSE_KEY_TYPE_EC2)
+        || integer_value(3) != Some(COSE_ALG_ES256)
+        || integer_value(-1) != Some(COSE_CURVE_P256)
+    {
+        return Err(unsupported());
+    }
+
+    let (x, y) = match (bytes_value(-2), bytes_value(-3)) {
+        (Some(x), Some(y)) if x.len() == 32 && y.len() == 32 => (x, y),
+        _ => return Err(unsupported()),
+    };
+
+    let point = EncodedPoint::from_affine_coordinates(x.as_slice().into(), y.as_slice().into(), false);
+
+    // Make sure the point is on the curve before storing it
+    VerifyingKey::from_encoded_point(&point).map_err(|_| invalid_passkey("Malformed public key"))?;
+
+    Ok(point.as_bytes().to_vec())
+}
+
+fn cbor_map_get(map: &CborValue, matches: impl Fn(&CborValue) -> bool) -> Option<&CborValue> {
+    map.as_map()?
+        .iter()
+        .find(|(key, _)| matches(key))
+        .map(|(_, value)| value)
+}
+
+fn decode_base64url(value: &str) -> Result<Vec<u8>, AppError> {
+    URL_SAFE_NO_PAD
+        .decode(value.trim_end_matches('='))
+        .map_err(|_| invalid_passkey("Malformed base64url value"))
+}
+
+fn invalid_passkey(reason: &str) -> AppError {
+    AppError::BadRequest(serde_json::json!({"error": reason}))
+}
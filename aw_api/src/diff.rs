@@ -0,0 +1,237 @@
+// Text diffs between article revisions, line based for `unified_diff` and word based for `word_diff`
+
+// Lines of context around each change in a unified diff, as in `diff -u`
+const CONTEXT_LINES: usize = 3;
+
+// Past this many edits the changed part is shown as removed then added as a whole,
+// which keeps the work and memory of very different texts bounded
+const MAX_EDIT_DISTANCE: usize = 2000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Edit {
+    Equal,
+    Delete,
+    Insert,
+}
+
+/// Unified diff of `old` and `new` with `---`/`+++` headers and `@@` hunks, empty when they are the same
+pub fn unified_diff(old: &str, new: &str, old_label: &str, new_label: &str) -> String {
+    let old_lines: Vec<&str> = old.lines().collect();
+    let new_lines: Vec<&str> = new.lines().collect();
+    let edits = diff_tokens(&old_lines, &new_lines);
+
+    let changes: Vec<usize> = edits
+        .iter()
+        .enumerate()
+        .filter(|(_, (edit, _))| *edit != Edit::Equal)
+        .map(|(index, _)| index)
+        .collect();
+
+    if changes.is_empty() {
+        return String::new();
+    }
+
+    // Changes closer than twice the context share a hunk
+    let mut hunks: Vec<(usize, usize)> = Vec::new();
+    for &index in &changes {
+        match hunks.last_mut() {
+            Some((_, last)) if index - *last <= 2 * CONTEXT_LINES => *last = index,
+            _ => hunks.push((index, index)),
+        }
+    }
+
+    let mut output = format!("--- {}\n+++ {}\n", old_label, new_label);
+
+    for (first_change, last_change) in hunks {
+        let start = first_change.saturating_sub(CONTEXT_LINES);
+        let end = (last_change + CONTEXT_LINES + 1).min(edits.len());
+
+        let old_before = edits[..start].iter().filter(|(edit, _)| *edit != Edit::Insert).count();
+        let new_before = edits[..start].iter().filter(|(edit, _)| *edit != Edit::Delete).count();
+        let old_count = edits[start..end].iter().filter(|(edit, _)| *edit != Edit::Insert).count();
+        let new_count = edits[start..end].iter().filter(|(edit, _)| *edit != Edit::Delete).count();
+
+        output.push_str(&format!(
+            "@@ -{} +{} @@\n",
+            hunk_range(old_before, old_count),
+            hunk_range(new_before, new_count)
+        ));
+
+        for (edit, line) in &edits[start..end] {
+            let marker = match edit {
+                Edit::Equal => ' ',
+                Edit::Delete => '-',
+                Edit::Insert => '+',
+            };
+            output.push(marker);
+            output.push_str(line);
+            output.push('\n');
+        }
+    }
+
+    output
+}
+
+/// `new` with the removed words as `[-old-]` and the added ones as `{+new+}`, like `git diff --word-diff=plain`
+pub fn word_diff(old: &str, new: &str) -> String {
+    let old_words = split_words(old);
+    let new_words = split_words(new);
+    let edits = diff_tokens(&old_words, &new_words);
+
+    let mut output = String::new();
+    let mut index = 0;
+
+    while index < edits.len() {
+        let edit = edits[index].0;
+        let mut run = String::new();
+        while index < edits.len() && edits[index].0 == edit {
+            run.push_str(edits[index].1);
+            index += 1;
+        }
+
+        match edit {
+            Edit::Equal => output.push_str(&run),
+            Edit::Delete => output.push_str(&format!("[-{}-]", run)),
+            Edit::Insert => output.push_str(&format!("{{+{}+}}", run)),
+        }
+    }
+
+    output
+}
+
+fn hunk_range(before: usize, count: usize) -> String {
+    // An empty side points at the line before the hunk
+    let start = if count == 0 { before } else { before + 1 };
+
+    match count {
+        1 => start.to_string(),
+        _ => format!("{},{}", start, count),
+    }
+}
+
+/// Words and the whitespace between them, so joining the tokens gives the text back
+fn split_words(text: &str) -> Vec<&str> {
+    let mut words = Vec::new();
+    let mut start = 0;
+    let mut in_whitespace = None;
+
+    for (index, character) in text.char_indices() {
+        let whitespace = character.is_whitespace();
+        if in_whitespace.is_some_and(|previous| previous != whitespace) {
+            words.push(&text[start..index]);
+            start = index;
+        }
+        in_whitespace = Some(whitespace);
+    }
+
+    if start < text.len() {
+        words.push(&text[start..]);
+    }
+
+    words
+}
+
+/// Shortest edit script from `old` to `new`, the tokens in order with what happened to them
+fn diff_tokens<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<(Edit, &'a str)> {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let old_middle = &old[prefix..old.len() - suffix];
+    let new_middle = &new[prefix..new.len() - suffix];
+
+    let mut edits: Vec<(Edit, &'a str)> = old[..prefix].iter().map(|token| (Edit::Equal, *token)).collect();
+    edits.extend(myers(old_middle, new_middle));
+    edits.extend(old[old.len() - suffix..].iter().map(|token| (Edit::Equal, *token)));
+
+    edits
+}
+
+/// Myers' O((N + M) D) algorithm, keeping the frontier of every step to walk the path back
+fn myers<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<(Edit, &'a str)> {
+    let n = old.len() as isize;
+    let m = new.len() as isize;
+    let max = (old.len() + new.len()).min(MAX_EDIT_DISTANCE) as isize;
+
+    // `frontier[k + offset]` is the furthest x reached on diagonal k = x - y
+    let offset = max + 1;
+    let mut frontier = vec![0isize; 2 * offset as usize + 1];
+    let mut trace: Vec<Vec<isize>> = Vec::new();
+
+    let mut found = false;
+    for d in 0..=max {
+        trace.push(frontier.clone());
+
+        for k in (-d..=d).step_by(2) {
+            let index = (k + offset) as usize;
+            let mut x = if k == -d || (k != d && frontier[index - 1] < frontier[index + 1]) {
+                frontier[index + 1]
+            } else {
+                frontier[index - 1] + 1
+            };
+            let mut y = x - k;
+
+            while x < n && y < m && old[x as usize] == new[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            frontier[index] = x;
+
+            if x >= n && y >= m {
+                found = true;
+                break;
+            }
+        }
+
+        if found {
+            break;
+        }
+    }
+
+    if !found {
+        let mut edits: Vec<(Edit, &'a str)> = old.iter().map(|token| (Edit::Delete, *token)).collect();
+        edits.extend(new.iter().map(|token| (Edit::Insert, *token)));
+        return edits;
+    }
+
+    let mut edits = Vec::new();
+    let (mut x, mut y) = (n, m);
+
+    for (d, frontier) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        let k = x - y;
+        let index = (k + offset) as usize;
+
+        let previous_k = if k == -d || (k != d && frontier[index - 1] < frontier[index + 1]) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let previous_x = frontier[(previous_k + offset) as usize];
+        let previous_y = previous_x - previous_k;
+
+        while x > previous_x && y > previous_y {
+            edits.push((Edit::Equal, old[(x - 1) as usize]));
+            x -= 1;
+            y -= 1;
+        }
+
+        if d > 0 {
+            if x == previous_x {
+                edits.push((Edit::Insert, new[(y - 1) as usize]));
+            } else {
+                edits.push((Edit::Delete, old[(x - 1) as usize]));
+            }
+        }
+
+        x = previous_x;
+        y = previous_y;
+    }
+
+    edits.reverse();
+    edits
+}
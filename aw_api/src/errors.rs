@@ -1,4 +1,4 @@
-use actix_web::{error::ResponseError, http::StatusCode, HttpResponse};
+use actix_web::{error::ResponseError, http::{header, StatusCode}, HttpResponse};
 use sqlx::Error as PgError;
 use serde_json::{Map as JsonMap, Value as JsonValue};
 use validator::ValidationErrors;
@@ -28,6 +28,10 @@ pub enum Error {
     #[error("Unprocessable Entity: {0}")]
     UnprocessableEntity(JsonValue),
 
+    // 429, with the number of seconds to wait for the `Retry-After` header
+    #[error("Too Many Requests: {0}")]
+    TooManyRequests(JsonValue, u64),
+
     // 500
     #[error("Internal Server Error")]
     InternalServerError,
@@ -44,6 +48,9 @@ impl ResponseError for Error {
             Error::UnprocessableEntity(ref message) => {
                 HttpResponse::build(StatusCode::UNPROCESSABLE_ENTITY).json(message)
             }
+            Error::TooManyRequests(ref message, retry_after) => HttpResponse::TooManyRequests()
+                .insert_header((header::RETRY_AFTER, retry_after.to_string()))
+                .json(message),
             Error::InternalServerError => {
                 HttpResponse::InternalServerError().json("Internal Server Error")
             }
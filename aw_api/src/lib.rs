@@ -1,6 +1,16 @@
+pub mod auth;
 pub mod routes;
 pub mod server;
 pub mod settings;
 pub mod errors;
+pub mod mailer;
+pub mod session_store;
+pub mod clock;
+pub mod audit;
+pub mod diff;
+pub mod markdown;
+pub mod pagination;
+pub mod publishing;
+pub mod trending;
 pub mod utils;
 pub mod schemas;
\ No newline at end of file
@@ -0,0 +1,39 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+
+use crate::errors::Error as AppError;
+use crate::settings::{EmailSettings, MailerBackend};
+
+use super::{FileMailer, SmtpMailer, StdoutMailer};
+
+#[derive(Debug, Clone)]
+pub struct Email {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+impl Email {
+    /// Headers and body in the shape of a plain text message, used by the local sinks
+    pub fn to_plain_text(&self, from: &str) -> String {
+        format!(
+            "From: {}\nTo: {}\nSubject: {}\n\n{}\n",
+            from, self.to, self.subject, self.body
+        )
+    }
+}
+
+/// Sends the outgoing mail of the application, the backend is chosen in `settings.rs`
+#[async_trait]
+pub trait Mailer: Send + Sync {
+    async fn send(&self, email: Email) -> Result<(), AppError>;
+}
+
+pub fn get_mailer(settings: &EmailSettings) -> Arc<dyn Mailer> {
+    match settings.backend {
+        MailerBackend::Stdout => Arc::new(StdoutMailer::new(settings)),
+        MailerBackend::File => Arc::new(FileMailer::new(settings)),
+        MailerBackend::Smtp => Arc::new(SmtpMailer::new(settings)),
+    }
+}
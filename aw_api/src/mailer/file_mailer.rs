@@ -0,0 +1,46 @@
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use async_trait::async_trait;
+use uuid::Uuid;
+
+use crate::errors::Error as AppError;
+use crate::settings::EmailSettings;
+
+use super::{Email, Mailer};
+
+/// Writes every message as a `.eml` file into `file_directory`, the tests read the mail from there
+pub struct FileMailer {
+    sender: String,
+    directory: PathBuf,
+}
+
+impl FileMailer {
+    pub fn new(settings: &EmailSettings) -> Self {
+        FileMailer {
+            sender: settings.sender.clone(),
+            directory: PathBuf::from(settings.file_directory.as_deref().unwrap_or("mail")),
+        }
+    }
+}
+
+#[async_trait]
+impl Mailer for FileMailer {
+    async fn send(&self, email: Email) -> Result<(), AppError> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_err(|_| AppError::InternalServerError)?
+            .as_nanos();
+
+        // Prefixing with the time keeps the files in the order they were sent
+        let path = self.directory.join(format!("{}-{}.eml", timestamp, Uuid::new_v4()));
+
+        std::fs::create_dir_all(&self.directory)
+            .and_then(|_| std::fs::write(&path, email.to_plain_text(&self.sender)))
+            .map_err(|err| {
+                eprintln!("Mailer Error: {:?}", err);
+
+                AppError::InternalServerError
+            })
+    }
+}
@@ -0,0 +1,9 @@
+mod email;
+mod stdout_mailer;
+mod file_mailer;
+mod smtp_mailer;
+
+pub use email::*;
+pub use stdout_mailer::*;
+pub use file_mailer::*;
+pub use smtp_mailer::*;
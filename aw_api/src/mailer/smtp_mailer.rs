@@ -0,0 +1,60 @@
+use async_trait::async_trait;
+use lettre::{
+    message::header::ContentType, transport::smtp::authentication::Credentials,
+    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+};
+
+use crate::errors::Error as AppError;
+use crate::settings::EmailSettings;
+
+use super::{Email, Mailer};
+
+pub struct SmtpMailer {
+    sender: String,
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+}
+
+impl SmtpMailer {
+    pub fn new(settings: &EmailSettings) -> Self {
+        let host = settings.smtp_host.as_deref().expect("smtp_host is required for the smtp mailer");
+
+        let mut builder = AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host)
+            .expect("Failed to build the SMTP transport");
+
+        if let Some(port) = settings.smtp_port {
+            builder = builder.port(port);
+        }
+
+        if let (Some(username), Some(password)) = (&settings.smtp_username, &settings.smtp_password) {
+            builder = builder.credentials(Credentials::new(username.clone(), password.clone()));
+        }
+
+        SmtpMailer {
+            sender: settings.sender.clone(),
+            transport: builder.build(),
+        }
+    }
+}
+
+#[async_trait]
+impl Mailer for SmtpMailer {
+    async fn send(&self, email: Email) -> Result<(), AppError> {
+        let message = Message::builder()
+            .from(self.sender.parse().map_err(|_| AppError::InternalServerError)?)
+            .to(email.to.parse().map_err(|_| AppError::InternalServerError)?)
+            .subject(email.subject)
+            .header(ContentType::TEXT_PLAIN)
+            .body(email.body)
+            .map_err(|_| AppError::InternalServerError)?;
+
+        self.transport
+            .send(message)
+            .await
+            .map(|_| ())
+            .map_err(|err| {
+                eprintln!("Mailer Error: {:?}", err);
+
+                AppError::InternalServerError
+            })
+    }
+}
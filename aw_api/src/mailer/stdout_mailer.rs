@@ -0,0 +1,26 @@
+use async_trait::async_trait;
+
+use crate::errors::Error as AppError;
+use crate::settings::EmailSettings;
+
+use super::{Email, Mailer};
+
+/// Prints every message, handy while developing locally
+pub struct StdoutMailer {
+    sender: String,
+}
+
+impl StdoutMailer {
+    pub fn new(settings: &EmailSettings) -> Self {
+        StdoutMailer { sender: settings.sender.clone() }
+    }
+}
+
+#[async_trait]
+impl Mailer for StdoutMailer {
+    async fn send(&self, email: Email) -> Result<(), AppError> {
+        println!("{}", email.to_plain_text(&self.sender));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,93 @@
+use std::collections::HashSet;
+
+use ammonia::Builder;
+use pulldown_cmark::{html, Options, Parser};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::errors::Error as AppError;
+
+// Rows rendered per query while filling in the `body_html` written before it existed
+const BACKFILL_BATCH: i64 = 100;
+
+/// Render a Markdown body, CommonMark with the GFM tables and strikethrough, to HTML safe to embed in a page
+///
+/// Scripts, event handlers, styles and links other than http, https and mailto are stripped
+pub fn render_markdown(source: &str) -> String {
+    let mut options = Options::empty();
+    options.insert(Options::ENABLE_TABLES);
+    options.insert(Options::ENABLE_STRIKETHROUGH);
+
+    let mut unsafe_html = String::with_capacity(source.len() * 3 / 2);
+    html::push_html(&mut unsafe_html, Parser::new_ext(source, options));
+
+    Builder::default()
+        .url_schemes(HashSet::from(["http", "https", "mailto"]))
+        .link_rel(Some("noopener noreferrer nofollow"))
+        // Code fences keep their `language-*` class for client side highlighting
+        .add_tag_attributes("code", &["class"])
+        .attribute_filter(|element, attribute, value| match (element, attribute) {
+            ("code", "class") if !value.starts_with("language-") => None,
+            _ => Some(value.into()),
+        })
+        .clean(&unsafe_html)
+        .to_string()
+}
+
+/// Render the article and comment bodies without a cached `body_html`, returns how many rows were filled in
+pub async fn render_missing_html(pool: &PgPool) -> Result<u64, AppError> {
+    let mut rendered = 0;
+
+    loop {
+        let articles: Vec<(Uuid, String)> = sqlx::query_as("SELECT id, body FROM articles WHERE body_html IS NULL LIMIT $1")
+            .bind(BACKFILL_BATCH)
+            .fetch_all(pool)
+            .await?;
+
+        if articles.is_empty() {
+            break;
+        }
+
+        for (id, body) in articles {
+            // The body is compared so an edit made meanwhile keeps its own rendering
+            rendered += sqlx::query("UPDATE articles SET body_html = $1 WHERE id = $2 AND body = $3 AND body_html IS NULL")
+                .bind(render_markdown(&body))
+                .bind(id)
+                .bind(&body)
+                .execute(pool)
+                .await?
+                .rows_affected();
+        }
+    }
+
+    loop {
+        let comments: Vec<(i32, String)> = sqlx::query_as("SELECT id, body FROM comments WHERE body_html IS NULL LIMIT $1")
+            .bind(BACKFILL_BATCH)
+            .fetch_all(pool)
+            .await?;
+
+        if comments.is_empty() {
+            break;
+        }
+
+        for (id, body) in comments {
+            rendered += sqlx::query("UPDATE comments SET body_html = $1 WHERE id = $2 AND body_html IS NULL")
+                .bind(render_markdown(&body))
+                .bind(id)
+                .execute(pool)
+                .await?
+                .rows_affected();
+        }
+    }
+
+    Ok(rendered)
+}
+
+/// Fill in the missing `body_html` once in the background, responses render those rows on the fly until then
+pub fn spawn_markdown_backfill(pool: PgPool) {
+    actix_web::rt::spawn(async move {
+        if let Err(err) = render_missing_html(&pool).await {
+            eprintln!("Markdown Error: failed to render the stored bodies: {:?}", err);
+        }
+    });
+}
@@ -0,0 +1,181 @@
+use actix_web::{HttpRequest, HttpResponse};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::errors::Error as AppError;
+
+/// Which side of the cursor a page is on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CursorDirection {
+    /// The next page
+    After,
+    /// The previous page
+    Before,
+}
+
+/// Position in a list ordered by a timestamp, e.g. `created_at`, and then `id`
+///
+/// Clients only see it as an opaque string, see `encode` and `decode`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cursor {
+    pub direction: CursorDirection,
+    pub timestamp: NaiveDateTime,
+    pub id: Uuid,
+}
+
+impl Cursor {
+    pub fn after(timestamp: NaiveDateTime, id: Uuid) -> Self {
+        Cursor { direction: CursorDirection::After, timestamp, id }
+    }
+
+    pub fn before(timestamp: NaiveDateTime, id: Uuid) -> Self {
+        Cursor { direction: CursorDirection::Before, timestamp, id }
+    }
+
+    pub fn encode(&self) -> String {
+        let direction = match self.direction {
+            CursorDirection::After => "a",
+            CursorDirection::Before => "b",
+        };
+
+        URL_SAFE_NO_PAD.encode(format!("{}:{}:{}", direction, self.timestamp.and_utc().timestamp_micros(), self.id))
+    }
+
+    pub fn decode(value: &str) -> Result<Self, AppError> {
+        let invalid_cursor = || AppError::BadRequest(serde_json::json!({"error": "Invalid cursor"}));
+
+        let decoded = URL_SAFE_NO_PAD
+            .decode(value)
+            .ok()
+            .and_then(|bytes| String::from_utf8(bytes).ok())
+            .ok_or_else(invalid_cursor)?;
+
+        let mut parts = decoded.splitn(3, ':');
+        let direction = match parts.next() {
+            Some("a") => CursorDirection::After,
+            Some("b") => CursorDirection::Before,
+            _ => return Err(invalid_cursor()),
+        };
+        let timestamp = parts
+            .next()
+            .and_then(|micros| micros.parse::<i64>().ok())
+            .and_then(DateTime::from_timestamp_micros)
+            .map(|timestamp| timestamp.naive_utc())
+            .ok_or_else(invalid_cursor)?;
+        let id = parts
+            .next()
+            .and_then(|id| Uuid::parse_str(id).ok())
+            .ok_or_else(invalid_cursor)?;
+
+        Ok(Cursor { direction, timestamp, id })
+    }
+}
+
+/// One page of a list with the cursors to its neighbours, `None` when there is nothing on that side
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub next_cursor: Option<String>,
+    pub prev_cursor: Option<String>,
+}
+
+/// `limit` and `offset` of the lists that only page by offset
+#[derive(Debug, Deserialize)]
+pub struct PageParams {
+    pub limit: Option<usize>,
+    pub offset: Option<usize>,
+}
+
+impl PageParams {
+    pub fn limit(&self) -> i64 {
+        page_limit(self.limit)
+    }
+
+    pub fn offset(&self) -> i64 {
+        page_offset(self.offset)
+    }
+
+    /// `limit` for the lists that were returned whole before they could be paged
+    ///
+    /// `None`, bound as `LIMIT NULL` which is no limit, unless the client asked for a page
+    pub fn limit_if_paged(&self) -> Option<i64> {
+        (self.limit.is_some() || self.offset.is_some()).then(|| self.limit())
+    }
+}
+
+/// Page size asked by the client, 20 by default and 100 at most
+pub fn page_limit(limit: Option<usize>) -> i64 {
+    std::cmp::min(limit.unwrap_or(20), 100) as i64
+}
+
+/// Number of items to skip asked by the client, an offset past `i64::MAX` is read as `i64::MAX`
+pub fn page_offset(offset: Option<usize>) -> i64 {
+    i64::try_from(offset.unwrap_or(0)).unwrap_or(i64::MAX)
+}
+
+/// Where a page sits in the whole list, used to build the pagination headers
+pub struct Pagination {
+    pub total: i64,
+    pub limit: i64,
+    pub offset: i64,
+    /// Set when the page was read with a cursor, the prev and next links then carry the cursors
+    pub cursors: Option<(Option<String>, Option<String>)>,
+}
+
+impl Pagination {
+    pub fn offset(total: i64, limit: i64, offset: i64) -> Self {
+        Pagination { total, limit, offset, cursors: None }
+    }
+}
+
+/// 200 response with `X-Total-Count` and an RFC 8288 `Link` header to the first, prev, next and last pages
+///
+/// The links repeat the query string of the request with only the paging parameters replaced
+pub fn paginated_response<T: Serialize>(req: &HttpRequest, body: &T, pagination: &Pagination) -> HttpResponse {
+    let connection_info = req.connection_info();
+    let base_query: Vec<&str> = req
+        .query_string()
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .filter(|pair| !matches!(pair.split('=').next(), Some("limit" | "offset" | "cursor")))
+        .collect();
+    let link = |page: String| {
+        let mut query = base_query.clone();
+        query.push(&page);
+        format!("<{}://{}{}?{}>", connection_info.scheme(), connection_info.host(), req.path(), query.join("&"))
+    };
+
+    let limit = pagination.limit.max(1);
+    let last_offset = if pagination.total > 0 { (pagination.total - 1) / limit * limit } else { 0 };
+
+    let mut links = vec![format!("{}; rel=\"first\"", link(format!("limit={}&offset=0", limit)))];
+
+    match &pagination.cursors {
+        Some((prev_cursor, next_cursor)) => {
+            if let Some(cursor) = prev_cursor {
+                links.push(format!("{}; rel=\"prev\"", link(format!("limit={}&cursor={}", limit, cursor))));
+            }
+            if let Some(cursor) = next_cursor {
+                links.push(format!("{}; rel=\"next\"", link(format!("limit={}&cursor={}", limit, cursor))));
+            }
+        }
+        None => {
+            if pagination.offset > 0 {
+                let prev_offset = (pagination.offset - limit).max(0);
+                links.push(format!("{}; rel=\"prev\"", link(format!("limit={}&offset={}", limit, prev_offset))));
+            }
+            if pagination.offset.saturating_add(limit) < pagination.total {
+                let next_offset = pagination.offset + limit;
+                links.push(format!("{}; rel=\"next\"", link(format!("limit={}&offset={}", limit, next_offset))));
+            }
+        }
+    }
+
+    links.push(format!("{}; rel=\"last\"", link(format!("limit={}&offset={}", limit, last_offset))));
+
+    HttpResponse::Ok()
+        .insert_header(("X-Total-Count", pagination.total.to_string()))
+        .insert_header(("Link", links.join(", ")))
+        .json(body)
+}
@@ -0,0 +1,35 @@
+use std::time::Duration;
+
+use sqlx::PgPool;
+
+use crate::errors::Error as AppError;
+
+/// Publish the drafts whose scheduled `published_at` has passed, returns how many went out
+///
+/// A draft of an author whose email address is not verified waits until it is
+pub async fn publish_due_articles(pool: &PgPool) -> Result<u64, AppError> {
+    let result = sqlx::query(r#"
+        UPDATE articles SET status = 'published'
+        WHERE status = 'draft' AND published_at <= CURRENT_TIMESTAMP
+            AND EXISTS (SELECT 1 FROM users WHERE users.id = articles.author_id AND users.email_verified_at IS NOT NULL)
+    "#)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+/// Publish the due drafts now and then every `every` for as long as the server runs
+pub fn spawn_scheduled_publishing(pool: PgPool, every: Duration) {
+    actix_web::rt::spawn(async move {
+        let mut interval = actix_web::rt::time::interval(every.max(Duration::from_secs(1)));
+
+        loop {
+            interval.tick().await;
+
+            if let Err(err) = publish_due_articles(&pool).await {
+                eprintln!("Publishing Error: failed to publish the scheduled articles: {:?}", err);
+            }
+        }
+    });
+}
@@ -0,0 +1,270 @@
+use actix_web::{web, HttpResponse};
+use sqlx::{self, PgPool};
+use uuid::Uuid;
+
+use crate::audit::{AuditContext, AuditEventKind};
+use crate::auth::{Admin, RequireRole, Role};
+use crate::schemas::*;
+use crate::errors::Error as AppError;
+
+/// Return the roles of a User
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/users/{username}/roles",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Success", body = UserRolesResponse),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Forbidden"),
+        (status = 404, description = "Not Found")
+    ),
+    params(
+        ("username" = String, Path, description = "Username of a user"),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn get_user_roles(
+    (path, _admin, pool): (web::Path<UserRolesPath>, RequireRole<Admin>, web::Data<PgPool>)
+) -> Result<HttpResponse, AppError> {
+    let pool = pool.get_ref();
+
+    match find_user_id(&path.username, pool).await? {
+        Some(user_id) => {
+            let roles = select_roles(user_id, pool).await?;
+
+            Ok(HttpResponse::Ok().json(UserRolesResponse { username: path.username.clone(), roles }))
+        }
+        None => Ok(user_not_found()),
+    }
+}
+
+/// Grant a role to a User
+#[utoipa::path(
+    put,
+    path = "/api/v1/admin/users/{username}/roles/{role}",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Success", body = UserRolesResponse),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Forbidden"),
+        (status = 404, description = "Not Found")
+    ),
+    params(
+        ("username" = String, Path, description = "Username of a user"),
+        ("role" = Role, Path, description = "Role to grant"),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn grant_user_role(
+    (path, admin, pool, audit): (web::Path<UserRolePath>, RequireRole<Admin>, web::Data<PgPool>, AuditContext)
+) -> Result<HttpResponse, AppError> {
+    let pool = pool.get_ref();
+
+    match find_user_id(&path.username, pool).await? {
+        Some(user_id) => {
+            let granted = sqlx::query("INSERT INTO user_roles (user_id, role) VALUES ($1, $2) ON CONFLICT DO NOTHING")
+                .bind(user_id)
+                .bind(path.role)
+                .execute(pool)
+                .await?
+                .rows_affected() > 0;
+
+            if granted {
+                audit
+                    .record(AuditEventKind::RoleGranted, Some(admin.id), role_change_details(user_id, &path))
+                    .await;
+            }
+
+            let roles = select_roles(user_id, pool).await?;
+
+            Ok(HttpResponse::Ok().json(UserRolesResponse { username: path.username.clone(), roles }))
+        }
+        None => Ok(user_not_found()),
+    }
+}
+
+/// Revoke a role from a User
+#[utoipa::path(
+    delete,
+    path = "/api/v1/admin/users/{username}/roles/{role}",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Success", body = UserRolesResponse),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Forbidden"),
+        (status = 404, description = "Not Found")
+    ),
+    params(
+        ("username" = String, Path, description = "Username of a user"),
+        ("role" = Role, Path, description = "Role to revoke"),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn revoke_user_role(
+    (path, admin, pool, audit): (web::Path<UserRolePath>, RequireRole<Admin>, web::Data<PgPool>, AuditContext)
+) -> Result<HttpResponse, AppError> {
+    let pool = pool.get_ref();
+
+    match find_user_id(&path.username, pool).await? {
+        Some(user_id) => {
+            // Keep at least one way back in, an admin cannot drop their own admin role
+            if user_id == admin.id && path.role == Role::Admin {
+                return Err(AppError::UnprocessableEntity(serde_json::json!({
+                    "error": "You cannot revoke your own admin role",
+                })));
+            }
+
+            let revoked = sqlx::query("DELETE FROM user_roles WHERE user_id = $1 AND role = $2")
+                .bind(user_id)
+                .bind(path.role)
+                .execute(pool)
+                .await?
+                .rows_affected() > 0;
+
+            if revoked {
+                audit
+                    .record(AuditEventKind::RoleRevoked, Some(admin.id), role_change_details(user_id, &path))
+                    .await;
+            }
+
+            let roles = select_roles(user_id, pool).await?;
+
+            Ok(HttpResponse::Ok().json(UserRolesResponse { username: path.username.clone(), roles }))
+        }
+        None => Ok(user_not_found()),
+    }
+}
+
+/// Query the security audit log
+///
+/// Newest events first, every filter is optional
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/audit-events",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Success", body = AuditEventListResponse),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Forbidden")
+    ),
+    params(
+        ("kind" = Option<AuditEventKind>, Query, description = "Only events of this kind"),
+        ("actor_id" = Option<Uuid>, Query, description = "Only events of this User"),
+        ("ip_address" = Option<String>, Query, description = "Only events from this address"),
+        ("since" = Option<String>, Query, description = "Only events at or after this time, e.g. 2026-10-01T00:00:00"),
+        ("until" = Option<String>, Query, description = "Only events before this time"),
+        ("limit" = Option<usize>, Query, description = "Page size, 20 by default and 100 at most"),
+        ("offset" = Option<usize>, Query, description = "Number of events to skip"),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn get_audit_events(
+    (params, _admin, pool): (web::Query<AuditEventsParams>, RequireRole<Admin>, web::Data<PgPool>)
+) -> Result<HttpResponse, AppError> {
+    let limit = std::cmp::min(params.limit.unwrap_or(20), 100) as i64;
+    let offset = params.offset.unwrap_or(0) as i64;
+
+    let events = sqlx::query_as!(
+        AuditEvent,
+        r#"
+            SELECT
+                e.id,
+                e.kind AS "kind: AuditEventKind",
+                e.actor_id,
+                u.username AS "actor_username?",
+                e.ip_address,
+                e.user_agent,
+                e.details,
+                e.created_at
+            FROM audit_events AS e
+            LEFT JOIN users AS u ON u.id = e.actor_id
+            WHERE ($1::audit_event_kind IS NULL OR e.kind = $1)
+                AND ($2::uuid IS NULL OR e.actor_id = $2)
+                AND ($3::text IS NULL OR e.ip_address = $3)
+                AND ($4::timestamp IS NULL OR e.created_at >= $4)
+                AND ($5::timestamp IS NULL OR e.created_at < $5)
+            ORDER BY e.created_at DESC, e.id
+            LIMIT $6 OFFSET $7
+        "#,
+        params.kind as Option<AuditEventKind>,
+        params.actor_id,
+        params.ip_address,
+        params.since,
+        params.until,
+        limit,
+        offset
+    )
+    .fetch_all(pool.get_ref())
+    .await?;
+
+    let total = sqlx::query_scalar!(
+        r#"
+            SELECT COUNT(*) AS "total!" FROM audit_events
+            WHERE ($1::audit_event_kind IS NULL OR kind = $1)
+                AND ($2::uuid IS NULL OR actor_id = $2)
+                AND ($3::text IS NULL OR ip_address = $3)
+                AND ($4::timestamp IS NULL OR created_at >= $4)
+                AND ($5::timestamp IS NULL OR created_at < $5)
+        "#,
+        params.kind as Option<AuditEventKind>,
+        params.actor_id,
+        params.ip_address,
+        params.since,
+        params.until
+    )
+    .fetch_one(pool.get_ref())
+    .await?;
+
+    let audit_events = events
+        .into_iter()
+        .map(|event| AuditEventResponseInner {
+            id: event.id,
+            kind: event.kind,
+            actor_id: event.actor_id,
+            actor_username: event.actor_username,
+            ip_address: event.ip_address,
+            user_agent: event.user_agent,
+            details: event.details,
+            created_at: CustomDateTime(event.created_at),
+        })
+        .collect();
+
+    Ok(HttpResponse::Ok().json(AuditEventListResponse { audit_events, total }))
+}
+
+// Some helpers for this route ------------------------------------------------------------
+async fn find_user_id(username: &str, pool: &PgPool) -> Result<Option<Uuid>, AppError> {
+    let user_id = sqlx::query_as::<_, (Uuid,)>("SELECT id FROM users WHERE username = $1")
+        .bind(username)
+        .fetch_optional(pool)
+        .await?
+        .map(|(id,)| id);
+
+    Ok(user_id)
+}
+
+async fn select_roles(user_id: Uuid, pool: &PgPool) -> Result<Vec<Role>, AppError> {
+    let roles = sqlx::query_as::<_, (Role,)>("SELECT role FROM user_roles WHERE user_id = $1 ORDER BY role")
+        .bind(user_id)
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .map(|(role,)| role)
+        .collect();
+
+    Ok(roles)
+}
+
+fn role_change_details(user_id: Uuid, path: &UserRolePath) -> serde_json::Value {
+    serde_json::json!({
+        "user_id": user_id,
+        "username": path.username,
+        "role": path.role,
+    })
+}
+
+fn user_not_found() -> HttpResponse {
+    HttpResponse::NotFound().json(serde_json::json!({
+        "message": "Record not found for the provided username",
+    }))
+}
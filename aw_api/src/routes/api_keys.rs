@@ -0,0 +1,133 @@
+use actix_web::{web, HttpResponse};
+use sqlx::{self, PgPool};
+use validator::Validate;
+
+use crate::auth::{ApiScope, AuthUser, create_api_key, revoke_api_key};
+use crate::schemas::*;
+use crate::errors::Error as AppError;
+use crate::utils::validation_errors_response;
+
+/// Create an API key for the current User
+///
+/// Scripts send the key in the `X-Api-Key` header instead of logging in, it can only do what its scopes allow.
+/// The key is only returned by this request, store it right away
+#[utoipa::path(
+    post,
+    path = "/api/v1/users/api-keys",
+    tag = "users",
+    responses(
+        (status = 201, description = "Created", body = CreatedApiKeyResponse),
+        (status = 400, description = "Bad request"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "API keys cannot create API keys")
+    ),
+    request_body = CreateApiKey,
+    security(("bearer_auth" = []))
+)]
+pub async fn add_api_key(
+    (form, auth, pool): (web::Json<CreateApiKey>, AuthUser, web::Data<PgPool>)
+) -> Result<HttpResponse, AppError> {
+    auth.require_session()?;
+
+    let mut new_key = form.into_inner();
+
+    // Validate the user input
+    let validation_result = new_key.validate();
+    if let Err(validation_errors) = validation_result {
+        return Ok(validation_errors_response(&validation_errors));
+    }
+
+    new_key.scopes.sort_by_key(|scope| scope.as_str());
+    new_key.scopes.dedup();
+
+    let created = create_api_key(auth.id, &new_key.name, &new_key.scopes, new_key.expires_in_days, pool.get_ref()).await?;
+
+    Ok(HttpResponse::Created().json(CreatedApiKeyResponse {
+        id: created.id,
+        name: new_key.name,
+        prefix: created.prefix,
+        scopes: new_key.scopes,
+        expires_at: created.expires_at.map(CustomDateTime),
+        key: created.key,
+    }))
+}
+
+/// Return the active API keys of the current User
+///
+/// Only the prefix of each key is returned, expired keys stay listed until they are revoked
+#[utoipa::path(
+    get,
+    path = "/api/v1/users/api-keys",
+    tag = "users",
+    responses(
+        (status = 200, description = "Success", body = ApiKeyListResponse),
+        (status = 401, description = "Unauthorized")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn get_api_keys(
+    (auth, pool): (AuthUser, web::Data<PgPool>)
+) -> Result<HttpResponse, AppError> {
+    auth.require_session()?;
+
+    let api_keys = sqlx::query_as!(
+        ApiKey,
+        r#"
+            SELECT id, name, prefix, scopes AS "scopes: Vec<ApiScope>", expires_at, last_used_at, created_at
+            FROM api_keys
+            WHERE user_id = $1 AND revoked_at IS NULL
+            ORDER BY created_at
+        "#,
+        auth.id
+    )
+    .fetch_all(pool.get_ref())
+    .await?;
+
+    let api_keys = api_keys
+        .into_iter()
+        .map(|api_key| ApiKeyResponseInner {
+            id: api_key.id,
+            name: api_key.name,
+            prefix: api_key.prefix,
+            scopes: api_key.scopes,
+            expires_at: api_key.expires_at.map(CustomDateTime),
+            last_used_at: api_key.last_used_at.map(CustomDateTime),
+            created_at: CustomDateTime(api_key.created_at),
+        })
+        .collect();
+
+    Ok(HttpResponse::Ok().json(ApiKeyListResponse { api_keys }))
+}
+
+/// Revoke an API key of the current User
+///
+/// The key stops working right away
+#[utoipa::path(
+    delete,
+    path = "/api/v1/users/api-keys/{id}",
+    tag = "users",
+    responses(
+        (status = 200, description = "Success"),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Not Found")
+    ),
+    params(
+        ("id" = Uuid, Path, description = "API key id"),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn delete_api_key(
+    (path, auth, pool): (web::Path<ApiKeyPath>, AuthUser, web::Data<PgPool>)
+) -> Result<HttpResponse, AppError> {
+    auth.require_session()?;
+
+    if revoke_api_key(path.id, auth.id, pool.get_ref()).await? {
+        Ok(HttpResponse::Ok().json(serde_json::json!({
+            "message": "API key revoked successfully",
+        })))
+    } else {
+        Ok(HttpResponse::NotFound().json(serde_json::json!({
+            "message": "Record not found for the provided id",
+        })))
+    }
+}
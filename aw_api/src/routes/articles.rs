@@ -1,69 +1,75 @@
-use actix_web::{web, HttpResponse, ResponseError, http::StatusCode};
-use futures::future::{join_all, FutureExt};
-use futures::future::try_join_all;
+use actix_web::{web, HttpRequest, HttpResponse, ResponseError, http::StatusCode};
+use std::collections::HashMap;
 use sqlx::postgres::PgRow;
-use sqlx::{self, PgPool};
+use sqlx::{self, PgConnection, PgPool, Postgres, QueryBuilder};
 use sluggify::sluggify::sluggify;
+use deunicode::deunicode;
 use blob_uuid::to_blob;
 use uuid::Uuid;
 use sqlx::Row;
 use sqlx::FromRow;
 use validator::Validate;
 
+use crate::auth::{ApiScope, AuthUser, MaybeAuthUser, Permission};
+use crate::audit::{AuditContext, AuditEventKind};
 use crate::schemas::*;
 use crate::errors::Error as AppError;
+use crate::pagination::{page_limit, page_offset, paginated_response, Cursor, CursorDirection, Page, PageParams, Pagination};
+use crate::settings::ApplicationSettings;
+use crate::trending::record_view;
+use crate::markdown::render_markdown;
+
+use super::revisions::record_revision;
 use crate::utils::validation_errors_response;
 
 /// Return article list
+///
+/// Filters can be combined, an article has to match all of them.
+/// Pages are walked with `next_cursor` and `prev_cursor`, `offset` still works for older clients.
+/// `articles_count` and the `X-Total-Count` header hold the number of matching articles, the `Link` header the other pages
 #[utoipa::path(
     get,
     path = "/api/v1/articles",
     tag = "articles",
     responses(
-        (status = 200, description = "Success"),
+        (status = 200, description = "Success", body = ArticleListResponse),
         (status = 400, description = "Bad request")
-    )
+    ),
+    params(
+        ("tag" = Option<String>, Query, description = "Comma separated tags, the article has to carry all of them"),
+        ("author" = Option<String>, Query, description = "Comma separated usernames, the article is by one of them"),
+        ("favorited" = Option<String>, Query, description = "Username of a user who favorited the article"),
+        ("limit" = Option<usize>, Query, description = "Page size, 20 by default and 100 at most"),
+        ("offset" = Option<usize>, Query, description = "Number of articles to skip"),
+        ("cursor" = Option<String>, Query, description = "`next_cursor` or `prev_cursor` of a previous page, only for the newest, oldest and updated sorts"),
+        ("sort" = Option<ArticleSort>, Query, description = "newest (default), oldest, updated, most_favorited, most_commented or trending"),
+    ),
+    security((), ("bearer_auth" = []))
 )]
 pub async fn get_articles(
-    (params, pool): (web::Query<ArticlesParams>, web::Data<PgPool>)
+    (req, params, auth, pool): (HttpRequest, web::Query<ArticlesParams>, MaybeAuthUser, web::Data<PgPool>)
 ) -> Result<HttpResponse, AppError> {
     // Access the PgPool from the Data container
     let pool = pool.get_ref();
 
-    let mut query = format!("SELECT * FROM articles AS a");
-
-    if let Some(ref author_name) = params.author {
-        query.push_str(" INNER JOIN users AS u ON u.id = a.author_id");
-        query.push_str(&format!(" WHERE u.username = '{}'", author_name));
-    }
-
-    if let Some(ref username_favorited_by) = params.favorited {
-        query.push_str(" INNER JOIN favorite_articles AS fa ON fa.article_id = a.id");
-        query.push_str(" INNER JOIN users AS u ON u.id = fa.user_id");
-        query.push_str(&format!(" WHERE u.username = '{}'", username_favorited_by));
-    }
-
-    if let Some(ref tag) = params.tag {
-        query.push_str(" INNER JOIN article_tags AS at ON at.article_id = a.id");
-        query.push_str(&format!(" WHERE at.tag_name = '{}'", tag));
-    }
+    let limit = page_limit(params.limit);
+    let offset = page_offset(params.offset);
 
-    let limit = std::cmp::min(params.limit.unwrap_or(20), 100) as i64;
-    let offset = params.offset.unwrap_or(0) as i64;
+    let mut query = QueryBuilder::new(format!("SELECT a.* FROM {} WHERE a.status = 'published'", article_source(params.sort)));
+    push_article_filters(&mut query, &params);
 
-    query.push_str(" ORDER BY a.created_at DESC");
-    query.push_str(&format!(" LIMIT {} OFFSET {}", limit, offset));
+    let mut count_query = QueryBuilder::new("SELECT COUNT(*) FROM articles AS a WHERE a.status = 'published'");
+    push_article_filters(&mut count_query, &params);
 
-    let matched_articles: Vec<Article> = sqlx::query_as(&query)
-        .fetch_all(pool)
-        .await?;
+    let total: i64 = count_query.build_query_scalar().fetch_one(pool).await?;
+    let page = fetch_article_page(query, params.sort, limit, offset, params.cursor.as_deref(), pool).await?;
 
-    let user_id: Option<Uuid> = None; // Replace with the actual user_id
-    let response = get_article_list_response(matched_articles, user_id, &pool).await;
+    let response = get_article_page_response(page, total, auth.id(), pool).await;
 
     match response {
         Ok(article_list_response) => {
-            Ok(HttpResponse::Ok().json(article_list_response))
+            let pagination = article_pagination(&article_list_response, limit, offset, params.cursor.is_some());
+            Ok(paginated_response(&req, &article_list_response, &pagination))
         }
         Err(e) => {
             Err(e)
@@ -71,25 +77,229 @@ pub async fn get_articles(
     }
 }
 
-/// Create an article
+/// Search articles
+///
+/// Full-text search over the title, description and body, in that order of weight, best matches first.
+/// Only articles written in `lang` are searched so the words are stemmed the same way, the default language otherwise.
+/// The `X-Total-Count` header holds the number of matches and the `Link` header the other pages
 #[utoipa::path(
-    post,
-    path = "/api/v1/articles/{username}",
+    get,
+    path = "/api/v1/articles/search",
     tag = "articles",
     responses(
-        (status = 201, description = "Created", body = CreateArticle),
+        (status = 200, description = "Success", body = ArticleSearchResponse),
+        (status = 400, description = "Bad request")
+    ),
+    params(
+        ("q" = String, Query, description = "Search terms, quoted phrases, `or` and `-word` are understood"),
+        ("lang" = Option<String>, Query, description = "Text search configuration, e.g. english or indonesian"),
+        ("limit" = Option<usize>, Query, description = "Page size, 20 by default and 100 at most"),
+        ("offset" = Option<usize>, Query, description = "Number of articles to skip"),
+    ),
+    security((), ("bearer_auth" = []))
+)]
+pub async fn search_articles(
+    (req, params, auth, pool, settings): (HttpRequest, web::Query<ArticleSearchParams>, MaybeAuthUser, web::Data<PgPool>, web::Data<ApplicationSettings>)
+) -> Result<HttpResponse, AppError> {
+    // Validate the user input
+    let validation_result = params.validate();
+    if let Err(validation_errors) = validation_result {
+        return Ok(validation_errors_response(&validation_errors));
+    }
+
+    let language = search_language(params.lang.as_deref(), &settings)?;
+
+    let limit = page_limit(params.limit);
+    let offset = page_offset(params.offset);
+
+    let pool = pool.get_ref();
+
+    // The text is escaped before `ts_headline` adds the `<mark>` tags, so the snippet is safe to render as HTML
+    let rows = sqlx::query(r#"
+        SELECT
+            a.*,
+            ts_headline(
+                a.search_language,
+                replace(replace(replace(a.description || ' ' || a.body, '&', '&amp;'), '<', '&lt;'), '>', '&gt;'),
+                query,
+                'StartSel=<mark>, StopSel=</mark>, MaxWords=35, MinWords=15, MaxFragments=2'
+            ) AS snippet
+        FROM articles AS a, websearch_to_tsquery($1::regconfig, $2) AS query
+        WHERE a.status = 'published' AND a.search_language = $1::regconfig AND a.search_vector @@ query
+        ORDER BY ts_rank(a.search_vector, query) DESC, a.created_at DESC
+        LIMIT $3 OFFSET $4
+    "#)
+    .bind(&language)
+    .bind(&params.q)
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(pool)
+    .await?;
+
+    let total: i64 = sqlx::query_scalar(r#"
+        SELECT COUNT(*)
+        FROM articles AS a, websearch_to_tsquery($1::regconfig, $2) AS query
+        WHERE a.status = 'published' AND a.search_language = $1::regconfig AND a.search_vector @@ query
+    "#)
+    .bind(&language)
+    .bind(&params.q)
+    .fetch_one(pool)
+    .await?;
+
+    let mut matched_articles = Vec::with_capacity(rows.len());
+    let mut snippets = HashMap::with_capacity(rows.len());
+    for row in rows {
+        let article = Article::from_row(&row)?;
+        snippets.insert(article.slug.clone(), row.try_get::<String, _>("snippet")?);
+        matched_articles.push(article);
+    }
+
+    let article_list = get_article_list_response(matched_articles, auth.id(), pool).await?;
+
+    // Matched by slug, an article deleted meanwhile is missing from the list
+    let articles: Vec<ArticleSearchResult> = article_list
+        .articles
+        .into_iter()
+        .map(|article| {
+            let snippet = snippets.remove(&article.slug).unwrap_or_default();
+            ArticleSearchResult { article, snippet }
+        })
+        .collect();
+
+    let search_response = ArticleSearchResponse {
+        articles,
+        articles_count: total as usize,
+    };
+    let pagination = Pagination::offset(total, limit, offset);
+
+    Ok(paginated_response(&req, &search_response, &pagination))
+}
+
+/// Trending articles
+///
+/// Ranked by recent favorites, comments and views, each worth less the older it is.
+/// The ranking is recomputed every few minutes, not on each request
+#[utoipa::path(
+    get,
+    path = "/api/v1/articles/trending",
+    tag = "articles",
+    responses(
+        (status = 200, description = "Success", body = ArticleListResponse),
         (status = 400, description = "Bad request")
     ),
     params(
-        ("username" = String, Path, description = "Username of a user"),
+        ("limit" = Option<usize>, Query, description = "Page size, 20 by default and 100 at most"),
+        ("offset" = Option<usize>, Query, description = "Number of articles to skip"),
+    ),
+    security((), ("bearer_auth" = []))
+)]
+pub async fn get_trending_articles(
+    (req, page, auth, pool): (HttpRequest, web::Query<PageParams>, MaybeAuthUser, web::Data<PgPool>)
+) -> Result<HttpResponse, AppError> {
+    let pool = pool.get_ref();
+
+    let trending_articles = sqlx::query_as::<_, Article>(r#"
+        SELECT a.*
+        FROM article_trending_scores AS t
+        INNER JOIN articles AS a ON a.id = t.article_id
+        WHERE a.status = 'published'
+        ORDER BY t.score DESC, a.created_at DESC, a.id DESC
+        LIMIT $1 OFFSET $2
+    "#)
+    .bind(page.limit())
+    .bind(page.offset())
+    .fetch_all(pool)
+    .await?;
+
+    let total: i64 = sqlx::query_scalar(r#"
+        SELECT COUNT(*)
+        FROM article_trending_scores AS t
+        INNER JOIN articles AS a ON a.id = t.article_id
+        WHERE a.status = 'published'
+    "#)
+        .fetch_one(pool)
+        .await?;
+
+    let mut article_list = get_article_list_response(trending_articles, auth.id(), pool).await?;
+    article_list.articles_count = total as usize;
+
+    let pagination = Pagination::offset(total, page.limit(), page.offset());
+
+    Ok(paginated_response(&req, &article_list, &pagination))
+}
+
+/// Return your drafts
+///
+/// Scheduled drafts, the ones with a `published_at`, come first in the order they go out
+#[utoipa::path(
+    get,
+    path = "/api/v1/articles/drafts",
+    tag = "articles",
+    responses(
+        (status = 200, description = "Success", body = ArticleListResponse),
+        (status = 401, description = "Unauthorized")
+    ),
+    params(
+        ("limit" = Option<usize>, Query, description = "Page size, 20 by default and 100 at most"),
+        ("offset" = Option<usize>, Query, description = "Number of drafts to skip"),
     ),
-    request_body = CreateArticle
+    security(("bearer_auth" = []), ("api_key" = []))
+)]
+pub async fn get_article_drafts(
+    (req, page, auth, pool): (HttpRequest, web::Query<PageParams>, AuthUser, web::Data<PgPool>)
+) -> Result<HttpResponse, AppError> {
+    auth.require_scope(ApiScope::ArticlesRead)?;
+
+    let pool = pool.get_ref();
+
+    let drafts = sqlx::query_as::<_, Article>(r#"
+        SELECT * FROM articles
+        WHERE author_id = $1 AND status = 'draft'
+        ORDER BY published_at ASC NULLS LAST, created_at DESC, id DESC
+        LIMIT $2 OFFSET $3
+    "#)
+    .bind(auth.id)
+    .bind(page.limit())
+    .bind(page.offset())
+    .fetch_all(pool)
+    .await?;
+
+    let total: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM articles WHERE author_id = $1 AND status = 'draft'")
+        .bind(auth.id)
+        .fetch_one(pool)
+        .await?;
+
+    let mut article_list = get_article_list_response(drafts, Some(auth.id), pool).await?;
+    article_list.articles_count = total as usize;
+
+    let pagination = Pagination::offset(total, page.limit(), page.offset());
+
+    Ok(paginated_response(&req, &article_list, &pagination))
+}
+
+/// Create an article
+///
+/// Only Users with a verified email address can publish or schedule, anyone can save a draft
+#[utoipa::path(
+    post,
+    path = "/api/v1/articles",
+    tag = "articles",
+    responses(
+        (status = 201, description = "Created", body = CreateArticle),
+        (status = 400, description = "Bad request"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Email address not verified"),
+        (status = 422, description = "The chosen slug is taken")
+    ),
+    request_body = CreateArticle,
+    security(("bearer_auth" = []), ("api_key" = []))
 )]
 pub async fn create_article(
-    (form, username, pool): (web::Json<CreateArticle>, web::Path<UserForArticle>, web::Data<PgPool>)
+    (form, auth, pool, audit, settings): (web::Json<CreateArticle>, AuthUser, web::Data<PgPool>, AuditContext, web::Data<ApplicationSettings>)
 ) -> Result<HttpResponse, AppError> {
+    auth.require_scope(ApiScope::ArticlesWrite)?;
+
     let article_data = form.into_inner();
-    let user_info = username.into_inner();
 
     // Validate the user input
     let article_validation_result = article_data.validate();
@@ -97,35 +307,56 @@ pub async fn create_article(
         return Ok(validation_errors_response(&validation_errors));
     }
 
-    // Validate the user input
-    let user_validation_result = user_info.validate();
-    if let Err(validation_errors) = user_validation_result {
-        return Ok(validation_errors_response(&validation_errors));
+    let language = search_language(article_data.language.as_deref(), &settings)?;
+
+    let status = article_data.status.unwrap_or(ArticleStatus::Published);
+    match (status, article_data.published_at) {
+        (ArticleStatus::Archived, _) => {
+            return Err(AppError::BadRequest(serde_json::json!({
+                "error": "A new article is either a draft or published",
+            })));
+        }
+        (ArticleStatus::Published, Some(_)) => {
+            return Err(AppError::BadRequest(serde_json::json!({
+                "error": "publishedAt schedules a draft, leave it out to publish now",
+            })));
+        }
+        _ => (),
+    }
+
+    // Publishing now or on a schedule needs a verified address, drafts can be saved without one
+    let publishes = status == ArticleStatus::Published || article_data.published_at.is_some();
+    if publishes && !auth.email_verified {
+        audit
+            .record(AuditEventKind::Forbidden, Some(auth.id), serde_json::json!({
+                "action": "create_article",
+                "reason": "email_not_verified",
+            }))
+            .await;
+
+        return Err(AppError::Forbidden(serde_json::json!({
+            "error": "Please verify your email address before publishing",
+        })));
     }
 
     // Access the PgPool from the Data container
     let pool = pool.get_ref();
 
-    // Fetch the current user
-    let author= sqlx::query_as!(
-        User,
-        "SELECT * FROM users WHERE username = $1 LIMIT 1",
-        &user_info.username
-    )
-    .fetch_one(pool)
-    .await
-    .map_err(|_| {
-        AppError::InternalServerError
-    })?;
-
     // Generating the Uuid here since it will help make a unique slug
     // This is for when some articles may have similar titles such that they generate the same slug
     let new_article_id = Uuid::new_v4();
-    let slug = generate_slug(&new_article_id, &article_data.title);
+    let custom_slug = article_data.slug.is_some();
+    let slug = match article_data.slug {
+        Some(slug) => {
+            ensure_slug_available(&slug, new_article_id, pool).await?;
+            slug
+        }
+        None => generate_slug(&new_article_id, &article_data.title),
+    };
 
     let new_article = NewArticle {
         id: new_article_id,
-        author_id: author.id,
+        author_id: auth.id,
         slug,
         title: article_data.title,
         description: article_data.description,
@@ -133,18 +364,33 @@ pub async fn create_article(
     };
 
     // Create a query and bind parameters
-    let query = sqlx::query("INSERT INTO articles (id, author_id, slug, title, description, body) VALUES ($1, $2, $3, $4, $5, $6)")
-        .bind(&new_article.id)
-        .bind(&new_article.author_id)
+    let query = sqlx::query(r#"
+        INSERT INTO articles (id, author_id, slug, custom_slug, title, description, body, body_html, search_language, status, published_at)
+        VALUES (
+            $1, $2, $3, $11, $4, $5, $6, $10, $7::regconfig, $8,
+            CASE WHEN $8 = 'published' THEN CURRENT_TIMESTAMP ELSE $9 END
+        )
+    "#)
+        .bind(new_article.id)
+        .bind(new_article.author_id)
         .bind(&new_article.slug)
         .bind(&new_article.title)
         .bind(&new_article.description)
-        .bind(&new_article.body);
+        .bind(&new_article.body)
+        .bind(language)
+        .bind(status)
+        .bind(article_data.published_at.map(|published_at| published_at.naive_utc()))
+        .bind(render_markdown(&new_article.body))
+        .bind(custom_slug);
 
-    // Execute the query on the pool
-    match query.execute(pool).await {
+    // The tags and the first revision are written with the article or not at all
+    let mut transaction = pool.begin().await?;
+
+    match query.execute(&mut *transaction).await {
         Ok(_) => {
-            let _ = replace_tags(new_article.id, article_data.tag_list, pool).await?;
+            replace_tags(new_article.id, article_data.tag_list, &mut transaction).await?;
+            record_revision(new_article.id, auth.id, None, &mut transaction).await?;
+            transaction.commit().await?;
 
             // Fetch the article response after it's created
             let article_response = get_article_response(new_article.slug, Some(new_article.author_id), pool).await?;
@@ -164,101 +410,91 @@ pub async fn create_article(
 /// It is quite similar to get all articles, but this one supposed to be use to get article from someone you've followed and an options `limit` and `offset`
 #[utoipa::path(
     get,
-    path = "/api/v1/articles/feed/{username}",
+    path = "/api/v1/articles/feed",
     tag = "articles",
     responses(
-        (status = 200, description = "Success", body = CreateArticle),
-        (status = 400, description = "Bad request")
+        (status = 200, description = "Success", body = ArticleListResponse),
+        (status = 400, description = "Bad request"),
+        (status = 401, description = "Unauthorized")
     ),
     params(
-        ("username" = String, Path, description = "Username of a user"),
         ("limit" = i64, Query, description = "Limit article output", minimum = 20),
-        ("offset" = i64, Query, description = "Offset article output", minimum = 0)
+        ("offset" = i64, Query, description = "Offset article output", minimum = 0),
+        ("cursor" = Option<String>, Query, description = "`next_cursor` or `prev_cursor` of a previous page, `offset` is ignored when it is given")
     ),
+    security(("bearer_auth" = []), ("api_key" = []))
 )]
 pub async fn get_articles_feed(
-    (params, username, pool): (web::Query<FeedParams>, web::Path<UserForArticle>, web::Data<PgPool>)
+    (req, params, auth, pool): (HttpRequest, web::Query<FeedParams>, AuthUser, web::Data<PgPool>)
 ) -> Result<HttpResponse, AppError> {
+    auth.require_scope(ApiScope::ArticlesRead)?;
+
     // TODO: use query string for limit & offset payload
     // Access the PgPool from the Data container
     let pool = pool.get_ref();
 
-    let user_info = username.into_inner();
-
-    // Validate the user input
-    let user_validation_result = user_info.validate();
-    if let Err(validation_errors) = user_validation_result {
-        return Ok(validation_errors_response(&validation_errors));
-    }
-
-    // Fetch the current user
-    let author= sqlx::query_as!(
-        User,
-        "SELECT * FROM users WHERE username = $1 LIMIT 1",
-        &user_info.username
-    )
-    .fetch_one(pool)
-    .await
-    .map_err(|_| {
-        AppError::InternalServerError
-    })?;
-
-    let limit = std::cmp::min(params.limit.unwrap_or(20), 100) as i64;
-    let offset = params.offset.unwrap_or(0) as i64;
+    let limit = page_limit(params.limit);
+    let offset = page_offset(params.offset);
 
-    let user_id = author.id;
+    let user_id = auth.id;
 
-    let following_ids: Vec<Uuid> = sqlx::query_as::<_, (Uuid,)>("SELECT user_id FROM followers WHERE follower_id = $1")
-        .bind(&user_id)
-        .fetch_all(pool)
-        .await?
-        .into_iter()
-        .map(|(id,)| id)
-        .collect();
-
-    // Query articles based on the following_ids.
-    let articles = sqlx::query_as::<_, Article>(
-        "SELECT id, created_at FROM articles WHERE author_id = ANY($1) ORDER BY created_at DESC LIMIT $2 OFFSET $3"
-    )
-    .bind(&following_ids)
-    .bind(&limit)
-    .bind(&offset);
+    // Query articles from the authors the user follows
+    let query = feed_query("SELECT a.*", user_id);
+    let total: i64 = feed_query("SELECT COUNT(*)", user_id)
+        .build_query_scalar()
+        .fetch_one(pool)
+        .await?;
 
     // Execute the query on the pool
-    match articles.fetch_all(pool).await {
-        Ok(res) => {
-            let article_response = get_article_list_response(res, Some(user_id), pool).await?;
+    match fetch_article_page(query, ArticleSort::Newest, limit, offset, params.cursor.as_deref(), pool).await {
+        Ok(page) => {
+            let article_response = get_article_page_response(page, total, Some(user_id), pool).await?;
+            let pagination = article_pagination(&article_response, limit, offset, params.cursor.is_some());
 
             // Return the article response as an HTTP response
-            Ok(HttpResponse::Ok().json(article_response))
-        }
-        Err(err) => {
-            let custom_err: AppError = err.into();
-            Ok(custom_err.error_response())
+            Ok(paginated_response(&req, &article_response, &pagination))
         }
+        Err(err) => Ok(err.error_response()),
     }
 }
 
 /// Return a specific article
+///
+/// A slug the article had before its title changed answers 301 with the current one in the `Location` header
 #[utoipa::path(
     get,
     path = "/api/v1/articles/data/{slug}",
     tag = "articles",
     responses(
         (status = 200, description = "Success"),
-        (status = 400, description = "Bad request")
+        (status = 301, description = "Moved to the current slug"),
+        (status = 400, description = "Bad request"),
+        (status = 404, description = "Not found")
     ),
     params(
         ("slug" = String, Path, description = "an article slug"),
-    )
+    ),
+    security((), ("bearer_auth" = []))
 )]
 pub async fn get_articles_by_slug(
-    (path, pool): (web::Path<ArticlePath>, web::Data<PgPool>)
+    (req, path, auth, pool): (HttpRequest, web::Path<ArticlePath>, MaybeAuthUser, web::Data<PgPool>)
 ) -> Result<HttpResponse, AppError> {
     // Access the PgPool from the Data container
     let pool = pool.get_ref();
 
-    let article_response = get_article_response(path.slug.to_string(), None, pool).await?;
+    if let Err(err) = find_visible_article(&path.slug, auth.id(), pool).await {
+        return match find_renamed_article(&path.slug, auth.id(), pool).await? {
+            Some(current_slug) => Ok(redirect_to_slug(&req, &path.slug, &current_slug)),
+            None => Err(err),
+        };
+    }
+
+    let article_response = get_article_response(path.slug.to_string(), auth.id(), pool).await?;
+
+    // A failed view count should not cost the reader the article
+    if let Err(err) = record_view(&path.slug, pool).await {
+        eprintln!("Trending Error: failed to record a view of {}: {:?}", path.slug, err);
+    }
 
     // Return the article response as an HTTP response
     Ok(HttpResponse::Ok().json(article_response))
@@ -271,19 +507,22 @@ pub async fn get_articles_by_slug(
     tag = "articles",
     responses(
         (status = 200, description = "Success"),
-        (status = 400, description = "Bad request")
+        (status = 400, description = "Bad request"),
+        (status = 403, description = "Not the author, or publishing with an unverified email address"),
+        (status = 422, description = "The chosen slug is taken")
     ),
     params(
         ("slug" = String, Path, description = "an article slug"),
-        ("username" = String, Query, description = "Username of a user"),
     ),
-    request_body = UpdateArticleOuter
+    request_body = UpdateArticleOuter,
+    security(("bearer_auth" = []), ("api_key" = []))
 )]
 pub async fn update_articles_by_slug(
-    (path, username, form, pool): (web::Path<ArticlePath>, web::Query<UserForArticle>, web::Json<UpdateArticleOuter>, web::Data<PgPool>)
+    (path, auth, form, pool, audit, settings): (web::Path<ArticlePath>, AuthUser, web::Json<UpdateArticleOuter>, web::Data<PgPool>, AuditContext, web::Data<ApplicationSettings>)
 ) -> Result<HttpResponse, AppError> {
+    auth.require_scope(ApiScope::ArticlesWrite)?;
+
     let update_article = form.into_inner().article;
-    let user_info = username.into_inner();
 
     // Validate the user input
     let validation_result = update_article.validate();
@@ -291,45 +530,73 @@ pub async fn update_articles_by_slug(
         return Ok(validation_errors_response(&validation_errors));
     }
 
-    // Validate the user input
-    let validation_result = user_info.validate();
-    if let Err(validation_errors) = validation_result {
-        return Ok(validation_errors_response(&validation_errors));
-    }
-
     let pool = pool.get_ref();
 
-    // Fetch the current user
-    let user= sqlx::query_as!(
-        User,
-        "SELECT * FROM users WHERE username = $1 LIMIT 1",
-        &user_info.username
-    )
-    .fetch_one(pool)
-    .await
-    .map_err(|_| {
-        AppError::InternalServerError
-    })?;
+    // Someone else's draft is not found rather than forbidden, the same as reading it
+    let article_id = find_visible_article(&path.slug, Some(auth.id), pool).await?;
 
-    let (article_id, article_author_id, article_slug): (Uuid, Uuid, String) = sqlx::query_as::<_, (Uuid, Uuid, String)>("SELECT id, author_id, slug FROM articles WHERE slug = $1")
-        .bind(&path.slug)
+    let (article_author_id, article_status, custom_slug): (Uuid, ArticleStatus, bool) = sqlx::query_as::<_, (Uuid, ArticleStatus, bool)>("SELECT author_id, status, custom_slug FROM articles WHERE id = $1")
+        .bind(article_id)
         .fetch_one(pool)
         .await
         .map_err(|_| {
             AppError::InternalServerError
         })?;
 
-    if user.id != article_author_id {
+    if auth.id != article_author_id {
+        audit
+            .record(AuditEventKind::Forbidden, Some(auth.id), serde_json::json!({
+                "action": "update_article",
+                "slug": path.slug,
+                "reason": "not_author",
+            }))
+            .await;
+
         return Err(AppError::Forbidden(serde_json::json!({
             "error": "user is not the author of article in question",
         })));
     }
 
-    let slug = match &update_article.title {
-        Some(title) => Some(generate_slug(&article_id, &title)),
+    // A slug the author chose stays until they choose another one
+    let slug = match (&update_article.slug, &update_article.title) {
+        (Some(slug), _) => {
+            ensure_slug_available(slug, article_id, pool).await?;
+            Some(slug.to_owned())
+        }
+        (None, Some(title)) if !custom_slug => Some(generate_slug(&article_id, title)),
+        _ => None,
+    };
+
+    // The language is kept unless a new one is given
+    let language = match update_article.language.as_deref() {
+        Some(language) => Some(search_language(Some(language), &settings)?),
         None => None,
     };
 
+    // Only drafts have a schedule
+    if update_article.published_at.is_some() && update_article.status.unwrap_or(article_status) != ArticleStatus::Draft {
+        return Err(AppError::BadRequest(serde_json::json!({
+            "error": "publishedAt schedules a draft, leave it out to publish now",
+        })));
+    }
+
+    // Publishing now or on a schedule needs a verified address, the same as creating
+    let publishes = update_article.published_at.is_some()
+        || (update_article.status == Some(ArticleStatus::Published) && article_status != ArticleStatus::Published);
+    if publishes && !auth.email_verified {
+        audit
+            .record(AuditEventKind::Forbidden, Some(auth.id), serde_json::json!({
+                "action": "update_article",
+                "slug": path.slug,
+                "reason": "email_not_verified",
+            }))
+            .await;
+
+        return Err(AppError::Forbidden(serde_json::json!({
+            "error": "Please verify your email address before publishing",
+        })));
+    }
+
     let article_change = ArticleChange {
         slug,
         title: update_article.title,
@@ -337,30 +604,50 @@ pub async fn update_articles_by_slug(
         body: update_article.body,
     };
 
-    let article = sqlx::query("UPDATE articles SET slug = $1, title = $2, description = $3, body = $4 WHERE id = $5 RETURNING *")
+    let article = sqlx::query(r#"
+        UPDATE articles SET
+            slug = COALESCE($1, slug), title = COALESCE($2, title), description = COALESCE($3, description), body = COALESCE($4, body),
+            body_html = COALESCE($9, body_html),
+            custom_slug = custom_slug OR $10,
+            search_language = COALESCE($6::regconfig, search_language),
+            status = COALESCE($7, status),
+            published_at = CASE
+                -- Publishing keeps the date of an earlier publication but not a schedule still to come
+                WHEN $7 = 'published' AND (published_at IS NULL OR published_at > CURRENT_TIMESTAMP) THEN CURRENT_TIMESTAMP
+                WHEN COALESCE($7, status) = 'draft' AND ($7 IS NOT NULL OR $8 IS NOT NULL) THEN $8
+                ELSE published_at
+            END
+        WHERE id = $5
+        RETURNING *
+    "#)
         .bind(&article_change.slug)
         .bind(&article_change.title)
         .bind(&article_change.description)
         .bind(&article_change.body)
-        .bind(&article_id);
+        .bind(article_id)
+        .bind(language)
+        .bind(update_article.status)
+        .bind(update_article.published_at.map(|published_at| published_at.naive_utc()))
+        .bind(article_change.body.as_deref().map(render_markdown))
+        .bind(update_article.slug.is_some());
+
+    // The article stays locked until its revision is written so concurrent edits get their own numbers
+    let mut transaction = pool.begin().await?;
 
-    match article.fetch_one(pool).await {
+    match article.fetch_one(&mut *transaction).await {
         Ok(res) => {
             let record_article_id: Uuid = res.get("id");
             let record_author_id: Uuid = res.get("author_id");
+            let record_slug: String = res.get("slug");
 
-            let _ = match update_article.tag_list {
-                Some(tags) => {
-                    let inserted_tags = replace_tags(record_article_id, tags, pool).await?;
+            if let Some(tags) = update_article.tag_list {
+                replace_tags(record_article_id, tags, &mut transaction).await?;
+            }
+
+            record_revision(record_article_id, auth.id, None, &mut transaction).await?;
+            transaction.commit().await?;
 
-                    inserted_tags
-                        .iter()
-                        .map(|article_tag| article_tag.tag_name.to_owned())
-                        .collect::<Vec<String>>()
-                }
-                None => select_tags_on_article(res.get("id"), pool).await?,
-            };
-            let article_response: ArticleResponse = get_article_response(article_slug, Some(record_author_id), pool).await?;
+            let article_response: ArticleResponse = get_article_response(record_slug, Some(record_author_id), pool).await?;
 
             // Return the article response as an HTTP response
             Ok(HttpResponse::Ok().json(article_response))
@@ -374,44 +661,29 @@ pub async fn update_articles_by_slug(
 }
 
 /// Delete an article
+///
+/// Only the author can delete an article, or a moderator
 #[utoipa::path(
     delete,
     path = "/api/v1/articles/data/{slug}",
     tag = "articles",
     responses(
         (status = 200, description = "Success"),
-        (status = 400, description = "Bad request")
+        (status = 400, description = "Bad request"),
+        (status = 403, description = "Forbidden")
     ),
     params(
         ("slug" = String, Path, description = "an article slug"),
-        ("username" = String, Query, description = "Username of a user"),
-    )
+    ),
+    security(("bearer_auth" = []), ("api_key" = []))
 )]
 pub async fn delete_articles_by_slug(
-    (path, username, pool): (web::Path<ArticlePath>, web::Query<UserForArticle>, web::Data<PgPool>)
+    (path, auth, pool, audit): (web::Path<ArticlePath>, AuthUser, web::Data<PgPool>, AuditContext)
 ) -> Result<HttpResponse, AppError> {
-    let user_info = username.into_inner();
-
-    // Validate the user input
-    let validation_result = user_info.validate();
-    if let Err(validation_errors) = validation_result {
-        return Ok(validation_errors_response(&validation_errors));
-    }
+    auth.require_scope(ApiScope::ArticlesWrite)?;
 
     let pool = pool.get_ref();
 
-    // Fetch the current user
-    let user= sqlx::query_as!(
-        User,
-        "SELECT * FROM users WHERE username = $1 LIMIT 1",
-        &user_info.username
-    )
-    .fetch_one(pool)
-    .await
-    .map_err(|_| {
-        AppError::InternalServerError
-    })?;
-
     let (article_id,article_author_id): (Uuid, Uuid) = sqlx::query_as::<_, (Uuid, Uuid)>("SELECT id, author_id, slug FROM articles WHERE slug = $1")
         .bind(&path.slug)
         .fetch_one(pool)
@@ -420,7 +692,15 @@ pub async fn delete_articles_by_slug(
             AppError::InternalServerError
         })?;
 
-    if user.id != article_author_id {
+    if auth.id != article_author_id && !auth.has_permission(Permission::DeleteAnyArticle) {
+        audit
+            .record(AuditEventKind::Forbidden, Some(auth.id), serde_json::json!({
+                "action": "delete_article",
+                "slug": path.slug,
+                "reason": "not_author",
+            }))
+            .await;
+
         return Err(AppError::Forbidden(serde_json::json!({
             "error": "user is not the author of article in question",
         })));
@@ -430,8 +710,10 @@ pub async fn delete_articles_by_slug(
 
     delete_favorites(article_id, pool).await?;
 
+    delete_comments(article_id, pool).await?;
+
     let query = sqlx::query("DELETE FROM articles WHERE id = $1")
-        .bind(&article_id);
+        .bind(article_id);
 
     match query.execute(pool).await {
         Ok(res) => {
@@ -456,61 +738,40 @@ pub async fn delete_articles_by_slug(
 }
 
 /// Favorite an article
+///
+/// Drafts can only be favorited by their author
 #[utoipa::path(
     post,
     path = "/api/v1/articles/favorite/{slug}",
     tag = "articles",
     responses(
         (status = 201, description = "Success"),
-        (status = 400, description = "Bad request")
+        (status = 400, description = "Bad request"),
+        (status = 404, description = "Not found")
     ),
     params(
         ("slug" = String, Path, description = "an article slug"),
-        ("username" = String, Query, description = "Username of a user"),
-    )
+    ),
+    security(("bearer_auth" = []), ("api_key" = []))
 )]
 pub async fn favorite_articles_by_slug(
-    (path, username, pool): (web::Path<ArticlePath>, web::Query<UserForArticle>, web::Data<PgPool>)
+    (path, auth, pool): (web::Path<ArticlePath>, AuthUser, web::Data<PgPool>)
 ) -> Result<HttpResponse, AppError> {
-    let user_info = username.into_inner();
-
-    // Validate the user input
-    let validation_result = user_info.validate();
-    if let Err(validation_errors) = validation_result {
-        return Ok(validation_errors_response(&validation_errors));
-    }
+    auth.require_scope(ApiScope::ArticlesWrite)?;
 
     let pool = pool.get_ref();
 
-    // Fetch the current user
-    let user= sqlx::query_as!(
-        User,
-        "SELECT * FROM users WHERE username = $1 LIMIT 1",
-        &user_info.username
-    )
-    .fetch_one(pool)
-    .await
-    .map_err(|_| {
-        AppError::InternalServerError
-    })?;
-
-    let (article_id,article_slug): (Uuid, String) = sqlx::query_as::<_, (Uuid, String)>("SELECT id, slug FROM articles WHERE slug = $1")
-        .bind(&path.slug)
-        .fetch_one(pool)
-        .await
-        .map_err(|_| {
-            AppError::InternalServerError
-        })?;
-
+    let article_id = find_visible_article(&path.slug, Some(auth.id), pool).await?;
+    let article_slug = path.slug.to_string();
 
     let favorite_article = NewFavoriteArticle {
-        user_id: user.id,
-        article_id: article_id
+        user_id: auth.id,
+        article_id
     };
 
     let query = sqlx::query("INSERT INTO favorite_articles VALUES ($1, $2)")
-        .bind(&favorite_article.user_id)
-        .bind(&favorite_article.article_id);
+        .bind(favorite_article.user_id)
+        .bind(favorite_article.article_id);
 
     // Execute the query on the pool
     match query.execute(pool).await {
@@ -520,7 +781,7 @@ pub async fn favorite_articles_by_slug(
             });
 
             // Fetch the article response after it's created
-            get_article_response(article_slug, Some(user.id), pool).await?;
+            get_article_response(article_slug, Some(auth.id), pool).await?;
 
             // Return a successful response
             Ok(HttpResponse::Ok().status(StatusCode::CREATED).json(success_response))
@@ -540,49 +801,27 @@ pub async fn favorite_articles_by_slug(
     tag = "articles",
     responses(
         (status = 200, description = "Success"),
-        (status = 400, description = "Bad request")
+        (status = 400, description = "Bad request"),
+        (status = 404, description = "Not found")
     ),
     params(
         ("slug" = String, Path, description = "an article slug"),
-        ("username" = String, Query, description = "Username of a user"),
-    )
+    ),
+    security(("bearer_auth" = []), ("api_key" = []))
 )]
 pub async fn unfavorite_articles_by_slug(
-    (path, username, pool): (web::Path<ArticlePath>, web::Query<UserForArticle>, web::Data<PgPool>)
+    (path, auth, pool): (web::Path<ArticlePath>, AuthUser, web::Data<PgPool>)
 ) -> Result<HttpResponse, AppError> {
-    let user_info = username.into_inner();
-
-    // Validate the user input
-    let validation_result = user_info.validate();
-    if let Err(validation_errors) = validation_result {
-        return Ok(validation_errors_response(&validation_errors));
-    }
+    auth.require_scope(ApiScope::ArticlesWrite)?;
 
     let pool = pool.get_ref();
 
-    // Fetch the current user
-    let user= sqlx::query_as!(
-        User,
-        "SELECT * FROM users WHERE username = $1 LIMIT 1",
-        &user_info.username
-    )
-    .fetch_one(pool)
-    .await
-    .map_err(|_| {
-        AppError::InternalServerError
-    })?;
-
-    let (article_id,article_slug): (Uuid, String) = sqlx::query_as::<_, (Uuid, String)>("SELECT id, slug FROM articles WHERE slug = $1")
-        .bind(&path.slug)
-        .fetch_one(pool)
-        .await
-        .map_err(|_| {
-            AppError::InternalServerError
-        })?;
+    let article_id = find_visible_article(&path.slug, Some(auth.id), pool).await?;
+    let article_slug = path.slug.to_string();
 
     let query = sqlx::query("DELETE FROM favorite_articles WHERE user_id = $1 AND article_id = $2")
-        .bind(&user.id)
-        .bind(&article_id);
+        .bind(auth.id)
+        .bind(article_id);
 
     match query.execute(pool).await {
         Ok(res) => {
@@ -592,7 +831,7 @@ pub async fn unfavorite_articles_by_slug(
                 });
 
                 // Fetch the article response after it's created
-                get_article_response(article_slug, Some(user.id), pool).await?;
+                get_article_response(article_slug, Some(auth.id), pool).await?;
 
                 // Return a successful response
                 Ok(HttpResponse::Ok().json(success_response))
@@ -612,42 +851,338 @@ pub async fn unfavorite_articles_by_slug(
 }
 
 // Some helpers for this route ------------------------------------------------------------
-async fn get_article_list_response(
-    articles: Vec<Article>,
+
+/// Id of the article behind `slug`, 404 unless the viewer may read it
+///
+/// Drafts and archived articles are only shown to their author
+pub(super) async fn find_visible_article(slug: &str, viewer: Option<Uuid>, pool: &PgPool) -> Result<Uuid, AppError> {
+    let article: Option<(Uuid, bool)> = sqlx::query_as(
+        "SELECT id, status = 'published' OR author_id IS NOT DISTINCT FROM $2 FROM articles WHERE slug = $1"
+    )
+    .bind(slug)
+    .bind(viewer)
+    .fetch_optional(pool)
+    .await?;
+
+    match article {
+        Some((article_id, true)) => Ok(article_id),
+        _ => Err(AppError::NotFound(serde_json::json!({
+            "error": "Article not found",
+        }))),
+    }
+}
+
+/// The current slug of the article that used to be at `slug`, under the same visibility as `find_visible_article`
+async fn find_renamed_article(slug: &str, viewer: Option<Uuid>, pool: &PgPool) -> Result<Option<String>, AppError> {
+    let current_slug: Option<String> = sqlx::query_scalar(r#"
+        SELECT a.slug FROM article_slug_history AS h
+        INNER JOIN articles AS a ON a.id = h.article_id
+        WHERE h.slug = $1 AND (a.status = 'published' OR a.author_id IS NOT DISTINCT FROM $2)
+    "#)
+    .bind(slug)
+    .bind(viewer)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(current_slug)
+}
+
+/// 301 to the same path with `old_slug` swapped for `current_slug`, the query string is kept
+fn redirect_to_slug(req: &HttpRequest, old_slug: &str, current_slug: &str) -> HttpResponse {
+    let path = req.path();
+    let mut location = match path.strip_suffix(old_slug) {
+        Some(prefix) => format!("{}{}", prefix, current_slug),
+        None => format!("/api/v1/articles/data/{}", current_slug),
+    };
+
+    if !req.query_string().is_empty() {
+        location.push('?');
+        location.push_str(req.query_string());
+    }
+
+    HttpResponse::MovedPermanently()
+        .insert_header((actix_web::http::header::LOCATION, location))
+        .json(serde_json::json!({
+            "slug": current_slug,
+        }))
+}
+
+/// Append the `ArticlesParams` filters as `AND` conditions on `articles AS a`, every value is bound
+fn push_article_filters(query: &mut QueryBuilder<'_, Postgres>, params: &ArticlesParams) {
+    let authors = params.authors();
+    if !authors.is_empty() {
+        query
+            .push(" AND a.author_id IN (SELECT id FROM users WHERE username = ANY(")
+            .push_bind(authors)
+            .push("))");
+    }
+
+    if let Some(ref username_favorited_by) = params.favorited {
+        query
+            .push(" AND EXISTS (SELECT 1 FROM favorite_articles AS fa INNER JOIN users AS fu ON fu.id = fa.user_id")
+            .push(" WHERE fa.article_id = a.id AND fu.username = ")
+            .push_bind(username_favorited_by.clone())
+            .push(")");
+    }
+
+    let tags = params.tags();
+    if !tags.is_empty() {
+        let tag_count = tags.len() as i64;
+
+        query
+            .push(" AND (SELECT COUNT(DISTINCT at.tag_name) FROM article_tags AS at WHERE at.article_id = a.id AND at.tag_name = ANY(")
+            .push_bind(tags)
+            .push(")) = ")
+            .push_bind(tag_count);
+    }
+}
+
+/// Finish `query`, selecting from `articles AS a`, with the order of `sort` and return one page of it
+///
+/// With a cursor only the articles on its side are read so the index does the skipping, without one the
+/// page starts at `offset`. One extra row is read to know whether there is another page after this one.
+/// Cursors only exist for the sorts on a timestamp, the others page by offset
+async fn fetch_article_page(
+    mut query: QueryBuilder<'_, Postgres>,
+    sort: ArticleSort,
+    limit: i64,
+    offset: i64,
+    cursor: Option<&str>,
+    pool: &PgPool,
+) -> Result<Page<Article>, AppError> {
+    let cursor = cursor.map(Cursor::decode).transpose()?;
+    let keyset = keyset_order(sort);
+
+    match (cursor, keyset) {
+        (Some(cursor), Some((column, descending))) => {
+            // The previous page is read backwards from the cursor
+            let forward = cursor.direction == CursorDirection::After;
+            let (comparison, order) = if forward == descending { (" < ", "DESC") } else { (" > ", "ASC") };
+
+            query
+                .push(format!(" AND ({column}, a.id)"))
+                .push(comparison)
+                .push("(")
+                .push_bind(cursor.timestamp)
+                .push(", ")
+                .push_bind(cursor.id)
+                .push(")")
+                .push(format!(" ORDER BY {column} {order}, a.id {order} LIMIT "))
+                .push_bind(limit + 1);
+        }
+        (Some(_), None) => {
+            return Err(AppError::BadRequest(serde_json::json!({
+                "error": "Cursors are only supported when sorting by newest, oldest or updated, use offset",
+            })));
+        }
+        (None, _) => {
+            query
+                .push(format!(" ORDER BY {} LIMIT ", article_order(sort)))
+                .push_bind(limit + 1)
+                .push(" OFFSET ")
+                .push_bind(offset);
+        }
+    }
+
+    let mut articles: Vec<Article> = query
+        .build_query_as()
+        .fetch_all(pool)
+        .await?;
+
+    let has_more = articles.len() as i64 > limit;
+    articles.truncate(limit as usize);
+
+    let (has_next, has_prev) = match cursor.map(|cursor| cursor.direction) {
+        Some(CursorDirection::After) => (has_more, true),
+        Some(CursorDirection::Before) => {
+            // Read backwards from the cursor, put back in list order
+            articles.reverse();
+            (true, has_more)
+        }
+        None => (has_more, offset > 0),
+    };
+
+    let timestamp = |article: &Article| match sort {
+        ArticleSort::Updated => article.updated_at,
+        _ => article.published_at.unwrap_or(article.created_at),
+    };
+
+    let next_cursor = articles
+        .last()
+        .filter(|_| has_next && keyset.is_some())
+        .map(|article| Cursor::after(timestamp(article), article.id).encode());
+    let prev_cursor = articles
+        .first()
+        .filter(|_| has_prev && keyset.is_some())
+        .map(|article| Cursor::before(timestamp(article), article.id).encode());
+
+    Ok(Page { items: articles, next_cursor, prev_cursor })
+}
+
+/// The timestamp column a sort can be paged by with a cursor, and whether it is descending
+fn keyset_order(sort: ArticleSort) -> Option<(&'static str, bool)> {
+    match sort {
+        ArticleSort::Newest => Some(("a.published_at", true)),
+        ArticleSort::Oldest => Some(("a.published_at", false)),
+        ArticleSort::Updated => Some(("a.updated_at", true)),
+        ArticleSort::MostFavorited | ArticleSort::MostCommented | ArticleSort::Trending => None,
+    }
+}
+
+/// `ORDER BY` list of a sort, ties are broken by creation so pages stay stable
+fn article_order(sort: ArticleSort) -> &'static str {
+    match sort {
+        ArticleSort::Newest => "a.published_at DESC, a.id DESC",
+        ArticleSort::Oldest => "a.published_at ASC, a.id ASC",
+        ArticleSort::Updated => "a.updated_at DESC, a.id DESC",
+        ArticleSort::MostFavorited => {
+            "(SELECT COUNT(*) FROM favorite_articles AS fa WHERE fa.article_id = a.id) DESC, a.created_at DESC, a.id DESC"
+        }
+        ArticleSort::MostCommented => {
+            "(SELECT COUNT(*) FROM comments AS c WHERE c.article_id = a.id) DESC, a.created_at DESC, a.id DESC"
+        }
+        // Scores of the last periodic refresh, see `article_source`
+        ArticleSort::Trending => "COALESCE(t.score, 0) DESC, a.created_at DESC, a.id DESC",
+    }
+}
+
+/// `FROM` clause a sort reads `articles AS a` from, trending joins the scores as `t`
+fn article_source(sort: ArticleSort) -> &'static str {
+    match sort {
+        ArticleSort::Trending => "articles AS a LEFT JOIN article_trending_scores AS t ON t.article_id = a.id",
+        _ => "articles AS a",
+    }
+}
+
+/// Published `articles AS a` written by the authors the user follows, `select` is the select list
+fn feed_query(select: &str, user_id: Uuid) -> QueryBuilder<'static, Postgres> {
+    let mut query = QueryBuilder::new(select);
+    query
+        .push(" FROM articles AS a WHERE a.status = 'published'")
+        .push(" AND a.author_id IN (SELECT user_id FROM followers WHERE follower_id = ")
+        .push_bind(user_id)
+        .push(")");
+
+    query
+}
+
+async fn get_article_page_response(
+    page: Page<Article>,
+    total: i64,
     user_id: Option<Uuid>,
     pool: &PgPool,
 ) -> Result<ArticleListResponse, AppError> {
-    let futures = articles
-        .iter()
-        .map(|article| {
-            let article_slug = article.slug.to_owned();
+    let mut response = get_article_list_response(page.items, user_id, pool).await?;
+    response.articles_count = total as usize;
+    response.next_cursor = page.next_cursor;
+    response.prev_cursor = page.prev_cursor;
 
-            async move {
-                match get_article_response(article_slug, user_id, pool).await {
-                    Ok(response) => Ok(response.article),
-                    Err(e) => Err(e),
-                }
-            }
+    Ok(response)
+}
+
+fn article_pagination(response: &ArticleListResponse, limit: i64, offset: i64, by_cursor: bool) -> Pagination {
+    Pagination {
+        total: response.articles_count as i64,
+        limit,
+        offset,
+        cursors: by_cursor.then(|| (response.prev_cursor.clone(), response.next_cursor.clone())),
+    }
+}
+
+/// Build the responses for a whole list of articles, keeping its order
+///
+/// Authors, tags, favorite counts and the viewer's favorited and following flags are read for all
+/// the articles at once, so a page costs the same single query whatever its size
+pub async fn get_article_list_response(
+    articles: Vec<Article>,
+    user_id: Option<Uuid>,
+    pool: &PgPool,
+) -> Result<ArticleListResponse, AppError> {
+    if articles.is_empty() {
+        return Ok(ArticleListResponse {
+            articles: Vec::new(),
+            articles_count: 0,
+            next_cursor: None,
+            prev_cursor: None,
         });
+    }
 
-    let results: Vec<Result<ArticleResponseInner, AppError>> = join_all(futures).await;
+    let article_ids: Vec<Uuid> = articles.iter().map(|article| article.id).collect();
 
-    let articles: Result<Vec<ArticleResponseInner>, AppError> = results
+    let rows: Vec<(Uuid, String, Option<String>, Vec<String>, i64, bool, bool)> = sqlx::query_as(r#"
+        SELECT
+            a.id,
+            u.username,
+            u.bio,
+            COALESCE(t.tag_list, '{}'),
+            COALESCE(f.favorites_count, 0),
+            EXISTS (SELECT 1 FROM favorite_articles AS fa WHERE fa.article_id = a.id AND fa.user_id = $2),
+            EXISTS (SELECT 1 FROM followers AS fo WHERE fo.user_id = a.author_id AND fo.follower_id = $2)
+        FROM articles AS a
+        INNER JOIN users AS u ON u.id = a.author_id
+        LEFT JOIN (
+            SELECT article_id, array_agg(tag_name ORDER BY created_at, tag_name) AS tag_list
+            FROM article_tags
+            WHERE article_id = ANY($1)
+            GROUP BY article_id
+        ) AS t ON t.article_id = a.id
+        LEFT JOIN (
+            SELECT article_id, COUNT(*) AS favorites_count
+            FROM favorite_articles
+            WHERE article_id = ANY($1)
+            GROUP BY article_id
+        ) AS f ON f.article_id = a.id
+        WHERE a.id = ANY($1)
+    "#)
+    .bind(&article_ids)
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+
+    let mut details: HashMap<Uuid, _> = rows
         .into_iter()
+        .map(|(id, username, bio, tags, favorites_count, favorited, following)| {
+            (id, (username, bio, tags, favorites_count, favorited, following))
+        })
         .collect();
 
-    match articles {
-        Ok(article_list) => {
-            Ok(ArticleListResponse {
-                articles_count: article_list.len(),
-                articles: article_list,
+    // An article deleted since the page was read is left out
+    let article_list: Vec<ArticleResponseInner> = articles
+        .into_iter()
+        .filter_map(|article| {
+            let (username, bio, tags, favorites_count, favorited, following) = details.remove(&article.id)?;
+
+            Some(ArticleResponseInner {
+                slug: article.slug,
+                title: article.title,
+                description: article.description,
+                body_html: article.body_html.unwrap_or_else(|| render_markdown(&article.body)),
+                body: article.body,
+                tag_list: tags,
+                status: article.status,
+                published_at: article.published_at.map(CustomDateTime),
+                created_at: CustomDateTime(article.created_at),
+                updated_at: CustomDateTime(article.updated_at),
+                favorited,
+                favorites_count: favorites_count as usize,
+                author: ProfileResponseInner {
+                    username,
+                    bio,
+                    following,
+                },
             })
-        }
-        Err(e) => Err(e),
-    }
+        })
+        .collect();
+
+    Ok(ArticleListResponse {
+        articles_count: article_list.len(),
+        articles: article_list,
+        next_cursor: None,
+        prev_cursor: None,
+    })
 }
 
-async fn get_article_response(
+pub(super) async fn get_article_response(
     slug: String,
     user_id: Option<Uuid>,
     pool: &PgPool,
@@ -677,8 +1212,11 @@ async fn get_article_response(
             slug: data.article.slug,
             title: data.article.title,
             description: data.article.description,
+            body_html: data.article.body_html.unwrap_or_else(|| render_markdown(&data.article.body)),
             body: data.article.body,
             tag_list: tags,
+            status: data.article.status,
+            published_at: data.article.published_at.map(CustomDateTime),
             created_at: CustomDateTime(data.article.created_at),
             updated_at: CustomDateTime(data.article.updated_at),
             favorited,
@@ -737,9 +1275,9 @@ async fn get_favorited_and_following(
             ON followers.follower_id = users.id AND followers.user_id = $2
         WHERE users.id = $3
     "#)
-    .bind(&article_id)
-    .bind(&author_id)
-    .bind(&user_id);
+    .bind(article_id)
+    .bind(author_id)
+    .bind(user_id);
 
     let result: (Option<Uuid>, Option<Uuid>) = query.fetch_one(pool).await?;
     let (_user_id, favorite_id, follow_id) = (Uuid::new_v4(), result.0, result.1); // Replace Uuid::new_v4() with an appropriate default Uuid
@@ -756,6 +1294,9 @@ impl<'r> FromRow<'r, PgRow> for Article {
             title: row.try_get("title")?,
             description: row.try_get("description")?,
             body: row.try_get("body")?,
+            body_html: row.try_get("body_html")?,
+            status: row.try_get("status")?,
+            published_at: row.try_get("published_at")?,
             created_at: row.try_get("created_at")?,
             updated_at: row.try_get("updated_at")?,
         })
@@ -772,6 +1313,9 @@ impl<'r> FromRow<'r, PgRow> for ArticleAndAuthor {
                 title: row.try_get("title")?,
                 description: row.try_get("description")?,
                 body: row.try_get("body")?,
+                body_html: row.try_get("body_html")?,
+                status: row.try_get("status")?,
+                published_at: row.try_get("published_at")?,
                 created_at: row.try_get("created_at")?,
                 updated_at: row.try_get("updated_at")?,
             },
@@ -781,6 +1325,7 @@ impl<'r> FromRow<'r, PgRow> for ArticleAndAuthor {
                 email: row.try_get("email")?,
                 password: row.try_get("password")?,
                 bio: row.try_get("bio")?,
+                email_verified_at: row.try_get("email_verified_at")?,
                 created_at: row.try_get("created_at")?,
                 updated_at: row.try_get("updated_at")?,
             },
@@ -788,43 +1333,66 @@ impl<'r> FromRow<'r, PgRow> for ArticleAndAuthor {
     }
 }
 
-fn generate_slug(uuid: &Uuid, title: &str) -> String {
-    format!("{}-{}", to_blob(uuid), sluggify(title, None))
+/// The text search configuration for an article or a search, rejecting the ones not listed in the settings
+fn search_language(requested: Option<&str>, settings: &ApplicationSettings) -> Result<String, AppError> {
+    let language = requested.unwrap_or(&settings.search_default_language);
+
+    if !settings.search_languages.iter().any(|allowed| allowed == language) {
+        return Err(AppError::BadRequest(serde_json::json!({
+            "error": format!("Unsupported language, use one of: {}", settings.search_languages.join(", ")),
+        })));
+    }
+
+    Ok(language.to_owned())
+}
+
+/// The slug of a title, other scripts than Latin are transliterated first so "Привет мир" reads `privet-mir`
+pub(super) fn generate_slug(uuid: &Uuid, title: &str) -> String {
+    format!("{}-{}", to_blob(uuid), sluggify(&deunicode(title), None))
+}
+
+/// Reject a chosen slug held by another article, now or in its slug history where it still redirects
+async fn ensure_slug_available(slug: &str, article_id: Uuid, pool: &PgPool) -> Result<(), AppError> {
+    let taken: bool = sqlx::query_scalar(r#"
+        SELECT
+            EXISTS (SELECT 1 FROM articles WHERE slug = $1 AND id <> $2)
+            OR EXISTS (SELECT 1 FROM article_slug_history WHERE slug = $1 AND article_id <> $2)
+    "#)
+    .bind(slug)
+    .bind(article_id)
+    .fetch_one(pool)
+    .await?;
+
+    if taken {
+        return Err(AppError::UnprocessableEntity(serde_json::json!({
+            "errors": { "slug": ["has already been taken"] },
+        })));
+    }
+
+    Ok(())
 }
 
 async fn replace_tags<I>(
     article_id: Uuid,
     tags: I,
-    pool: &PgPool,
-) -> Result<Vec<ArticleTag>, AppError>
+    connection: &mut PgConnection,
+) -> Result<(), AppError>
 where
     I: IntoIterator<Item = String>,
 {
-    delete_tags(article_id, pool).await?;
-
-    let tag_results: Result<Vec<ArticleTag>, AppError> = Ok(try_join_all(tags
-        .into_iter()
-        .map(|tag_name| {
-            let article_id = article_id;
-            let tag_name = tag_name.to_string();
-            let pool = pool.clone();
-
-            async move {
-                let result = add_tag(article_id, &tag_name, &pool).await?;
-                Result::<_, AppError>::Ok(result)
-            }
-            .boxed()
-        }))
+    sqlx::query!("DELETE FROM article_tags WHERE article_id = $1", article_id)
+        .execute(&mut *connection)
         .await
-        .map_err(|_| {
-            // Handle the error here
-            AppError::InternalServerError
-        })?);
+        .map_err(|_| AppError::InternalServerError)?;
 
-    tag_results
+    for tag_name in tags {
+        add_tag(article_id, &tag_name, &mut *connection).await?;
+    }
+
+    Ok(())
 }
 
-async fn add_tag<T>(article_id: Uuid, tag_name: T, pool: &PgPool) -> Result<ArticleTag, AppError>
+async fn add_tag<T>(article_id: Uuid, tag_name: T, connection: &mut PgConnection) -> Result<ArticleTag, AppError>
 where
     T: ToString,
 {
@@ -842,7 +1410,7 @@ where
         article_id,
         tag_name
     )
-    .fetch_one(pool)
+    .fetch_one(connection)
     .await
     .map_err(|_| {
         // eprintln!("SQLx Error: {:?}", err);
@@ -861,9 +1429,19 @@ async fn delete_tags(article_id: Uuid, pool: &PgPool) -> Result<(), AppError> {
     Ok(())
 }
 
+async fn delete_comments(article_id: Uuid, pool: &PgPool) -> Result<(), AppError> {
+    sqlx::query("DELETE FROM comments WHERE article_id = $1")
+        .bind(article_id)
+        .execute(pool)
+        .await
+        .map_err(|_| AppError::InternalServerError)?;
+
+    Ok(())
+}
+
 async fn delete_favorites(article_id: Uuid, pool: &PgPool) -> Result<(), AppError> {
     let _ = sqlx::query("DELETE FROM favorite_articles WHERE article_id = $1")
-        .bind(&article_id)
+        .bind(article_id)
         .execute(pool)
         .await
         .map_err(|_| AppError::InternalServerError)?;
@@ -1,72 +1,68 @@
-use actix_web::{web, HttpResponse, ResponseError, http::StatusCode};
+use actix_web::{web, HttpRequest, HttpResponse, ResponseError, http::StatusCode};
 use futures::future::join_all;
 use sqlx::{self, PgPool, Postgres};
 use uuid::Uuid;
 use sqlx::Row;
 use validator::Validate;
 
+use crate::auth::{ApiScope, AuthUser, MaybeAuthUser, Permission};
+use crate::audit::{AuditContext, AuditEventKind};
 use crate::schemas::*;
 use crate::errors::Error as AppError;
+use crate::markdown::render_markdown;
+use crate::pagination::{paginated_response, PageParams, Pagination};
 use crate::utils::validation_errors_response;
 
+use super::articles::find_visible_article;
+
 /// Return list of articles with your comment
+///
+/// Oldest first, the `X-Total-Count` header holds the number of comments and the `Link` header the other pages.
+/// All of them are returned unless `limit` or `offset` is given
 #[utoipa::path(
     get,
     path = "/api/v1/articles/comments/{slug}",
     tag = "articles",
     responses(
         (status = 200, description = "Success"),
-        (status = 400, description = "Bad request")
+        (status = 400, description = "Bad request"),
+        (status = 404, description = "Not found")
     ),
     params(
         ("slug" = String, Path, description = "an article slug"),
-        ("username" = String, Query, description = "Username of a user")
-    )
+        ("limit" = Option<usize>, Query, description = "Page size, 20 when only `offset` is given and 100 at most"),
+        ("offset" = Option<usize>, Query, description = "Number of comments to skip"),
+    ),
+    security((), ("bearer_auth" = []))
 )]
 pub async fn get_articles_comments(
-    (params, username, pool): (web::Path<ArticlePath>, web::Query<UserForArticle>, web::Data<PgPool>)
+    (req, params, page, auth, pool): (HttpRequest, web::Path<ArticlePath>, web::Query<PageParams>, MaybeAuthUser, web::Data<PgPool>)
 ) -> Result<HttpResponse, AppError> {
     let pool = pool.get_ref();
 
-    let user_info = username.into_inner();
-
-    // Validate the user input
-    let user_validation_result = user_info.validate();
-    if let Err(validation_errors) = user_validation_result {
-        return Ok(validation_errors_response(&validation_errors));
-    }
-
-    let user= sqlx::query_as!(
-        User,
-        "SELECT * FROM users WHERE username = $1 LIMIT 1",
-        &user_info.username
-    )
-    .fetch_one(pool)
-    .await
-    .map_err(|_| {
-        AppError::InternalServerError
-    })?;
-
-    let article_id: Vec<Uuid> = sqlx::query_as::<_, (Uuid,)>("SELECT id FROM articles WHERE slug = $1")
-        .bind(&params.slug)
-        .fetch_all(pool)
-        .await?
-        .into_iter()
-        .map(|(id,)| id)
-        .collect();
+    let article_id = find_visible_article(&params.slug, auth.id(), pool).await?;
+    let limit = page.limit_if_paged();
 
     let comments = sqlx::query_as!(
         Comment,
-        "SELECT * FROM comments WHERE article_id = $1",
-        article_id.get(0)
+        "SELECT * FROM comments WHERE article_id = $1 ORDER BY created_at, id LIMIT $2 OFFSET $3",
+        article_id,
+        limit,
+        page.offset()
     )
     .fetch_all(pool)
     .await?;
 
-    let comment_response = get_comment_list_response(comments, Some(user.id), pool).await?;
+    let total = sqlx::query_scalar!("SELECT COUNT(*) FROM comments WHERE article_id = $1", article_id)
+        .fetch_one(pool)
+        .await?
+        .unwrap_or(0);
+
+    let comment_response = get_comment_list_response(comments, auth.id(), pool).await?;
+    let pagination = Pagination::offset(total, limit.unwrap_or(total), page.offset());
 
     // Return the article response as an HTTP response
-    Ok(HttpResponse::Ok().json(comment_response))
+    Ok(paginated_response(&req, &comment_response, &pagination))
 }
 
 /// Add a comment to an articles
@@ -76,19 +72,21 @@ pub async fn get_articles_comments(
     tag = "articles",
     responses(
         (status = 201, description = "Success"),
-        (status = 400, description = "Bad request")
+        (status = 400, description = "Bad request"),
+        (status = 404, description = "Not found")
     ),
     params(
         ("slug" = String, Path, description = "an article slug"),
-        ("username" = String, Query, description = "Username of a user")
     ),
-    request_body = AddComment
+    request_body = AddComment,
+    security(("bearer_auth" = []), ("api_key" = []))
 )]
 pub async fn add_articles_comments(
-    (form, path, username, pool): (web::Json<AddComment>,  web::Path<ArticlePath>, web::Query<UserForArticle>, web::Data<PgPool>)
+    (form, path, auth, pool): (web::Json<AddComment>,  web::Path<ArticlePath>, AuthUser, web::Data<PgPool>)
 ) -> Result<HttpResponse, AppError> {
+    auth.require_scope(ApiScope::CommentsWrite)?;
+
     let comment_data: AddComment = form.into_inner();
-    let user_info = username.into_inner();
 
     // Validate the user input
     let article_validation_result = comment_data.validate();
@@ -96,55 +94,30 @@ pub async fn add_articles_comments(
         return Ok(validation_errors_response(&validation_errors));
     }
 
-    // Validate the user input
-    let user_validation_result = user_info.validate();
-    if let Err(validation_errors) = user_validation_result {
-        return Ok(validation_errors_response(&validation_errors));
-    }
-
     // Access the PgPool from the Data container
     let pool = pool.get_ref();
 
-    // Fetch the current user
-    let user= sqlx::query_as!(
-        User,
-        "SELECT * FROM users WHERE username = $1 LIMIT 1",
-        &user_info.username
-    )
-    .fetch_one(pool)
-    .await
-    .map_err(|_| {
-        AppError::InternalServerError
-    })?;
-
-    let article_id: Vec<Uuid> = sqlx::query_as::<_, (Uuid,)>("SELECT id FROM articles WHERE slug = $1")
-        .bind(&path.slug)
-        .fetch_all(pool)
-        .await?
-        .into_iter()
-        .map(|(id,)| id)
-        .collect();
-
-    let user_id = user.id;
-    let article_id =  article_id.get(0).unwrap().to_owned();
+    let user_id = auth.id;
+    let article_id = find_visible_article(&path.slug, Some(user_id), pool).await?;
     let new_comment = NewComment {
         article_id,
         user_id,
         body: comment_data.body,
     };
 
-    let query = sqlx::query("INSERT INTO comments (article_id, user_id, body) VALUES ($1, $2, $3)")
-        .bind(&new_comment.article_id)
-        .bind(&new_comment.user_id)
-        .bind(&new_comment.body);
+    let query = sqlx::query("INSERT INTO comments (article_id, user_id, body, body_html) VALUES ($1, $2, $3, $4)")
+        .bind(new_comment.article_id)
+        .bind(new_comment.user_id)
+        .bind(&new_comment.body)
+        .bind(render_markdown(&new_comment.body));
 
     // Execute the query on the pool
     match query.execute(pool).await {
         Ok(_) => {
             // Now, perform a SELECT query to retrieve the inserted record
             let select_query = sqlx::query("SELECT * FROM comments WHERE article_id = $1 AND user_id = $2 AND body = $3")
-                .bind(&new_comment.article_id)
-                .bind(&new_comment.user_id)
+                .bind(new_comment.article_id)
+                .bind(new_comment.user_id)
                 .bind(&new_comment.body);
 
             match select_query.fetch_one(pool).await {
@@ -168,64 +141,57 @@ pub async fn add_articles_comments(
     }
 }
 
-/// Add a comment to an articles
+/// Delete a comment from an articles
+///
+/// Only the commenter can delete a comment, or a moderator
 #[utoipa::path(
     delete,
-    path = "/api/v1/articles/comments/{slug}",
+    path = "/api/v1/articles/comments/{slug}/{comment_id}",
     tag = "articles",
     responses(
-        (status = 201, description = "Success"),
-        (status = 400, description = "Bad request")
+        (status = 200, description = "Success"),
+        (status = 400, description = "Bad request"),
+        (status = 403, description = "Forbidden")
     ),
     params(
         ("slug" = String, Path, description = "an article slug"),
         ("comment_id" = i64, Path, description = "comment id"),
-        ("username" = String, Query, description = "Username of a user")
     ),
-    request_body = AddComment
+    security(("bearer_auth" = []), ("api_key" = []))
 )]
 pub async fn delete_articles_comments(
-    (form, username, pool): (web::Path<ArticleCommentPath>, web::Query<UserForArticle>, web::Data<PgPool>)
+    (form, auth, pool, audit): (web::Path<ArticleCommentPath>, AuthUser, web::Data<PgPool>, AuditContext)
 ) -> Result<HttpResponse, AppError> {
-    let user_info = username.into_inner();
-
-    // Validate the user input
-    let user_validation_result = user_info.validate();
-    if let Err(validation_errors) = user_validation_result {
-        return Ok(validation_errors_response(&validation_errors));
-    }
+    auth.require_scope(ApiScope::CommentsWrite)?;
 
     // Access the PgPool from the Data container
     let pool = pool.get_ref();
 
-    let (comment_id, user_id): (Uuid, Uuid) = sqlx::query_as::<_, (Uuid, Uuid)>("SELECT id, user_id FROM comments WHERE id = $1")
-        .bind(&form.comment_id)
+    let (comment_id, user_id): (i32, Uuid) = sqlx::query_as::<_, (i32, Uuid)>("SELECT id, user_id FROM comments WHERE id = $1")
+        .bind(form.comment_id)
         .fetch_one(pool)
         .await
         .map_err(|_| {
             AppError::InternalServerError
         })?;
 
-    // Fetch the current user
-    let user= sqlx::query_as!(
-        User,
-        "SELECT * FROM users WHERE username = $1 LIMIT 1",
-        &user_info.username
-    )
-    .fetch_one(pool)
-    .await
-    .map_err(|_| {
-        AppError::InternalServerError
-    })?;
+    if auth.id != user_id && !auth.has_permission(Permission::DeleteAnyComment) {
+        audit
+            .record(AuditEventKind::Forbidden, Some(auth.id), serde_json::json!({
+                "action": "delete_comment",
+                "slug": form.slug,
+                "comment_id": comment_id,
+                "reason": "not_author",
+            }))
+            .await;
 
-    if user.id != user_id {
         return Err(AppError::Forbidden(serde_json::json!({
             "error": "user is not the author of article in question",
         })));
     }
 
     let query = sqlx::query("DELETE FROM comments WHERE id = $1")
-        .bind(&comment_id);
+        .bind(comment_id);
 
     match query.execute(pool).await {
         Ok(res) => {
@@ -334,6 +300,7 @@ async fn get_comment_response(
             id: comment.id,
             created_at: CustomDateTime(comment.created_at),
             updated_at: CustomDateTime(comment.updated_at),
+            body_html: comment.body_html.unwrap_or_else(|| render_markdown(&comment.body)),
             body: comment.body,
             author: ProfileResponseInner {
                 username: commenter.username,
@@ -1,9 +1,17 @@
+// Handlers take their extractors as a single tuple, which grows with every dependency they need
+#![allow(clippy::type_complexity)]
+
 mod ping;
 mod users;
 mod profile;
 mod tags;
 mod articles;
 mod comments;
+mod revisions;
+mod admin;
+mod two_factor;
+mod passkeys;
+mod api_keys;
 
 pub use ping::*;
 pub use users::*;
@@ -11,3 +19,8 @@ pub use profile::*;
 pub use tags::*;
 pub use articles::*;
 pub use comments::*;
+pub use revisions::*;
+pub use admin::*;
+pub use two_factor::*;
+pub use passkeys::*;
+pub use api_keys::*;
@@ -0,0 +1,218 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use sqlx::{self, PgPool};
+use validator::Validate;
+
+use crate::auth::{
+    AuthUser, start_passkey_registration, finish_passkey_registration, start_passkey_login, finish_passkey_login,
+    delete_passkey
+};
+use crate::audit::{AuditContext, AuditEventKind};
+use crate::schemas::*;
+use crate::errors::Error as AppError;
+use crate::settings::ApplicationSettings;
+use crate::utils::validation_errors_response;
+
+use super::start_session;
+
+/// Start registering a passkey for the current User
+///
+/// Pass the returned options to `navigator.credentials.create({ publicKey })`, the binary fields are base64url
+#[utoipa::path(
+    post,
+    path = "/api/v1/users/passkeys/register/start",
+    tag = "users",
+    responses(
+        (status = 200, description = "Success"),
+        (status = 401, description = "Unauthorized")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn passkey_registration_options(
+    (auth, pool, settings): (AuthUser, web::Data<PgPool>, web::Data<ApplicationSettings>)
+) -> Result<HttpResponse, AppError> {
+    auth.require_session()?;
+
+    let options = start_passkey_registration(auth.id, &auth.username, pool.get_ref(), &settings).await?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "publicKey": options })))
+}
+
+/// Finish registering a passkey with the credential created by the authenticator
+#[utoipa::path(
+    post,
+    path = "/api/v1/users/passkeys/register/finish",
+    tag = "users",
+    responses(
+        (status = 201, description = "Created"),
+        (status = 400, description = "Bad request"),
+        (status = 401, description = "Unauthorized"),
+        (status = 422, description = "Passkey already registered")
+    ),
+    request_body = PasskeyRegistration,
+    security(("bearer_auth" = []))
+)]
+pub async fn register_passkey(
+    (form, auth, pool, settings): (web::Json<PasskeyRegistration>, AuthUser, web::Data<PgPool>, web::Data<ApplicationSettings>)
+) -> Result<HttpResponse, AppError> {
+    auth.require_session()?;
+
+    let registration = form.into_inner();
+
+    // Validate the user input
+    let validation_result = registration.validate();
+    if let Err(validation_errors) = validation_result {
+        return Ok(validation_errors_response(&validation_errors));
+    }
+
+    let id = finish_passkey_registration(
+        auth.id,
+        &registration.response.client_data_json,
+        &registration.response.attestation_object,
+        registration.name.as_deref().unwrap_or("Passkey"),
+        pool.get_ref(),
+        &settings,
+    )
+    .await?;
+
+    Ok(HttpResponse::Created().json(serde_json::json!({
+        "message": "Passkey registered successfully",
+        "id": id,
+    })))
+}
+
+/// Return the passkeys of the current User
+#[utoipa::path(
+    get,
+    path = "/api/v1/users/passkeys",
+    tag = "users",
+    responses(
+        (status = 200, description = "Success", body = PasskeyListResponse),
+        (status = 401, description = "Unauthorized")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn get_passkeys(
+    (auth, pool): (AuthUser, web::Data<PgPool>)
+) -> Result<HttpResponse, AppError> {
+    auth.require_session()?;
+
+    let passkeys = sqlx::query_as!(
+        Passkey,
+        "SELECT id, name, created_at, last_used_at FROM webauthn_credentials WHERE user_id = $1 ORDER BY created_at",
+        auth.id
+    )
+    .fetch_all(pool.get_ref())
+    .await?;
+
+    let passkeys = passkeys
+        .into_iter()
+        .map(|passkey| PasskeyResponseInner {
+            id: passkey.id,
+            name: passkey.name,
+            created_at: CustomDateTime(passkey.created_at),
+            last_used_at: passkey.last_used_at.map(CustomDateTime),
+        })
+        .collect();
+
+    Ok(HttpResponse::Ok().json(PasskeyListResponse { passkeys }))
+}
+
+/// Remove a passkey of the current User
+#[utoipa::path(
+    delete,
+    path = "/api/v1/users/passkeys/{id}",
+    tag = "users",
+    responses(
+        (status = 200, description = "Success"),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Not Found")
+    ),
+    params(
+        ("id" = Uuid, Path, description = "Passkey id"),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn remove_passkey(
+    (path, auth, pool): (web::Path<PasskeyPath>, AuthUser, web::Data<PgPool>)
+) -> Result<HttpResponse, AppError> {
+    auth.require_session()?;
+
+    if delete_passkey(path.id, auth.id, pool.get_ref()).await? {
+        Ok(HttpResponse::Ok().json(serde_json::json!({
+            "message": "Passkey removed successfully",
+        })))
+    } else {
+        Ok(HttpResponse::NotFound().json(serde_json::json!({
+            "message": "Record not found for the provided id",
+        })))
+    }
+}
+
+/// Start a login with a passkey
+///
+/// Pass the returned options to `navigator.credentials.get({ publicKey })`, the binary fields are base64url
+#[utoipa::path(
+    post,
+    path = "/api/v1/users/passkeys/login/start",
+    tag = "users",
+    responses(
+        (status = 200, description = "Success")
+    )
+)]
+pub async fn passkey_login_options(
+    (pool, settings): (web::Data<PgPool>, web::Data<ApplicationSettings>)
+) -> Result<HttpResponse, AppError> {
+    let options = start_passkey_login(pool.get_ref(), &settings).await?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "publicKey": options })))
+}
+
+/// Finish a login with the assertion signed by the authenticator
+///
+/// Responds with the same tokens as `/api/v1/users/login`, a passkey already is a strong factor so no TOTP code is asked
+#[utoipa::path(
+    post,
+    path = "/api/v1/users/passkeys/login/finish",
+    tag = "users",
+    responses(
+        (status = 201, description = "Success"),
+        (status = 400, description = "Bad request"),
+        (status = 401, description = "Unknown passkey or invalid signature")
+    ),
+    request_body = PasskeyAssertion
+)]
+pub async fn login_with_passkey(
+    (req, form, pool, settings): (HttpRequest, web::Json<PasskeyAssertion>, web::Data<PgPool>, web::Data<ApplicationSettings>)
+) -> Result<HttpResponse, AppError> {
+    let assertion = form.into_inner();
+
+    let pool = pool.get_ref();
+    let audit = AuditContext::new(&req, pool);
+
+    let login = match finish_passkey_login(
+        &assertion.id,
+        &assertion.response.client_data_json,
+        &assertion.response.authenticator_data,
+        &assertion.response.signature,
+        &audit,
+        pool,
+        &settings,
+    )
+    .await
+    {
+        Ok(login) => login,
+        Err(err @ AppError::Unauthorized(_)) => {
+            audit
+                .record(AuditEventKind::LoginFailed, None, serde_json::json!({
+                    "credential_id": assertion.id,
+                    "reason": "invalid_passkey",
+                }))
+                .await;
+
+            return Err(err);
+        }
+        Err(err) => return Err(err),
+    };
+
+    start_session(&req, login.user_id, &login.username, "passkey", pool, &settings).await
+}
@@ -3,6 +3,7 @@ use sqlx::{self, PgPool};
 use uuid::Uuid;
 use validator::Validate;
 
+use crate::auth::{ApiScope, AuthUser, MaybeAuthUser};
 use crate::schemas::*;
 use crate::errors::Error as AppError;
 use crate::utils::validation_errors_response;
@@ -18,10 +19,11 @@ use crate::utils::validation_errors_response;
     ),
     params(
         ("username" = String, Path, description = "Username of a user"),
-    )
+    ),
+    security((), ("bearer_auth" = []))
 )]
 pub async fn get_profile(
-    (form, pool): (web::Path<Profile>, web::Data<PgPool>)
+    (form, auth, pool): (web::Path<Profile>, MaybeAuthUser, web::Data<PgPool>)
 ) -> Result<HttpResponse, AppError> {
     let user_profile = form.into_inner();
 
@@ -49,13 +51,16 @@ pub async fn get_profile(
         // Check if the bio is null or has a value
         let bio_value = bio.unwrap_or_default();
 
-        // Create a query to check if a record exists in the followers table
-        let is_following = sqlx::query("SELECT 1 FROM followers WHERE user_id = $1 AND follower_id = $2")
-            .bind(&user_id)
-            .bind(&user_id)
-            .fetch_optional(pool)
-            .await?
-            .is_some();
+        // Create a query to check if the current user follows this profile
+        let is_following = match auth.id() {
+            Some(follower_id) => sqlx::query("SELECT 1 FROM followers WHERE user_id = $1 AND follower_id = $2")
+                .bind(user_id)
+                .bind(follower_id)
+                .fetch_optional(pool)
+                .await?
+                .is_some(),
+            None => false,
+        };
 
         // Construct the JSON response
         let response = serde_json::json!({
@@ -81,7 +86,7 @@ pub async fn get_profile(
 
 /// Follow a User
 ///
-/// Input an email of someone you want to follow
+/// Follow the user in the path as the current user
 #[utoipa::path(
     post,
     path = "/api/v1/profiles/{username}/follow",
@@ -89,24 +94,20 @@ pub async fn get_profile(
     responses(
         (status = 201, description = "Success", body = ProfileResponse),
         (status = 400, description = "Bad request"),
-        (status = 500, description = "Internal server error")
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Not Found")
     ),
     params(
-        ("username" = String, Path, description = "Your username"),
+        ("username" = String, Path, description = "Username of a user you want to follow"),
     ),
-    request_body = ProfileFollow
+    security(("bearer_auth" = []), ("api_key" = []))
 )]
 pub async fn follow_profile(
-    (form, following_data, pool): (web::Path<Profile>, web::Json<ProfileFollow>, web::Data<PgPool>)
+    (form, auth, pool): (web::Path<Profile>, AuthUser, web::Data<PgPool>)
 ) -> Result<HttpResponse, AppError> {
-    let user_info = form.into_inner();
-    let following_data = following_data.into_inner();
+    auth.require_scope(ApiScope::ProfilesWrite)?;
 
-    // Validate the user input
-    let following_data_validation_result = following_data.validate();
-    if let Err(validation_errors) = following_data_validation_result {
-        return Ok(validation_errors_response(&validation_errors));
-    }
+    let user_info = form.into_inner();
 
     // Validate the user input
     let validation_result = user_info.validate();
@@ -117,40 +118,25 @@ pub async fn follow_profile(
     // Access the PgPool from the Data container
     let pool = pool.get_ref();
 
-    // Fetch the current user
-    let current_user= sqlx::query_as!(
-        User,
-        "SELECT * FROM users WHERE username = $1 LIMIT 1",
-        &user_info.username
-    )
-    .fetch_one(pool)
-    .await
-    .map_err(|_| {
-        AppError::InternalServerError
-    })?;
-
     // Fetch the target user data
     let target_user = sqlx::query_as::<_, (Uuid, String, Option<String>)>(
-        "SELECT id, username, bio FROM users WHERE email = $1 LIMIT 1"
+        "SELECT id, username, bio FROM users WHERE username = $1 LIMIT 1"
     )
-    .bind(&following_data.email)
+    .bind(&user_info.username)
     .fetch_optional(pool)
     .await?;
 
-
     if let Some((following_user_id, following_username, following_user_bio)) = target_user {
-        if &user_info.username == &following_username {
+        if following_user_id == auth.id {
             return Err(AppError::UnprocessableEntity(
                 serde_json::json!({"error": "You cannot follow yourself"}),
             ));
         }
 
-        let follower_id = current_user.id;
-
         // Insert the follower record
-        sqlx::query("INSERT INTO followers (user_id, follower_id) VALUES ($1, $2)")
-            .bind(&following_user_id)
-            .bind(&follower_id)
+        sqlx::query("INSERT INTO followers (user_id, follower_id) VALUES ($1, $2) ON CONFLICT DO NOTHING")
+            .bind(following_user_id)
+            .bind(auth.id)
             .execute(pool)
             .await
             .map_err(|_| {
@@ -183,16 +169,21 @@ pub async fn follow_profile(
     path = "/api/v1/profiles/{username}/follow",
     tag = "profiles",
     responses(
-        (status = 201, description = "Success"),
-        (status = 400, description = "Bad request")
+        (status = 202, description = "Success"),
+        (status = 400, description = "Bad request"),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Not Found")
     ),
     params(
         ("username" = String, Path, description = "Username of a user you followed"),
-    )
+    ),
+    security(("bearer_auth" = []), ("api_key" = []))
 )]
 pub async fn unfollow_profile(
-    (form, pool): (web::Path<Profile>, web::Data<PgPool>)
+    (form, auth, pool): (web::Path<Profile>, AuthUser, web::Data<PgPool>)
 ) -> Result<HttpResponse, AppError> {
+    auth.require_scope(ApiScope::ProfilesWrite)?;
+
     let user_info = form.into_inner();
 
     // Validate the user input
@@ -204,18 +195,6 @@ pub async fn unfollow_profile(
     // Access the PgPool from the Data container
     let pool = pool.get_ref();
 
-    // Fetch the current user
-    let current_user= sqlx::query_as!(
-        User,
-        "SELECT * FROM users WHERE username = $1 LIMIT 1",
-        &user_info.username
-    )
-    .fetch_one(pool)
-    .await
-    .map_err(|_| {
-        AppError::NotFound(serde_json::json!({"error": "Record not found for the provided username"}))
-    })?;
-
     // Fetch the target user data
     let target_user = sqlx::query_as::<_, (Uuid, String, Option<String>)>(
         "SELECT id, username, bio FROM users WHERE username = $1 LIMIT 1"
@@ -225,12 +204,10 @@ pub async fn unfollow_profile(
     .await?;
 
     if let Some((following_user_id, following_username, following_user_bio)) = target_user {
-        let follower_id = current_user.id;
-
         // Delete the follower record
         sqlx::query("DELETE FROM followers WHERE user_id = $1 AND follower_id = $2")
-            .bind(&following_user_id)
-            .bind(&follower_id)
+            .bind(following_user_id)
+            .bind(auth.id)
             .execute(pool)
             .await
             .map_err(|_| {
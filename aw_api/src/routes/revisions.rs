@@ -0,0 +1,249 @@
+use actix_web::{web, HttpResponse};
+use sqlx::{self, PgConnection, PgPool};
+use uuid::Uuid;
+
+use crate::auth::{ApiScope, AuthUser, MaybeAuthUser};
+use crate::audit::{AuditContext, AuditEventKind};
+use crate::diff::{unified_diff, word_diff};
+use crate::markdown::render_markdown;
+use crate::schemas::*;
+use crate::errors::Error as AppError;
+
+use super::articles::{find_visible_article, generate_slug, get_article_response};
+
+/// Return the revisions of an article
+///
+/// Newest first, every change of the title, description or body is a revision
+#[utoipa::path(
+    get,
+    path = "/api/v1/articles/data/{slug}/revisions",
+    tag = "articles",
+    responses(
+        (status = 200, description = "Success", body = ArticleRevisionListResponse),
+        (status = 404, description = "Article not found")
+    ),
+    params(
+        ("slug" = String, Path, description = "an article slug"),
+    ),
+    security((), ("bearer_auth" = []))
+)]
+pub async fn get_article_revisions(
+    (path, auth, pool): (web::Path<ArticlePath>, MaybeAuthUser, web::Data<PgPool>)
+) -> Result<HttpResponse, AppError> {
+    let pool = pool.get_ref();
+
+    let article_id = find_visible_article(&path.slug, auth.id(), pool).await?;
+
+    let revisions = sqlx::query_as!(
+        ArticleRevision,
+        r#"
+        SELECT
+            r.revision, r.title, r.description, r.body, u.username AS "edited_by?", r.restored_from, r.created_at
+        FROM article_revisions AS r
+        LEFT JOIN users AS u ON u.id = r.edited_by
+        WHERE r.article_id = $1
+        ORDER BY r.revision DESC
+        "#,
+        article_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let revisions: Vec<ArticleRevisionResponseInner> = revisions
+        .into_iter()
+        .map(|revision| ArticleRevisionResponseInner {
+            revision: revision.revision,
+            title: revision.title,
+            description: revision.description,
+            body: revision.body,
+            edited_by: revision.edited_by,
+            restored_from: revision.restored_from,
+            created_at: CustomDateTime(revision.created_at),
+        })
+        .collect();
+
+    Ok(HttpResponse::Ok().json(ArticleRevisionListResponse {
+        revisions_count: revisions.len(),
+        revisions,
+    }))
+}
+
+/// Compare two revisions of an article
+///
+/// `unified` gives a line diff per field, `word` the newer text with the changed words marked
+#[utoipa::path(
+    get,
+    path = "/api/v1/articles/data/{slug}/revisions/diff",
+    tag = "articles",
+    responses(
+        (status = 200, description = "Success", body = RevisionDiffResponse),
+        (status = 404, description = "Article or revision not found")
+    ),
+    params(
+        ("slug" = String, Path, description = "an article slug"),
+        ("from" = i32, Query, description = "The older revision"),
+        ("to" = i32, Query, description = "The newer revision"),
+        ("mode" = Option<DiffMode>, Query, description = "unified (default) or word"),
+    ),
+    security((), ("bearer_auth" = []))
+)]
+pub async fn get_article_revision_diff(
+    (path, params, auth, pool): (web::Path<ArticlePath>, web::Query<RevisionDiffParams>, MaybeAuthUser, web::Data<PgPool>)
+) -> Result<HttpResponse, AppError> {
+    let pool = pool.get_ref();
+
+    let article_id = find_visible_article(&path.slug, auth.id(), pool).await?;
+
+    let from = find_revision(article_id, params.from, pool).await?;
+    let to = find_revision(article_id, params.to, pool).await?;
+
+    let field_diff = |old: &str, new: &str, field: &str| match params.mode {
+        DiffMode::Unified => unified_diff(
+            old,
+            new,
+            &format!("revision {}/{}", from.revision, field),
+            &format!("revision {}/{}", to.revision, field),
+        ),
+        DiffMode::Word if old == new => String::new(),
+        DiffMode::Word => word_diff(old, new),
+    };
+
+    Ok(HttpResponse::Ok().json(RevisionDiffResponse {
+        from: from.revision,
+        to: to.revision,
+        mode: params.mode,
+        title: field_diff(&from.title, &to.title, "title"),
+        description: field_diff(&from.description, &to.description, "description"),
+        body: field_diff(&from.body, &to.body, "body"),
+    }))
+}
+
+/// Restore a revision of your article
+///
+/// The article gets the title, description and body of the revision back, which is recorded as a new revision
+#[utoipa::path(
+    post,
+    path = "/api/v1/articles/data/{slug}/revisions/{revision}/restore",
+    tag = "articles",
+    responses(
+        (status = 200, description = "Success"),
+        (status = 403, description = "Not the author of the article"),
+        (status = 404, description = "Article or revision not found")
+    ),
+    params(
+        ("slug" = String, Path, description = "an article slug"),
+        ("revision" = i32, Path, description = "The revision to restore"),
+    ),
+    security(("bearer_auth" = []), ("api_key" = []))
+)]
+pub async fn restore_article_revision(
+    (path, auth, pool, audit): (web::Path<ArticleRevisionPath>, AuthUser, web::Data<PgPool>, AuditContext)
+) -> Result<HttpResponse, AppError> {
+    auth.require_scope(ApiScope::ArticlesWrite)?;
+
+    let pool = pool.get_ref();
+
+    let article_id = find_visible_article(&path.slug, Some(auth.id), pool).await?;
+    let (author_id, custom_slug): (Uuid, bool) = sqlx::query_as("SELECT author_id, custom_slug FROM articles WHERE id = $1")
+        .bind(article_id)
+        .fetch_one(pool)
+        .await?;
+
+    if auth.id != author_id {
+        audit
+            .record(AuditEventKind::Forbidden, Some(auth.id), serde_json::json!({
+                "action": "restore_article_revision",
+                "slug": path.slug,
+                "reason": "not_author",
+            }))
+            .await;
+
+        return Err(AppError::Forbidden(serde_json::json!({
+            "error": "user is not the author of article in question",
+        })));
+    }
+
+    let revision = find_revision(article_id, path.revision, pool).await?;
+    let slug = match custom_slug {
+        true => path.slug.to_owned(),
+        false => generate_slug(&article_id, &revision.title),
+    };
+
+    let mut transaction = pool.begin().await?;
+
+    sqlx::query("UPDATE articles SET slug = $1, title = $2, description = $3, body = $4, body_html = $6 WHERE id = $5")
+        .bind(&slug)
+        .bind(&revision.title)
+        .bind(&revision.description)
+        .bind(&revision.body)
+        .bind(article_id)
+        .bind(render_markdown(&revision.body))
+        .execute(&mut *transaction)
+        .await?;
+
+    record_revision(article_id, auth.id, Some(revision.revision), &mut transaction).await?;
+    transaction.commit().await?;
+
+    let article_response = get_article_response(slug, Some(auth.id), pool).await?;
+
+    Ok(HttpResponse::Ok().json(article_response))
+}
+
+// Some helpers for this route ------------------------------------------------------------
+
+/// Keep the current title, description and body of the article as its next revision
+///
+/// Nothing is written when they match the latest revision, e.g. after a change of status only,
+/// unless it is a restore which is always recorded
+pub async fn record_revision(
+    article_id: Uuid,
+    edited_by: Uuid,
+    restored_from: Option<i32>,
+    connection: &mut PgConnection,
+) -> Result<(), AppError> {
+    sqlx::query(r#"
+        INSERT INTO article_revisions (article_id, revision, title, description, body, edited_by, restored_from)
+        SELECT a.id, COALESCE(latest.revision, 0) + 1, a.title, a.description, a.body, $2, $3
+        FROM articles AS a
+        LEFT JOIN LATERAL (
+            SELECT revision, title, description, body
+            FROM article_revisions
+            WHERE article_id = a.id
+            ORDER BY revision DESC
+            LIMIT 1
+        ) AS latest ON TRUE
+        WHERE a.id = $1
+            AND (
+                latest.revision IS NULL
+                OR $3 IS NOT NULL
+                OR (latest.title, latest.description, latest.body) IS DISTINCT FROM (a.title, a.description, a.body)
+            )
+    "#)
+    .bind(article_id)
+    .bind(edited_by)
+    .bind(restored_from)
+    .execute(connection)
+    .await?;
+
+    Ok(())
+}
+
+async fn find_revision(article_id: Uuid, revision: i32, pool: &PgPool) -> Result<ArticleRevision, AppError> {
+    sqlx::query_as!(
+        ArticleRevision,
+        r#"
+        SELECT
+            r.revision, r.title, r.description, r.body, u.username AS "edited_by?", r.restored_from, r.created_at
+        FROM article_revisions AS r
+        LEFT JOIN users AS u ON u.id = r.edited_by
+        WHERE r.article_id = $1 AND r.revision = $2
+        "#,
+        article_id,
+        revision
+    )
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| AppError::NotFound(serde_json::json!({
+        "error": format!("Revision {} not found", revision),
+    })))
+}
@@ -1,10 +1,14 @@
-use actix_web::{web, HttpResponse};
+use actix_web::{web, HttpRequest, HttpResponse};
 use sqlx::{self, PgPool};
 
 use crate::schemas::*;
 use crate::errors::Error as AppError;
+use crate::pagination::{paginated_response, PageParams, Pagination};
 
 /// Return list of available tags
+///
+/// Tags of published articles only, sorted by name, the `X-Total-Count` header holds the number of tags and the `Link` header the other pages.
+/// All of them are returned unless `limit` or `offset` is given
 #[utoipa::path(
     get,
     path = "/api/v1/tags",
@@ -12,29 +16,43 @@ use crate::errors::Error as AppError;
     responses(
         (status = 200, description = "Success", body = TagsResponse),
         (status = 400, description = "Bad request")
+    ),
+    params(
+        ("limit" = Option<usize>, Query, description = "Page size, 20 when only `offset` is given and 100 at most"),
+        ("offset" = Option<usize>, Query, description = "Number of tags to skip"),
     )
 )]
 pub async fn get_tags(
-    pool: web::Data<PgPool>
+    (req, page, pool): (HttpRequest, web::Query<PageParams>, web::Data<PgPool>)
 ) -> Result<HttpResponse, AppError> {
     // Access the PgPool from the Data container
     let pool = pool.get_ref();
 
-    let result: Result<Vec<ArticleTag>, AppError> = sqlx::query_as!(
-        ArticleTag,
-        // "SELECT DISTINCT tag_name FROM article_tags"
-        "SELECT DISTINCT ON (tag_name) * FROM article_tags"
+    let limit = page.limit_if_paged();
+
+    let tag_list: Vec<String> = sqlx::query_scalar!(
+        r#"
+        SELECT DISTINCT t.tag_name FROM article_tags AS t
+        INNER JOIN articles AS a ON a.id = t.article_id
+        WHERE a.status = 'published'
+        ORDER BY t.tag_name LIMIT $1 OFFSET $2
+        "#,
+        limit,
+        page.offset()
     )
     .fetch_all(pool)
-    .await
-    .map_err(|err| {
-        let custom_err: AppError = err.into();
-        custom_err // Return your custom error directly
-    });
+    .await?;
 
-    let tags: Vec<ArticleTag> = result?;
-    let tag_list: Vec<String> = tags.iter().map(|tag| tag.tag_name.clone()).collect();
+    let total = sqlx::query_scalar!(r#"
+        SELECT COUNT(DISTINCT t.tag_name) FROM article_tags AS t
+        INNER JOIN articles AS a ON a.id = t.article_id
+        WHERE a.status = 'published'
+    "#)
+        .fetch_one(pool)
+        .await?
+        .unwrap_or(0);
 
-    Ok(HttpResponse::Ok().json(TagsResponse { tags: tag_list }))
-}
+    let pagination = Pagination::offset(total, limit.unwrap_or(total), page.offset());
 
+    Ok(paginated_response(&req, &TagsResponse { tags: tag_list }, &pagination))
+}
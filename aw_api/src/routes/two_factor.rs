@@ -0,0 +1,202 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use sqlx::{self, PgPool};
+use validator::Validate;
+
+use crate::auth::{
+    AuthUser, LoginThrottleKeys, begin_totp_enrollment, confirm_totp_enrollment, verify_second_factor, disable_totp,
+    decode_challenge_token, check_login_throttle, record_login_failure, clear_login_failures
+};
+use crate::clock::Clock;
+use crate::audit::{AuditContext, AuditEventKind};
+use crate::schemas::*;
+use crate::errors::Error as AppError;
+use crate::settings::ApplicationSettings;
+use crate::utils::validation_errors_response;
+
+use super::start_session;
+
+/// Start enrolling the current User in two-factor authentication
+///
+/// Add the returned `otpauth_uri` to an authenticator app, then confirm with a code at `/api/v1/users/2fa/confirm`.
+/// Enrolling again before confirming replaces the secret
+#[utoipa::path(
+    post,
+    path = "/api/v1/users/2fa/enroll",
+    tag = "users",
+    responses(
+        (status = 200, description = "Success", body = TotpEnrollmentResponse),
+        (status = 401, description = "Unauthorized"),
+        (status = 422, description = "Two-factor authentication already enabled")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn enroll_two_factor(
+    (auth, pool, settings): (AuthUser, web::Data<PgPool>, web::Data<ApplicationSettings>)
+) -> Result<HttpResponse, AppError> {
+    auth.require_session()?;
+
+    let pool = pool.get_ref();
+
+    let (email,): (String,) = sqlx::query_as("SELECT email FROM users WHERE id = $1")
+        .bind(auth.id)
+        .fetch_one(pool)
+        .await?;
+
+    let enrollment = begin_totp_enrollment(auth.id, &email, pool, &settings).await?;
+
+    Ok(HttpResponse::Ok().json(TotpEnrollmentResponse {
+        secret: enrollment.secret,
+        otpauth_uri: enrollment.otpauth_uri,
+    }))
+}
+
+/// Confirm the enrollment with a first code and turn two-factor authentication on
+///
+/// The recovery codes are only shown in this response, each one can replace a code once
+#[utoipa::path(
+    post,
+    path = "/api/v1/users/2fa/confirm",
+    tag = "users",
+    responses(
+        (status = 200, description = "Success", body = RecoveryCodesResponse),
+        (status = 400, description = "Bad request"),
+        (status = 401, description = "Unauthorized"),
+        (status = 422, description = "Invalid code or no pending enrollment")
+    ),
+    request_body = TwoFactorCode,
+    security(("bearer_auth" = []))
+)]
+pub async fn confirm_two_factor(
+    (form, auth, pool, clock, settings): (web::Json<TwoFactorCode>, AuthUser, web::Data<PgPool>, web::Data<dyn Clock>, web::Data<ApplicationSettings>)
+) -> Result<HttpResponse, AppError> {
+    auth.require_session()?;
+
+    let confirm_request = form.into_inner();
+
+    // Validate the user input
+    let validation_result = confirm_request.validate();
+    if let Err(validation_errors) = validation_result {
+        return Ok(validation_errors_response(&validation_errors));
+    }
+
+    let recovery_codes = confirm_totp_enrollment(auth.id, &confirm_request.code, clock.now(), pool.get_ref(), &settings).await?;
+
+    Ok(HttpResponse::Ok().json(RecoveryCodesResponse { recovery_codes }))
+}
+
+/// Turn two-factor authentication off for the current User
+///
+/// Needs a current code or a recovery code
+#[utoipa::path(
+    delete,
+    path = "/api/v1/users/2fa",
+    tag = "users",
+    responses(
+        (status = 200, description = "Success"),
+        (status = 400, description = "Bad request"),
+        (status = 401, description = "Unauthorized"),
+        (status = 422, description = "Invalid code"),
+        (status = 429, description = "Too many invalid codes, see the `Retry-After` header")
+    ),
+    request_body = TwoFactorCode,
+    security(("bearer_auth" = []))
+)]
+pub async fn disable_two_factor(
+    (form, auth, pool, clock, settings, audit): (web::Json<TwoFactorCode>, AuthUser, web::Data<PgPool>, web::Data<dyn Clock>, web::Data<ApplicationSettings>, AuditContext)
+) -> Result<HttpResponse, AppError> {
+    auth.require_session()?;
+
+    let disable_request = form.into_inner();
+
+    // Validate the user input
+    let validation_result = disable_request.validate();
+    if let Err(validation_errors) = validation_result {
+        return Ok(validation_errors_response(&validation_errors));
+    }
+
+    let pool = pool.get_ref();
+
+    // Guesses through here are throttled like logins
+    let throttle_keys = LoginThrottleKeys::for_user(auth.id);
+    check_login_throttle(&throttle_keys, pool).await?;
+
+    if !verify_second_factor(auth.id, &disable_request.code, clock.now(), pool, &settings).await? {
+        audit
+            .record(AuditEventKind::Forbidden, Some(auth.id), serde_json::json!({
+                "action": "disable_two_factor",
+                "reason": "invalid_two_factor_code",
+            }))
+            .await;
+
+        record_login_failure(&throttle_keys, Some(auth.id), &audit, pool, &settings).await?;
+
+        return Err(AppError::UnprocessableEntity(serde_json::json!({"error": "Invalid two-factor code"})));
+    }
+
+    clear_login_failures(&throttle_keys, pool).await?;
+
+    disable_totp(auth.id, pool).await?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "message": "Two-factor authentication disabled",
+    })))
+}
+
+/// Finish a login with the second factor
+///
+/// Send the `challenge_token` from `/api/v1/users/login` with a code from the authenticator app or a recovery code
+#[utoipa::path(
+    post,
+    path = "/api/v1/users/login/2fa",
+    tag = "users",
+    responses(
+        (status = 201, description = "Success"),
+        (status = 400, description = "Bad request"),
+        (status = 401, description = "Invalid challenge token or code"),
+        (status = 429, description = "Too many failed attempts, see the `Retry-After` header")
+    ),
+    request_body = TwoFactorLogin
+)]
+pub async fn login_two_factor(
+    (req, form, pool, clock, settings): (HttpRequest, web::Json<TwoFactorLogin>, web::Data<PgPool>, web::Data<dyn Clock>, web::Data<ApplicationSettings>)
+) -> Result<HttpResponse, AppError> {
+    let login_request = form.into_inner();
+
+    // Validate the user input
+    let validation_result = login_request.validate();
+    if let Err(validation_errors) = validation_result {
+        return Ok(validation_errors_response(&validation_errors));
+    }
+
+    let pool = pool.get_ref();
+
+    let user_id = decode_challenge_token(&login_request.challenge_token, &settings)?;
+    let (username, email) = sqlx::query_as::<_, (String, String)>("SELECT username, email FROM users WHERE id = $1")
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::Unauthorized(serde_json::json!({"error": "Invalid or expired challenge token"})))?;
+
+    // Wrong codes count against the same limits as wrong passwords
+    let throttle_keys = LoginThrottleKeys::new(&email, req.connection_info().peer_addr());
+    check_login_throttle(&throttle_keys, pool).await?;
+
+    if !verify_second_factor(user_id, &login_request.code, clock.now(), pool, &settings).await? {
+        let audit = AuditContext::new(&req, pool);
+
+        audit
+            .record(AuditEventKind::LoginFailed, Some(user_id), serde_json::json!({
+                "email": email,
+                "reason": "invalid_two_factor_code",
+            }))
+            .await;
+
+        record_login_failure(&throttle_keys, Some(user_id), &audit, pool, &settings).await?;
+
+        return Err(AppError::Unauthorized(serde_json::json!({"error": "Invalid two-factor code"})));
+    }
+
+    clear_login_failures(&throttle_keys, pool).await?;
+
+    start_session(&req, user_id, &username, "two_factor", pool, &settings).await
+}
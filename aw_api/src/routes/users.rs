@@ -1,19 +1,33 @@
-use actix_web::{web, HttpResponse, ResponseError, http::StatusCode};
+use actix_web::{web, HttpRequest, HttpResponse, ResponseError, http::StatusCode};
 use sqlx::{self, PgPool};
 use argon2::{
     password_hash::{PasswordHash, PasswordVerifier},
     Argon2,
 };
 use sqlx::Row;
+use uuid::Uuid;
 use validator::Validate;
 
+use crate::auth::{
+    AuthUser, SessionClient, generate_token, create_session, revoke_session, revoke_all_sessions,
+    issue_refresh_token, rotate_refresh_token, revoke_refresh_token_family,
+    decode_email_token, send_verification_email, VERIFY_EMAIL_PURPOSE,
+    request_password_reset, reset_password as reset_user_password,
+    LoginThrottleKeys, check_login_throttle, record_login_failure, clear_login_failures,
+    totp_enabled, generate_challenge_token, send_magic_link, redeem_magic_link,
+    start_cookie_session, end_cookie_session, removal_cookies
+};
+use crate::audit::{AuditContext, AuditEventKind};
 use crate::schemas::*;
 use crate::errors::Error as AppError;
-use crate::utils::{validation_errors_response, hash_password};
+use crate::mailer::Mailer;
+use crate::session_store::SessionStore;
+use crate::settings::{ApplicationSettings, AuthMode, SessionSettings};
+use crate::utils::{validation_errors_response, hash_password, verify_password};
 
 /// Register a new User
 ///
-/// Please wrap the payload with `user` key
+/// Please wrap the payload with `user` key, a link to verify the email address is mailed to the User
 #[utoipa::path(
     post,
     path = "/api/v1/users/register",
@@ -25,7 +39,7 @@ use crate::utils::{validation_errors_response, hash_password};
     request_body = UserRegister
 )]
 pub async fn register(
-    (form, pool): (web::Json<In<UserRegister>>, web::Data<PgPool>)
+    (form, pool, mailer, settings): (web::Json<In<UserRegister>>, web::Data<PgPool>, web::Data<dyn Mailer>, web::Data<ApplicationSettings>)
 ) -> Result<HttpResponse, AppError> {
     let register_user = form.into_inner().user;
 
@@ -41,14 +55,19 @@ pub async fn register(
     let pool = pool.get_ref();
 
     // Create a query and bind parameters
-    let query = sqlx::query("INSERT INTO users (email, username, password) VALUES ($1, $2, $3)")
+    let query = sqlx::query_as::<_, (Uuid,)>("INSERT INTO users (email, username, password) VALUES ($1, $2, $3) RETURNING id")
         .bind(&register_user.email)
         .bind(&register_user.username)
         .bind(password_hash.to_string());
 
     // Execute the query on the pool
-    match query.execute(pool).await {
-        Ok(_) => {
+    match query.fetch_one(pool).await {
+        Ok((user_id,)) => {
+            // The account exists either way, a lost email can be sent again from `users/verify/resend`
+            if let Err(err) = send_verification_email(&user_id, &register_user.email, mailer.get_ref(), &settings).await {
+                eprintln!("Failed to send the verification email: {:?}", err);
+            }
+
             let success_response = serde_json::json!({
                 "message": "Record created successfully",
             });
@@ -66,19 +85,24 @@ pub async fn register(
 
 /// Login
 ///
-/// Please wrap the payload with `user` key
+/// Please wrap the payload with `user` key, the returned `token` goes into the `Authorization: Bearer <token>` header
+/// and the `refresh_token` can be exchanged for a new pair at `/api/v1/users/refresh`.
+/// Users with two-factor authentication get a `challenge_token` instead, to send with their code to `/api/v1/users/login/2fa`.
+/// Repeated failures for an email or from an address are slowed down and eventually locked out
 #[utoipa::path(
     post,
     path = "/api/v1/users/login",
     tag = "users",
     responses(
         (status = 201, description = "Success", body = UserLogin),
-        (status = 400, description = "Bad request")
+        (status = 200, description = "Two-factor authentication required"),
+        (status = 400, description = "Bad request"),
+        (status = 429, description = "Too many failed attempts, see the `Retry-After` header")
     ),
     request_body = UserLogin
 )]
 pub async fn login(
-    (form, pool): (web::Json<In<UserLogin>>, web::Data<PgPool>)
+    (req, form, pool, settings): (HttpRequest, web::Json<In<UserLogin>>, web::Data<PgPool>, web::Data<ApplicationSettings>)
 ) -> Result<HttpResponse, AppError> {
     let login_user = form.into_inner().user;
 
@@ -90,8 +114,12 @@ pub async fn login(
 
     let pool = pool.get_ref();
 
+    // The peer address is used rather than the forwarded one since the headers can be spoofed
+    let throttle_keys = LoginThrottleKeys::new(&login_user.email, req.connection_info().peer_addr());
+    check_login_throttle(&throttle_keys, pool).await?;
+
     // Retrieve the user's stored password hash from the database based on their email
-    let query = sqlx::query("SELECT password FROM users WHERE email = $1")
+    let query = sqlx::query("SELECT id, username, password FROM users WHERE email = $1")
         .bind(&login_user.email);
 
     // Execute the query and fetch the result
@@ -109,12 +137,30 @@ pub async fn login(
                 .is_ok()
             {
                 // Passwords match; authentication successful
-                let success_response = serde_json::json!({
-                    "message": "Authentication successful",
-                });
-                Ok(HttpResponse::Ok().status(StatusCode::CREATED).json(success_response))
+                let user_id = row.get("id");
+
+                // The failures are only cleared once the second factor passed as well
+                if totp_enabled(user_id, pool).await? {
+                    return two_factor_challenge(&user_id, &settings);
+                }
+
+                clear_login_failures(&throttle_keys, pool).await?;
+
+                start_session(&req, user_id, row.get("username"), "password", pool, &settings).await
             } else {
                 // Passwords do not match; authentication failed
+                let user_id = row.get::<Uuid, _>("id");
+                let audit = AuditContext::new(&req, pool);
+
+                audit
+                    .record(AuditEventKind::LoginFailed, Some(user_id), serde_json::json!({
+                        "email": login_user.email,
+                        "reason": "invalid_password",
+                    }))
+                    .await;
+
+                record_login_failure(&throttle_keys, Some(user_id), &audit, pool, &settings).await?;
+
                 let error_response = serde_json::json!({
                     "error": "Authentication failed",
                 });
@@ -122,6 +168,20 @@ pub async fn login(
             }
         }
         Err(err) => {
+            // Unknown emails count as failures too, otherwise they could be probed without limit
+            if let sqlx::Error::RowNotFound = err {
+                let audit = AuditContext::new(&req, pool);
+
+                audit
+                    .record(AuditEventKind::LoginFailed, None, serde_json::json!({
+                        "email": login_user.email,
+                        "reason": "unknown_email",
+                    }))
+                    .await;
+
+                record_login_failure(&throttle_keys, None, &audit, pool, &settings).await?;
+            }
+
             // Handle errors (e.g., user not found, database error)
             let custom_err: AppError = err.into();
             Ok(custom_err.error_response())
@@ -129,23 +189,481 @@ pub async fn login(
     }
 }
 
-/// Update a User
+/// Ask for the second factor before `start_session`, the client continues at `/api/v1/users/login/2fa`
+fn two_factor_challenge(user_id: &Uuid, settings: &ApplicationSettings) -> Result<HttpResponse, AppError> {
+    let challenge_response = serde_json::json!({
+        "message": "Two-factor authentication required",
+        "two_factor_required": true,
+        "challenge_token": generate_challenge_token(user_id, settings)?,
+    });
+    Ok(HttpResponse::Ok().json(challenge_response))
+}
+
+/// Open a session for a User who passed every login step and respond with its tokens
+///
+/// In cookie mode the session goes into an HttpOnly cookie instead, next to the CSRF cookie.
+/// `method` is the last login step, e.g. `password` or `passkey`, kept in the audit log
+pub(crate) async fn start_session(
+    req: &HttpRequest,
+    user_id: Uuid,
+    username: &str,
+    method: &str,
+    pool: &PgPool,
+    settings: &ApplicationSettings,
+) -> Result<HttpResponse, AppError> {
+    let session_settings = req
+        .app_data::<web::Data<SessionSettings>>()
+        .ok_or(AppError::InternalServerError)?;
+
+    // Every login is a session the user can see and revoke later
+    let session_id = create_session(user_id, &SessionClient::from_request(req), pool).await?;
+
+    AuditContext::new(req, pool)
+        .record(AuditEventKind::Login, Some(user_id), serde_json::json!({
+            "method": method,
+            "session_id": session_id,
+        }))
+        .await;
+
+    if session_settings.mode == AuthMode::Cookie {
+        let store = req
+            .app_data::<web::Data<dyn SessionStore>>()
+            .ok_or(AppError::InternalServerError)?;
+        let cookie_session = start_cookie_session(session_id, store.get_ref(), session_settings).await?;
+
+        return Ok(HttpResponse::Created()
+            .cookie(cookie_session.session_cookie)
+            .cookie(cookie_session.csrf_cookie)
+            .json(serde_json::json!({
+                "message": "Authentication successful",
+                "csrf_token": cookie_session.csrf_token,
+            })));
+    }
+
+    let token = generate_token(&user_id, username, &session_id, settings)?;
+    let refresh_token = issue_refresh_token(user_id, session_id, pool, settings).await?;
+
+    let success_response = serde_json::json!({
+        "message": "Authentication successful",
+        "token": token,
+        "refresh_token": refresh_token,
+    });
+    Ok(HttpResponse::Ok().status(StatusCode::CREATED).json(success_response))
+}
+
+/// Verify the email address of a User
+///
+/// Opened from the link mailed on registration or by `users/verify/resend`
+#[utoipa::path(
+    get,
+    path = "/api/v1/users/verify",
+    tag = "users",
+    responses(
+        (status = 200, description = "Success"),
+        (status = 400, description = "Invalid or expired token")
+    ),
+    params(
+        ("token" = String, Query, description = "Token from the verification email"),
+    )
+)]
+pub async fn verify_email(
+    (params, pool, settings): (web::Query<VerifyEmailParams>, web::Data<PgPool>, web::Data<ApplicationSettings>)
+) -> Result<HttpResponse, AppError> {
+    let claims = decode_email_token(&params.token, VERIFY_EMAIL_PURPOSE, &settings)?;
+    let user_id = Uuid::parse_str(&claims.sub)
+        .map_err(|_| AppError::BadRequest(serde_json::json!({"error": "Invalid or expired token"})))?;
+
+    // Links sent to a previous address of the user no longer match
+    let result = sqlx::query(
+        "UPDATE users SET email_verified_at = COALESCE(email_verified_at, CURRENT_TIMESTAMP) WHERE id = $1 AND email = $2",
+    )
+    .bind(user_id)
+    .bind(&claims.email)
+    .execute(pool.get_ref())
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::BadRequest(serde_json::json!({"error": "Invalid or expired token"})));
+    }
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "message": "Email verified successfully",
+    })))
+}
+
+/// Send the verification email of the current User again
+#[utoipa::path(
+    post,
+    path = "/api/v1/users/verify/resend",
+    tag = "users",
+    responses(
+        (status = 200, description = "Success"),
+        (status = 401, description = "Unauthorized"),
+        (status = 422, description = "Email already verified")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn resend_verification_email(
+    (auth, pool, mailer, settings): (AuthUser, web::Data<PgPool>, web::Data<dyn Mailer>, web::Data<ApplicationSettings>)
+) -> Result<HttpResponse, AppError> {
+    auth.require_session()?;
+
+    if auth.email_verified {
+        return Err(AppError::UnprocessableEntity(serde_json::json!({
+            "error": "Email is already verified",
+        })));
+    }
+
+    let (email,): (String,) = sqlx::query_as("SELECT email FROM users WHERE id = $1")
+        .bind(auth.id)
+        .fetch_one(pool.get_ref())
+        .await?;
+
+    send_verification_email(&auth.id, &email, mailer.get_ref(), &settings).await?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "message": "Verification email sent",
+    })))
+}
+
+/// Ask for a password reset
+///
+/// Please wrap the payload with `user` key, a reset token is mailed when the address belongs to a User.
+/// The response is the same either way
+#[utoipa::path(
+    post,
+    path = "/api/v1/users/password/forgot",
+    tag = "users",
+    responses(
+        (status = 200, description = "Success"),
+        (status = 400, description = "Bad request")
+    ),
+    request_body = ForgotPassword
+)]
+pub async fn forgot_password(
+    (form, pool, mailer, settings): (web::Json<In<ForgotPassword>>, web::Data<PgPool>, web::Data<dyn Mailer>, web::Data<ApplicationSettings>)
+) -> Result<HttpResponse, AppError> {
+    let forgot_request = form.into_inner().user;
+
+    // Validate the user input
+    let validation_result = forgot_request.validate();
+    if let Err(validation_errors) = validation_result {
+        return Ok(validation_errors_response(&validation_errors));
+    }
+
+    request_password_reset(&forgot_request.email, pool.get_ref(), mailer.get_ref(), &settings).await?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "message": "If the address is registered, a password reset email has been sent",
+    })))
+}
+
+/// Reset the password
+///
+/// Please wrap the payload with `user` key, every session of the User is revoked afterwards
+#[utoipa::path(
+    post,
+    path = "/api/v1/users/password/reset",
+    tag = "users",
+    responses(
+        (status = 200, description = "Success"),
+        (status = 400, description = "Invalid or expired token")
+    ),
+    request_body = ResetPassword
+)]
+pub async fn reset_password(
+    (form, pool, audit): (web::Json<In<ResetPassword>>, web::Data<PgPool>, AuditContext)
+) -> Result<HttpResponse, AppError> {
+    let reset_request = form.into_inner().user;
+
+    // Validate the user input
+    let validation_result = reset_request.validate();
+    if let Err(validation_errors) = validation_result {
+        return Ok(validation_errors_response(&validation_errors));
+    }
+
+    let password_hash = hash_password(reset_request.password.as_bytes())?;
+
+    let user_id = reset_user_password(&reset_request.token, &password_hash, pool.get_ref()).await?;
+
+    audit
+        .record(AuditEventKind::PasswordChanged, Some(user_id), serde_json::json!({"method": "reset"}))
+        .await;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "message": "Password updated successfully, please login again",
+    })))
+}
+
+/// Ask for a login link
+///
+/// Please wrap the payload with `user` key, a one time login token is mailed when the address belongs to a User.
+/// The response is the same either way, unless too many links were asked for the address
+#[utoipa::path(
+    post,
+    path = "/api/v1/users/magic-link",
+    tag = "users",
+    responses(
+        (status = 200, description = "Success"),
+        (status = 400, description = "Bad request"),
+        (status = 429, description = "Too many links requested, see the `Retry-After` header")
+    ),
+    request_body = MagicLinkRequest
+)]
+pub async fn request_magic_link(
+    (form, pool, mailer, settings): (web::Json<In<MagicLinkRequest>>, web::Data<PgPool>, web::Data<dyn Mailer>, web::Data<ApplicationSettings>)
+) -> Result<HttpResponse, AppError> {
+    let magic_link_request = form.into_inner().user;
+
+    // Validate the user input
+    let validation_result = magic_link_request.validate();
+    if let Err(validation_errors) = validation_result {
+        return Ok(validation_errors_response(&validation_errors));
+    }
+
+    send_magic_link(&magic_link_request.email, pool.get_ref(), mailer.get_ref(), &settings).await?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "message": "If the address is registered, a login link has been sent",
+    })))
+}
+
+/// Login with the token of a login link
+///
+/// Responds like `/api/v1/users/login`, including the two-factor challenge for Users who enabled it
+#[utoipa::path(
+    post,
+    path = "/api/v1/users/magic-link/consume",
+    tag = "users",
+    responses(
+        (status = 201, description = "Success"),
+        (status = 200, description = "Two-factor authentication required"),
+        (status = 400, description = "Bad request"),
+        (status = 401, description = "Invalid, expired or already used token")
+    ),
+    request_body = MagicLinkLogin
+)]
+pub async fn consume_magic_link(
+    (req, form, pool, settings): (HttpRequest, web::Json<MagicLinkLogin>, web::Data<PgPool>, web::Data<ApplicationSettings>)
+) -> Result<HttpResponse, AppError> {
+    let magic_link_login = form.into_inner();
+
+    // Validate the user input
+    let validation_result = magic_link_login.validate();
+    if let Err(validation_errors) = validation_result {
+        return Ok(validation_errors_response(&validation_errors));
+    }
+
+    let pool = pool.get_ref();
+
+    let (user_id, username) = redeem_magic_link(&magic_link_login.token, pool, &settings).await?;
+
+    if totp_enabled(user_id, pool).await? {
+        return two_factor_challenge(&user_id, &settings);
+    }
+
+    start_session(&req, user_id, &username, "magic_link", pool, &settings).await
+}
+
+/// Refresh an access token
+///
+/// The refresh token is single use, the response carries the one to use next time
+#[utoipa::path(
+    post,
+    path = "/api/v1/users/refresh",
+    tag = "users",
+    responses(
+        (status = 200, description = "Success"),
+        (status = 400, description = "Bad request"),
+        (status = 401, description = "Invalid, expired or reused refresh token")
+    ),
+    request_body = RefreshTokenRequest
+)]
+pub async fn refresh(
+    (form, pool, settings): (web::Json<RefreshTokenRequest>, web::Data<PgPool>, web::Data<ApplicationSettings>)
+) -> Result<HttpResponse, AppError> {
+    let refresh_request = form.into_inner();
+
+    // Validate the user input
+    let validation_result = refresh_request.validate();
+    if let Err(validation_errors) = validation_result {
+        return Ok(validation_errors_response(&validation_errors));
+    }
+
+    let pool = pool.get_ref();
+
+    let rotated = rotate_refresh_token(&refresh_request.refresh_token, pool, &settings).await?;
+    let token = generate_token(&rotated.user_id, &rotated.username, &rotated.session_id, &settings)?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "token": token,
+        "refresh_token": rotated.refresh_token,
+    })))
+}
+
+/// Logout
+///
+/// Revokes the given refresh token together with every token rotated from the same login.
+/// In cookie mode no body is needed, the session of the cookie is revoked and the cookies are cleared
+#[utoipa::path(
+    post,
+    path = "/api/v1/users/logout",
+    tag = "users",
+    responses(
+        (status = 200, description = "Success"),
+        (status = 400, description = "Bad request"),
+        (status = 401, description = "Unknown refresh token"),
+        (status = 403, description = "Missing or invalid CSRF token")
+    ),
+    request_body = RefreshTokenRequest
+)]
+pub async fn logout(
+    (req, form, pool, session_settings, store): (HttpRequest, Option<web::Json<RefreshTokenRequest>>, web::Data<PgPool>, web::Data<SessionSettings>, web::Data<dyn SessionStore>)
+) -> Result<HttpResponse, AppError> {
+    if session_settings.mode == AuthMode::Cookie {
+        return logout_cookie_session(&req, pool.get_ref(), &session_settings, store.get_ref()).await;
+    }
+
+    let logout_request = match form {
+        Some(form) => form.into_inner(),
+        None => return Err(AppError::BadRequest(serde_json::json!({
+            "error": "A refresh token is required",
+        }))),
+    };
+
+    // Validate the user input
+    let validation_result = logout_request.validate();
+    if let Err(validation_errors) = validation_result {
+        return Ok(validation_errors_response(&validation_errors));
+    }
+
+    revoke_refresh_token_family(&logout_request.refresh_token, pool.get_ref()).await?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "message": "Logged out successfully",
+    })))
+}
+
+async fn logout_cookie_session(
+    req: &HttpRequest,
+    pool: &PgPool,
+    session_settings: &SessionSettings,
+    store: &dyn SessionStore,
+) -> Result<HttpResponse, AppError> {
+    if let Some(session_id) = end_cookie_session(req, store, session_settings).await? {
+        sqlx::query("UPDATE sessions SET revoked_at = CURRENT_TIMESTAMP WHERE id = $1 AND revoked_at IS NULL")
+            .bind(session_id)
+            .execute(pool)
+            .await?;
+    }
+
+    let [session_cookie, csrf_cookie] = removal_cookies(session_settings);
+
+    Ok(HttpResponse::Ok()
+        .cookie(session_cookie)
+        .cookie(csrf_cookie)
+        .json(serde_json::json!({
+            "message": "Logged out successfully",
+        })))
+}
+
+/// Return the active sessions of the current User
+#[utoipa::path(
+    get,
+    path = "/api/v1/users/sessions",
+    tag = "users",
+    responses(
+        (status = 200, description = "Success", body = SessionListResponse),
+        (status = 401, description = "Unauthorized")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn get_sessions(
+    (auth, pool): (AuthUser, web::Data<PgPool>)
+) -> Result<HttpResponse, AppError> {
+    let session_id = auth.require_session()?;
+
+    let pool = pool.get_ref();
+
+    let sessions = sqlx::query_as!(
+        Session,
+        "SELECT * FROM sessions WHERE user_id = $1 AND revoked_at IS NULL ORDER BY last_seen_at DESC",
+        auth.id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let sessions = sessions
+        .into_iter()
+        .map(|session| SessionResponseInner {
+            current: session.id == session_id,
+            id: session.id,
+            user_agent: session.user_agent,
+            ip_address: session.ip_address,
+            created_at: CustomDateTime(session.created_at),
+            last_seen_at: CustomDateTime(session.last_seen_at),
+        })
+        .collect();
+
+    Ok(HttpResponse::Ok().json(SessionListResponse { sessions }))
+}
+
+/// Revoke a session of the current User
+///
+/// The access and refresh tokens of that session stop working right away
+#[utoipa::path(
+    delete,
+    path = "/api/v1/users/sessions/{id}",
+    tag = "users",
+    responses(
+        (status = 200, description = "Success"),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Not Found")
+    ),
+    params(
+        ("id" = Uuid, Path, description = "Session id"),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn delete_session(
+    (path, auth, pool): (web::Path<SessionPath>, AuthUser, web::Data<PgPool>)
+) -> Result<HttpResponse, AppError> {
+    auth.require_session()?;
+
+    if revoke_session(path.id, auth.id, pool.get_ref()).await? {
+        Ok(HttpResponse::Ok().json(serde_json::json!({
+            "message": "Session revoked successfully",
+        })))
+    } else {
+        Ok(HttpResponse::NotFound().json(serde_json::json!({
+            "message": "Record not found for the provided id",
+        })))
+    }
+}
+
+/// Update the current User
 ///
-/// Please wrap the payload with `user` key
+/// Please wrap the payload with `user` key, a new `password` or `email` needs the `current_password`, a new `password` signs out every other session
 #[utoipa::path(
     put,
     path = "/api/v1/users/update",
     tag = "users",
     responses(
         (status = 201, description = "Success", body = UserUpdate),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Current password is incorrect"),
         (status = 404, description = "Not Found"),
+        (status = 429, description = "Too many incorrect current passwords"),
         (status = 400, description = "Bad request")
     ),
-    request_body = UserUpdate
+    request_body = UserUpdate,
+    security(("bearer_auth" = []))
 )]
 pub async fn update(
-    (form, pool): (web::Json<In<UserUpdate>>, web::Data<PgPool>)
+    (form, auth, pool, mailer, settings, audit): (web::Json<In<UserUpdate>>, AuthUser, web::Data<PgPool>, web::Data<dyn Mailer>, web::Data<ApplicationSettings>, AuditContext)
 ) -> Result<HttpResponse, AppError> {
+    let session_id = auth.require_session()?;
+
     let update_user = form.into_inner().user;
 
     // Validate the user input
@@ -154,21 +672,95 @@ pub async fn update(
         return Ok(validation_errors_response(&validation_errors));
     }
 
-    let hashed_password = hash_password(update_user.password.unwrap().as_bytes())?;
-
     let pool = pool.get_ref();
 
-    let query = sqlx::query(
-        "UPDATE users SET email = $1, password = $2, bio = $3 WHERE username = $4",
-    )
+    let (stored_email, stored_password_hash): (String, String) = sqlx::query_as("SELECT email, password FROM users WHERE id = $1")
+        .bind(auth.id)
+        .fetch_one(pool)
+        .await?;
+
+    // A stolen access token alone is not enough to take the account over
+    let email_changes = update_user.email.as_ref().is_some_and(|email| *email != stored_email);
+    if update_user.password.is_some() || email_changes {
+        let current_password = update_user.current_password.as_deref().ok_or_else(|| AppError::BadRequest(serde_json::json!({
+            "error": "current_password is required to change the password or the email",
+        })))?;
+
+        // Guesses through here are throttled like logins
+        let throttle_keys = LoginThrottleKeys::for_user(auth.id);
+        check_login_throttle(&throttle_keys, pool).await?;
+
+        if !verify_password(current_password.as_bytes(), &stored_password_hash) {
+            let action = if update_user.password.is_some() { "change_password" } else { "change_email" };
+
+            audit
+                .record(AuditEventKind::Forbidden, Some(auth.id), serde_json::json!({
+                    "action": action,
+                    "reason": "invalid_current_password",
+                }))
+                .await;
+
+            record_login_failure(&throttle_keys, Some(auth.id), &audit, pool, &settings).await?;
+
+            return Err(AppError::Forbidden(serde_json::json!({
+                "error": "Current password is incorrect",
+            })));
+        }
+
+        clear_login_failures(&throttle_keys, pool).await?;
+    }
+
+    // Keep the current password unless a new one is given
+    let hashed_password = match &update_user.password {
+        Some(password) => Some(hash_password(password.as_bytes())?),
+        None => None,
+    };
+
+    // A new address has to be verified again, `previous` still holds the row as it was before the update
+    let query = sqlx::query_as::<_, (String, bool)>(r#"
+        UPDATE users SET
+            email = $1,
+            password = COALESCE($2, users.password),
+            bio = $3,
+            username = COALESCE($4, users.username),
+            email_verified_at = CASE WHEN users.email = $1 THEN users.email_verified_at END
+        FROM users AS previous
+        WHERE users.id = $5 AND previous.id = users.id
+        RETURNING users.email, users.email IS DISTINCT FROM previous.email
+    "#)
     .bind(&update_user.email)
-    .bind(hashed_password.to_string())
+    .bind(hashed_password)
     .bind(&update_user.bio)
-    .bind(&update_user.username);
+    .bind(&update_user.username)
+    .bind(auth.id);
+
+    // The other sessions are signed out together with the password change, as on a reset
+    let mut tx = pool.begin().await?;
+
+    let updated = query.fetch_optional(&mut *tx).await;
+    if let Ok(Some(_)) = updated {
+        if update_user.password.is_some() {
+            revoke_all_sessions(auth.id, Some(session_id), &mut tx).await?;
+        }
+
+        tx.commit().await?;
+    }
+
+    match updated {
+        Ok(updated) => {
+            if let Some((email, email_changed)) = updated {
+                if update_user.password.is_some() {
+                    audit
+                        .record(AuditEventKind::PasswordChanged, Some(auth.id), serde_json::json!({"method": "update"}))
+                        .await;
+                }
+
+                if email_changed {
+                    if let Err(err) = send_verification_email(&auth.id, &email, mailer.get_ref(), &settings).await {
+                        eprintln!("Failed to send the verification email: {:?}", err);
+                    }
+                }
 
-    match query.execute(pool).await {
-        Ok(res) => {
-            if res.rows_affected() > 0 {
                 let success_response = serde_json::json!({
                     "message": "Record updated successfully",
                 });
@@ -188,38 +780,35 @@ pub async fn update(
     }
 }
 
-/// Delete a User
-///
-/// Please wrap the payload with `user` key
+/// Delete the current User
 #[utoipa::path(
     delete,
     path = "/api/v1/users/delete",
     tag = "users",
     responses(
-        (status = 200, description = "Success", body = UserDelete),
+        (status = 200, description = "Success"),
+        (status = 401, description = "Unauthorized"),
         (status = 400, description = "Bad request")
     ),
-    request_body = UserDelete
+    security(("bearer_auth" = []))
 )]
 pub async fn delete(
-    (form, pool): (web::Json<In<UserDelete>>, web::Data<PgPool>)
+    (auth, pool, audit): (AuthUser, web::Data<PgPool>, AuditContext)
 ) -> Result<HttpResponse, AppError> {
-    let user_info = form.into_inner().user;
-
-    // Validate the user input
-    let validation_result = user_info.validate();
-    if let Err(validation_errors) = validation_result {
-        return Ok(validation_errors_response(&validation_errors));
-    }
+    auth.require_session()?;
 
     let pool = pool.get_ref();
 
-    let query = sqlx::query("DELETE FROM users WHERE username = $1")
-        .bind(&user_info.username);
+    let query = sqlx::query("DELETE FROM users WHERE id = $1")
+        .bind(auth.id);
 
     match query.execute(pool).await {
         Ok(res) => {
             if res.rows_affected() > 0 {
+                audit
+                    .record(AuditEventKind::AccountDeleted, Some(auth.id), serde_json::json!({"username": auth.username}))
+                    .await;
+
                 let success_response = serde_json::json!({
                     "message": "Record deleted successfully",
                 });
@@ -0,0 +1,66 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::auth::ApiScope;
+
+use super::CustomDateTime;
+
+#[derive(Debug)]
+pub struct ApiKey {
+    pub id: Uuid,
+    pub name: String,
+    pub prefix: String,
+    pub scopes: Vec<ApiScope>,
+    pub expires_at: Option<NaiveDateTime>,
+    pub last_used_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Validate, Deserialize, ToSchema)]
+pub struct CreateApiKey {
+    // Shown in the list of keys, e.g. "CI bot"
+    #[validate(length(min = 1, max = 64, message = "fails validation - must be 1-64 characters long"))]
+    pub name: String,
+
+    #[validate(length(min = 1, message = "fails validation - cannot be empty"))]
+    pub scopes: Vec<ApiScope>,
+
+    // The key never expires when left out
+    #[validate(range(min = 1, max = 365, message = "fails validation - must be 1-365 days"))]
+    pub expires_in_days: Option<i32>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ApiKeyResponseInner {
+    pub id: Uuid,
+    pub name: String,
+    pub prefix: String,
+    pub scopes: Vec<ApiScope>,
+    pub expires_at: Option<CustomDateTime>,
+    pub last_used_at: Option<CustomDateTime>,
+    pub created_at: CustomDateTime,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CreatedApiKeyResponse {
+    pub id: Uuid,
+    pub name: String,
+    pub prefix: String,
+    pub scopes: Vec<ApiScope>,
+    pub expires_at: Option<CustomDateTime>,
+    // The full key, it cannot be retrieved again
+    pub key: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ApiKeyListResponse {
+    pub api_keys: Vec<ApiKeyResponseInner>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ApiKeyPath {
+    pub id: Uuid,
+}
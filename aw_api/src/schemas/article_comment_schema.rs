@@ -13,6 +13,7 @@ pub struct Comment {
     pub article_id: Uuid,
     pub user_id: Uuid,
     pub body: String,
+    pub body_html: Option<String>,
     pub created_at: NaiveDateTime,
     pub updated_at: NaiveDateTime,
 }
@@ -59,6 +60,7 @@ pub struct CommentResponseInner {
     pub created_at: CustomDateTime,
     pub updated_at: CustomDateTime,
     pub body: String,
+    pub body_html: String,
     pub author: ProfileResponseInner,
 }
 
@@ -87,6 +89,7 @@ impl<'r> FromRow<'r, PgRow> for CommentAndCommenter {
                 article_id: row.try_get("article_id")?,
                 user_id: row.try_get("user_id")?,
                 body: row.try_get("body")?,
+                body_html: row.try_get("body_html")?,
                 created_at: row.try_get("created_at")?,
                 updated_at: row.try_get("updated_at")?
             },
@@ -96,6 +99,7 @@ impl<'r> FromRow<'r, PgRow> for CommentAndCommenter {
                 email: row.try_get("email")?,
                 password: row.try_get("password")?,
                 bio: row.try_get("bio")?,
+                email_verified_at: row.try_get("email_verified_at")?,
                 created_at: row.try_get("created_at")?,
                 updated_at: row.try_get("updated_at")?,
             },
@@ -0,0 +1,69 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::CustomDateTime;
+
+#[derive(Debug)]
+pub struct ArticleRevision {
+    pub revision: i32,
+    pub title: String,
+    pub description: String,
+    pub body: String,
+    pub edited_by: Option<String>,
+    pub restored_from: Option<i32>,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ArticleRevisionResponseInner {
+    pub revision: i32,
+    pub title: String,
+    pub description: String,
+    pub body: String,
+    // Username of the editor, `None` once their account is deleted
+    pub edited_by: Option<String>,
+    pub restored_from: Option<i32>,
+    pub created_at: CustomDateTime,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ArticleRevisionListResponse {
+    pub revisions: Vec<ArticleRevisionResponseInner>,
+    pub revisions_count: usize,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum DiffMode {
+    // Line by line with `-`/`+` markers and `@@` hunks
+    #[default]
+    Unified,
+    // The new text with `[-removed-]` and `{+added+}` words
+    Word,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RevisionDiffParams {
+    pub from: i32,
+    pub to: i32,
+    #[serde(default)]
+    pub mode: DiffMode,
+}
+
+/// One diff per field, empty when the field did not change
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RevisionDiffResponse {
+    pub from: i32,
+    pub to: i32,
+    pub mode: DiffMode,
+    pub title: String,
+    pub description: String,
+    pub body: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ArticleRevisionPath {
+    pub slug: String,
+    pub revision: i32,
+}
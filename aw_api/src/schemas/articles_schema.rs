@@ -2,13 +2,17 @@ use uuid::Uuid;
 use serde::{Deserialize, Serialize, Serializer};
 use utoipa::ToSchema;
 use validator::Validate;
-use chrono::NaiveDateTime;
-use regex::Regex;
+use chrono::{DateTime, NaiveDateTime, Utc};
 use lazy_static::lazy_static;
+use regex::Regex;
 use crate::schemas::users_schema::User;
 
 use super::ProfileResponseInner;
 
+lazy_static! {
+    static ref RE_SLUG: Regex = Regex::new(r"^[a-z0-9]+(-[a-z0-9]+)*$").unwrap();
+}
+
 #[derive(Debug, PartialEq, ToSchema)]
 pub struct CustomDateTime(pub NaiveDateTime);
 
@@ -19,6 +23,18 @@ impl Serialize for CustomDateTime {
     }
 }
 
+/// Where an article is in its life, stored as the `article_status` type
+///
+/// Only published articles are listed, a draft with a `published_at` is scheduled
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema, sqlx::Type)]
+#[sqlx(type_name = "article_status", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum ArticleStatus {
+    Draft,
+    Published,
+    Archived,
+}
+
 #[derive(Debug)]
 pub struct Article {
     pub id: Uuid,
@@ -27,17 +43,62 @@ pub struct Article {
     pub title: String,
     pub description: String,
     pub body: String,
+    // Sanitized HTML of the body, `None` until it is rendered
+    pub body_html: Option<String>,
+    pub status: ArticleStatus,
+    pub published_at: Option<NaiveDateTime>,
     pub created_at: NaiveDateTime,
     pub updated_at: NaiveDateTime,
 }
 
+/// Order of the article list
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ArticleSort {
+    #[default]
+    Newest,
+    Oldest,
+    // Last edited first
+    Updated,
+    MostFavorited,
+    MostCommented,
+    // Same ranking as `/articles/trending`, from the periodically refreshed scores
+    Trending,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct ArticlesParams {
+    // Comma separated, an article has to carry every tag
     pub tag: Option<String>,
+    // Comma separated, an article by any of the authors matches
     pub author: Option<String>,
     pub favorited: Option<String>,
     pub limit: Option<usize>,
     pub offset: Option<usize>,
+    // `next_cursor` or `prev_cursor` of a previous page, `offset` is ignored when it is given
+    pub cursor: Option<String>,
+    #[serde(default)]
+    pub sort: ArticleSort,
+}
+
+impl ArticlesParams {
+    pub fn tags(&self) -> Vec<String> {
+        split_list(self.tag.as_deref())
+    }
+
+    pub fn authors(&self) -> Vec<String> {
+        split_list(self.author.as_deref())
+    }
+}
+
+fn split_list(value: Option<&str>) -> Vec<String> {
+    value
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(str::to_owned)
+        .collect()
 }
 
 #[derive(Debug, Serialize, ToSchema)]
@@ -51,7 +112,11 @@ pub struct ArticleResponseInner {
     pub title: String,
     pub description: String,
     pub body: String,
+    // The body rendered from Markdown, safe to embed as is
+    pub body_html: String,
     pub tag_list: Vec<String>,
+    pub status: ArticleStatus,
+    pub published_at: Option<CustomDateTime>,
     pub created_at: CustomDateTime,
     pub updated_at: CustomDateTime,
     pub favorited: bool,
@@ -62,33 +127,40 @@ pub struct ArticleResponseInner {
 #[derive(Debug, Serialize, ToSchema)]
 pub struct ArticleListResponse {
     pub articles: Vec<ArticleResponseInner>,
+    // Every article matching the request, not only the ones on this page
     pub articles_count: usize,
+    pub next_cursor: Option<String>,
+    pub prev_cursor: Option<String>,
 }
 
-#[derive(Debug)]
-pub struct ArticleAndAuthor {
-    pub article: Article,
-    pub author: User,
+#[derive(Debug, Validate, Deserialize)]
+pub struct ArticleSearchParams {
+    #[validate(length(min = 1, max = 200, message = "fails validation - must be 1-200 characters long"))]
+    pub q: String,
+    pub lang: Option<String>,
+    pub limit: Option<usize>,
+    pub offset: Option<usize>,
 }
 
-lazy_static! {
-    static ref RE_USERNAME: Regex = Regex::new(r"^[_0-9a-zA-Z]{3,}$").unwrap();
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ArticleSearchResult {
+    #[serde(flatten)]
+    pub article: ArticleResponseInner,
+    // HTML escaped excerpt with the matches wrapped in `<mark>`
+    pub snippet: String,
 }
 
-#[derive(Debug, Validate, Deserialize, ToSchema)]
-pub struct UserForArticle {
-    #[validate(
-        length(
-            min = 3,
-            max = 20,
-            message = "fails validation - must be 3-20 characters long"
-        ),
-        regex(
-            path = "RE_USERNAME",
-            message = "fails validation - is not only alphanumeric/underscore characters"
-        )
-    )]
-    pub username: String,
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ArticleSearchResponse {
+    pub articles: Vec<ArticleSearchResult>,
+    // Every match, not only the ones on this page
+    pub articles_count: usize,
+}
+
+#[derive(Debug)]
+pub struct ArticleAndAuthor {
+    pub article: Article,
+    pub author: User,
 }
 
 #[derive(Debug)]
@@ -115,6 +187,29 @@ pub struct CreateArticle {
 
     #[validate(length(min = 1, message = "fails validation - cannot be empty"))]
     pub tag_list: Vec<String>,
+
+    // Chosen by the author instead of generated from the title, kept when the title changes
+    #[validate(
+        length(
+            min = 3,
+            max = 100,
+            message = "fails validation - must be 3-100 characters long"
+        ),
+        regex(
+            path = "RE_SLUG",
+            message = "fails validation - is not only lowercase letters and digits separated by single hyphens"
+        )
+    )]
+    pub slug: Option<String>,
+
+    // Text search configuration of the article, one of `search_languages` in the settings
+    pub language: Option<String>,
+
+    // `published` by default, `draft` keeps it to the author until it is published
+    pub status: Option<ArticleStatus>,
+
+    // Schedules a draft, it is published once this time has passed
+    pub published_at: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug)]
@@ -126,6 +221,7 @@ pub struct CreateArticleOuter {
 pub struct FeedParams {
     pub limit: Option<usize>,
     pub offset: Option<usize>,
+    pub cursor: Option<String>,
 }
 
 #[derive(Debug)]
@@ -160,6 +256,27 @@ pub struct UpdateArticle {
 
     #[validate(length(min = 1, message = "fails validation - cannot be empty"))]
     pub tag_list: Option<Vec<String>>,
+
+    // Chosen by the author instead of generated from the title, kept when the title changes
+    #[validate(
+        length(
+            min = 3,
+            max = 100,
+            message = "fails validation - must be 3-100 characters long"
+        ),
+        regex(
+            path = "RE_SLUG",
+            message = "fails validation - is not only lowercase letters and digits separated by single hyphens"
+        )
+    )]
+    pub slug: Option<String>,
+
+    pub language: Option<String>,
+
+    pub status: Option<ArticleStatus>,
+
+    // Schedules the article as a draft, or moves the schedule of a draft
+    pub published_at: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug, Validate, Deserialize, ToSchema)]
@@ -0,0 +1,53 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::audit::AuditEventKind;
+
+use super::CustomDateTime;
+
+#[derive(Debug)]
+pub struct AuditEvent {
+    pub id: Uuid,
+    pub kind: AuditEventKind,
+    pub actor_id: Option<Uuid>,
+    pub actor_username: Option<String>,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    pub details: JsonValue,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AuditEventsParams {
+    pub kind: Option<AuditEventKind>,
+    pub actor_id: Option<Uuid>,
+    pub ip_address: Option<String>,
+    // Inclusive lower and exclusive upper bound on `created_at`, e.g. `2026-10-01T00:00:00`
+    pub since: Option<NaiveDateTime>,
+    pub until: Option<NaiveDateTime>,
+    pub limit: Option<usize>,
+    pub offset: Option<usize>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AuditEventResponseInner {
+    pub id: Uuid,
+    pub kind: AuditEventKind,
+    pub actor_id: Option<Uuid>,
+    // `None` once the account is deleted
+    pub actor_username: Option<String>,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    #[schema(value_type = Object)]
+    pub details: JsonValue,
+    pub created_at: CustomDateTime,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AuditEventListResponse {
+    pub audit_events: Vec<AuditEventResponseInner>,
+    pub total: i64,
+}
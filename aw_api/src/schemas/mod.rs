@@ -3,9 +3,21 @@ mod profile_schema;
 mod articles_schema;
 mod article_tag_schema;
 mod article_comment_schema;
+mod article_revision_schema;
+mod session_schema;
+mod two_factor_schema;
+mod passkey_schema;
+mod api_key_schema;
+mod audit_schema;
 
 pub use users_schema::*;
 pub use profile_schema::*;
 pub use articles_schema::*;
 pub use article_tag_schema::*;
-pub use article_comment_schema::*;
\ No newline at end of file
+pub use article_comment_schema::*;
+pub use article_revision_schema::*;
+pub use session_schema::*;
+pub use two_factor_schema::*;
+pub use passkey_schema::*;
+pub use api_key_schema::*;
+pub use audit_schema::*;
\ No newline at end of file
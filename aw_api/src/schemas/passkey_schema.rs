@@ -0,0 +1,68 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+use validator::Validate;
+
+use super::CustomDateTime;
+
+#[derive(Debug)]
+pub struct Passkey {
+    pub id: Uuid,
+    pub name: String,
+    pub created_at: NaiveDateTime,
+    pub last_used_at: Option<NaiveDateTime>,
+}
+
+/// `PublicKeyCredential` from `navigator.credentials.create()`, with the binary fields in base64url
+#[derive(Debug, Validate, Deserialize, ToSchema)]
+pub struct PasskeyRegistration {
+    pub id: String,
+    pub response: AttestationResponse,
+
+    // Shown in the list of passkeys, e.g. "Work laptop"
+    #[validate(length(min = 1, max = 64, message = "fails validation - must be 1-64 characters long"))]
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct AttestationResponse {
+    #[serde(rename = "clientDataJSON")]
+    pub client_data_json: String,
+    #[serde(rename = "attestationObject")]
+    pub attestation_object: String,
+}
+
+/// `PublicKeyCredential` from `navigator.credentials.get()`, with the binary fields in base64url
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct PasskeyAssertion {
+    pub id: String,
+    pub response: AssertionResponse,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct AssertionResponse {
+    #[serde(rename = "clientDataJSON")]
+    pub client_data_json: String,
+    #[serde(rename = "authenticatorData")]
+    pub authenticator_data: String,
+    pub signature: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PasskeyResponseInner {
+    pub id: Uuid,
+    pub name: String,
+    pub created_at: CustomDateTime,
+    pub last_used_at: Option<CustomDateTime>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PasskeyListResponse {
+    pub passkeys: Vec<PasskeyResponseInner>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PasskeyPath {
+    pub id: Uuid,
+}
@@ -35,9 +35,3 @@ pub struct Profile {
     )]
     pub username: String,
 }
-
-#[derive(Debug, Validate, Deserialize, ToSchema)]
-pub struct ProfileFollow {
-    #[validate(email(message = "fails validation - is not a valid email address"))]
-    pub email: String
-}
\ No newline at end of file
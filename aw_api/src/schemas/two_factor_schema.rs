@@ -0,0 +1,30 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::Validate;
+
+#[derive(Debug, Validate, Deserialize, ToSchema)]
+pub struct TwoFactorCode {
+    // A code from the authenticator app or a recovery code
+    #[validate(length(min = 1, message = "fails validation - cannot be empty"))]
+    pub code: String,
+}
+
+#[derive(Debug, Validate, Deserialize, ToSchema)]
+pub struct TwoFactorLogin {
+    #[validate(length(min = 1, message = "fails validation - cannot be empty"))]
+    pub challenge_token: String,
+
+    #[validate(length(min = 1, message = "fails validation - cannot be empty"))]
+    pub code: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TotpEnrollmentResponse {
+    pub secret: String,
+    pub otpauth_uri: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RecoveryCodesResponse {
+    pub recovery_codes: Vec<String>,
+}
@@ -1,12 +1,14 @@
 use lazy_static::lazy_static;
 use regex::Regex;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use validator::Validate;
 use chrono::NaiveDateTime;
 use uuid::Uuid;
 
 use utoipa::ToSchema;
 
+use crate::auth::Role;
+
 lazy_static! {
     static ref RE_USERNAME: Regex = Regex::new(r"^[_0-9a-zA-Z]{3,}$").unwrap();
 }
@@ -20,6 +22,7 @@ pub struct User {
     pub bio: Option<String>,
     pub created_at: NaiveDateTime,
     pub updated_at: NaiveDateTime,
+    pub email_verified_at: Option<NaiveDateTime>,
 }
 
 #[derive(Debug, Deserialize, ToSchema)]
@@ -92,22 +95,69 @@ pub struct UserUpdate {
     ))]
     pub password: Option<String>,
 
+    // Required along with a new `password` or `email`
+    pub current_password: Option<String>,
+
     #[validate(length(min = 1, message = "fails validation - cannot be empty"))]
     pub bio: Option<String>,
 }
 
 #[derive(Debug, Validate, Deserialize, ToSchema)]
-pub struct UserDelete {
-    #[validate(
-        length(
-            min = 1,
-            max = 20,
-            message = "fails validation - must be 1-20 characters long"
-        ),
-        regex(
-            path = "RE_USERNAME",
-            message = "fails validation - is not only alphanumeric/underscore characters"
-        )
-    )]
-    pub username: String
+pub struct RefreshTokenRequest {
+    #[validate(length(min = 1, message = "fails validation - cannot be empty"))]
+    pub refresh_token: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UserRolesPath {
+    pub username: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UserRolePath {
+    pub username: String,
+    pub role: Role,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct UserRolesResponse {
+    pub username: String,
+    pub roles: Vec<Role>,
+}
+
+
+#[derive(Debug, Deserialize)]
+pub struct VerifyEmailParams {
+    pub token: String,
+}
+#[derive(Debug, Validate, Deserialize, ToSchema)]
+pub struct ForgotPassword {
+    #[validate(email(message = "fails validation - is not a valid email address"))]
+    pub email: String,
+}
+
+#[derive(Debug, Validate, Deserialize, ToSchema)]
+pub struct ResetPassword {
+    #[validate(length(min = 1, message = "fails validation - cannot be empty"))]
+    pub token: String,
+
+    #[validate(length(
+        min = 8,
+        max = 72,
+        message = "fails validation - must be 8-72 characters long"
+    ))]
+    pub password: String,
+}
+
+
+#[derive(Debug, Validate, Deserialize, ToSchema)]
+pub struct MagicLinkRequest {
+    #[validate(email(message = "fails validation - is not a valid email address"))]
+    pub email: String,
+}
+
+#[derive(Debug, Validate, Deserialize, ToSchema)]
+pub struct MagicLinkLogin {
+    #[validate(length(min = 1, message = "fails validation - cannot be empty"))]
+    pub token: String,
 }
\ No newline at end of file
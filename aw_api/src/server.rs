@@ -1,37 +1,73 @@
 use actix_web::{dev::Server, web, App, HttpServer};
 use std::net::TcpListener;
-use utoipa::OpenApi;
+use std::sync::Arc;
+use std::time::Duration;
+use utoipa::{Modify, OpenApi};
+use utoipa::openapi::security::{ApiKey, ApiKeyValue, HttpAuthScheme, HttpBuilder, SecurityScheme};
 use utoipa_swagger_ui::SwaggerUi;
 
 use sqlx::{PgPool, postgres::PgPoolOptions};
 
-use crate::settings::{Settings, DatabaseSettings};
+use crate::settings::{Settings, DatabaseSettings, ApplicationSettings, SessionSettings};
+use crate::mailer::{get_mailer, Mailer};
+use crate::session_store::{get_session_store, SessionStore};
+use crate::trending::spawn_trending_refresh;
+use crate::publishing::spawn_scheduled_publishing;
+use crate::markdown::spawn_markdown_backfill;
+use crate::clock::{Clock, SystemClock};
 
 // Route handlers
 use crate::routes::{ping, third_party_api};
-use crate::routes::{register, login, update, delete}; // User handlers
+use crate::routes::{
+    register, verify_email, resend_verification_email, forgot_password, reset_password,
+    request_magic_link, consume_magic_link, login, refresh, logout, get_sessions, delete_session, update, delete,
+    enroll_two_factor, confirm_two_factor, disable_two_factor, login_two_factor,
+    passkey_registration_options, register_passkey, get_passkeys, remove_passkey, passkey_login_options, login_with_passkey,
+    add_api_key, get_api_keys, delete_api_key
+}; // User handlers
 use crate::routes::{get_profile, follow_profile, unfollow_profile}; // Profile handlers
 use crate::routes::get_tags; // Tag handlers
 use crate::routes::{
-    get_articles, create_article, get_articles_feed, get_articles_by_slug, update_articles_by_slug, delete_articles_by_slug,
+    get_articles, search_articles, get_trending_articles, get_article_drafts, create_article, get_articles_feed, get_articles_by_slug, update_articles_by_slug, delete_articles_by_slug,
     favorite_articles_by_slug, unfavorite_articles_by_slug,
-    get_articles_comments, add_articles_comments, delete_articles_comments
+    get_articles_comments, add_articles_comments, delete_articles_comments,
+    get_article_revisions, get_article_revision_diff, restore_article_revision
 }; // Article handlers
+use crate::routes::{get_user_roles, grant_user_role, revoke_user_role, get_audit_events}; // Admin handlers
 
 // OpenAPI Schema
 use crate::routes::{
     __path_ping,
-    __path_register, __path_login, __path_update, __path_delete,
+    __path_register, __path_verify_email, __path_resend_verification_email,
+    __path_forgot_password, __path_reset_password, __path_request_magic_link, __path_consume_magic_link, __path_login, __path_refresh, __path_logout, __path_get_sessions, __path_delete_session,
+    __path_update, __path_delete,
+    __path_enroll_two_factor, __path_confirm_two_factor, __path_disable_two_factor, __path_login_two_factor,
+    __path_passkey_registration_options, __path_register_passkey, __path_get_passkeys, __path_remove_passkey,
+    __path_passkey_login_options, __path_login_with_passkey,
+    __path_add_api_key, __path_get_api_keys, __path_delete_api_key,
     __path_get_profile, __path_follow_profile, __path_unfollow_profile,
     __path_get_tags,
-    __path_get_articles, __path_create_article, __path_get_articles_feed, __path_get_articles_by_slug, __path_update_articles_by_slug,
+    __path_get_articles, __path_search_articles, __path_get_trending_articles, __path_get_article_drafts, __path_create_article, __path_get_articles_feed, __path_get_articles_by_slug, __path_update_articles_by_slug,
     __path_delete_articles_by_slug, __path_favorite_articles_by_slug, __path_unfavorite_articles_by_slug,
-    __path_get_articles_comments, __path_add_articles_comments, __path_delete_articles_comments
+    __path_get_articles_comments, __path_add_articles_comments, __path_delete_articles_comments,
+    __path_get_article_revisions, __path_get_article_revision_diff, __path_restore_article_revision,
+    __path_get_user_roles, __path_grant_user_role, __path_revoke_user_role, __path_get_audit_events
 }; // Path
-use crate::schemas::{UserRegister, UserLogin, UserUpdate, UserDelete};
-use crate::schemas::{Profile, ProfileResponse, ProfileResponseInner, ProfileFollow};
+use crate::schemas::{MagicLinkRequest, MagicLinkLogin};
+use crate::schemas::{UserRegister, UserLogin, UserUpdate, ForgotPassword, ResetPassword, RefreshTokenRequest, SessionResponseInner, SessionListResponse, UserRolesResponse};
+use crate::schemas::{TwoFactorCode, TwoFactorLogin, TotpEnrollmentResponse, RecoveryCodesResponse};
+use crate::schemas::{
+    PasskeyRegistration, AttestationResponse, PasskeyAssertion, AssertionResponse, PasskeyResponseInner, PasskeyListResponse
+};
+use crate::schemas::{CreateApiKey, ApiKeyResponseInner, CreatedApiKeyResponse, ApiKeyListResponse};
+use crate::auth::{ApiScope, Role};
+use crate::audit::AuditEventKind;
+use crate::schemas::{AuditEventResponseInner, AuditEventListResponse};
+use crate::schemas::{Profile, ProfileResponse, ProfileResponseInner};
 use crate::schemas::{ArticleTag, TagsResponse};
 use crate::schemas::{CreateArticle, ArticleResponseInner, ArticleListResponse, UpdateArticleOuter, UpdateArticle, AddComment};
+use crate::schemas::{ArticleSearchResult, ArticleSearchResponse, ArticleSort, ArticleStatus};
+use crate::schemas::{ArticleRevisionResponseInner, ArticleRevisionListResponse, DiffMode, RevisionDiffResponse};
 
 pub fn get_connection_pool(
     configuration: &DatabaseSettings
@@ -48,6 +84,11 @@ pub struct Application {
 
 impl Application {
     pub async fn build_app(configuration: Settings) -> Result<Self, std::io::Error> {
+        Self::build_app_with_clock(configuration, Arc::new(SystemClock)).await
+    }
+
+    /// Same as `build_app` with another clock, the tests use a `FixedClock` to get predictable TOTP codes
+    pub async fn build_app_with_clock(configuration: Settings, clock: Arc<dyn Clock>) -> Result<Self, std::io::Error> {
         let connection_pool = get_connection_pool(&configuration.database);
 
         let address = format!(
@@ -57,7 +98,25 @@ impl Application {
 
         let listener = TcpListener::bind(address)?;
         let port = listener.local_addr().unwrap().port();
-        let server = start(listener, connection_pool)?;
+        let mailer = get_mailer(&configuration.email);
+        let session_store = get_session_store(&configuration.session, &connection_pool);
+
+        spawn_trending_refresh(connection_pool.clone(), configuration.trending.clone());
+        spawn_scheduled_publishing(
+            connection_pool.clone(),
+            Duration::from_secs(configuration.application.scheduled_publish_interval_seconds),
+        );
+        spawn_markdown_backfill(connection_pool.clone());
+
+        let server = start(
+            listener,
+            connection_pool,
+            configuration.application,
+            configuration.session,
+            session_store,
+            mailer,
+            clock,
+        )?;
 
         Ok(Self { port, server })
     }
@@ -71,24 +130,59 @@ impl Application {
     }
 }
 
+// Registers the `Authorization: Bearer <token>` scheme used by the protected routes
+struct SecurityAddon;
+
+impl Modify for SecurityAddon {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        if let Some(components) = openapi.components.as_mut() {
+            components.add_security_scheme(
+                "bearer_auth",
+                SecurityScheme::Http(
+                    HttpBuilder::new()
+                        .scheme(HttpAuthScheme::Bearer)
+                        .bearer_format("JWT")
+                        .build(),
+                ),
+            );
+            components.add_security_scheme(
+                "api_key",
+                SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::new("X-Api-Key"))),
+            );
+        }
+    }
+}
+
 pub fn start(
     listener: TcpListener,
-    db_pool: PgPool
+    db_pool: PgPool,
+    app_settings: ApplicationSettings,
+    session_settings: SessionSettings,
+    session_store: Arc<dyn SessionStore>,
+    mailer: Arc<dyn Mailer>,
+    clock: Arc<dyn Clock>
 ) -> Result<Server, std::io::Error> {
     #[derive(OpenApi)]
     #[openapi(
         paths(
             ping,
             // user paths
-            register, login, update, delete,
+            register, verify_email, resend_verification_email, forgot_password, reset_password,
+            request_magic_link, consume_magic_link, login, refresh, logout, get_sessions, delete_session, update, delete,
+            enroll_two_factor, confirm_two_factor, disable_two_factor, login_two_factor,
+            passkey_registration_options, register_passkey, get_passkeys, remove_passkey, passkey_login_options, login_with_passkey,
+            add_api_key, get_api_keys, delete_api_key,
             // Profile
             get_profile, follow_profile, unfollow_profile,
             // Tag
             get_tags,
             // Articles
-            get_articles, create_article, get_articles_feed, get_articles_by_slug, update_articles_by_slug, delete_articles_by_slug,
+            get_articles, search_articles, get_trending_articles, get_article_drafts, create_article, get_articles_feed, get_articles_by_slug, update_articles_by_slug, delete_articles_by_slug,
             favorite_articles_by_slug, unfavorite_articles_by_slug,
-            get_articles_comments, add_articles_comments, delete_articles_comments
+            get_articles_comments, add_articles_comments, delete_articles_comments,
+            get_article_revisions, get_article_revision_diff, restore_article_revision,
+            // Admin
+            get_user_roles, grant_user_role, revoke_user_role, get_audit_events
         ),
         info(
             title = "Actix-web RESTful",
@@ -99,16 +193,27 @@ pub fn start(
         ),
         components(
             schemas(
-                UserRegister, UserLogin, UserUpdate, UserDelete,
-                Profile, ProfileResponse, ProfileResponseInner, ProfileFollow,
+                UserRegister, UserLogin, UserUpdate, ForgotPassword, ResetPassword, MagicLinkRequest, MagicLinkLogin, RefreshTokenRequest, SessionResponseInner, SessionListResponse,
+                UserRolesResponse, Role, AuditEventKind, AuditEventResponseInner, AuditEventListResponse,
+                TwoFactorCode, TwoFactorLogin, TotpEnrollmentResponse, RecoveryCodesResponse,
+                PasskeyRegistration, AttestationResponse, PasskeyAssertion, AssertionResponse, PasskeyResponseInner, PasskeyListResponse,
+                CreateApiKey, ApiKeyResponseInner, CreatedApiKeyResponse, ApiKeyListResponse, ApiScope,
+                Profile, ProfileResponse, ProfileResponseInner,
                 ArticleTag, TagsResponse, CreateArticle, ArticleResponseInner, ArticleListResponse, UpdateArticleOuter,
-                UpdateArticle, AddComment
+                UpdateArticle, AddComment, ArticleSearchResult, ArticleSearchResponse, ArticleSort, ArticleStatus,
+                ArticleRevisionResponseInner, ArticleRevisionListResponse, DiffMode, RevisionDiffResponse
             ),
-        )
+        ),
+        modifiers(&SecurityAddon)
     )]
     struct ApiDoc;
 
     let db_pool_data = web::Data::new(db_pool);
+    let app_settings_data = web::Data::new(app_settings);
+    let session_settings_data = web::Data::new(session_settings);
+    let session_store_data: web::Data<dyn SessionStore> = web::Data::from(session_store);
+    let mailer_data: web::Data<dyn Mailer> = web::Data::from(mailer);
+    let clock_data: web::Data<dyn Clock> = web::Data::from(clock);
 
     let server = HttpServer::new(move || {
         App::new()
@@ -116,6 +221,11 @@ pub fn start(
                 SwaggerUi::new("/apidoc/{_:.*}").url("/api-docs/openapi.json", ApiDoc::openapi()),
             )
             .app_data(db_pool_data.clone())
+            .app_data(app_settings_data.clone())
+            .app_data(session_settings_data.clone())
+            .app_data(session_store_data.clone())
+            .app_data(mailer_data.clone())
+            .app_data(clock_data.clone())
 
             // Ping route ---------------------------------------------------------------
             .route("/ping", web::get().to(ping))
@@ -130,10 +240,99 @@ pub fn start(
                                 web::resource("users/register")
                                     .route(web::post().to(register))
                             )
+                            .service(
+                                web::resource("users/verify")
+                                    .route(web::get().to(verify_email))
+                            )
+                            .service(
+                                web::resource("users/verify/resend")
+                                    .route(web::post().to(resend_verification_email))
+                            )
+                            .service(
+                                web::resource("users/password/forgot")
+                                    .route(web::post().to(forgot_password))
+                            )
+                            .service(
+                                web::resource("users/password/reset")
+                                    .route(web::post().to(reset_password))
+                            )
+                            .service(
+                                web::resource("users/magic-link")
+                                    .route(web::post().to(request_magic_link))
+                            )
+                            .service(
+                                web::resource("users/magic-link/consume")
+                                    .route(web::post().to(consume_magic_link))
+                            )
                             .service(
                                 web::resource("users/login")
                                     .route(web::post().to(login))
                             )
+                            .service(
+                                web::resource("users/login/2fa")
+                                    .route(web::post().to(login_two_factor))
+                            )
+                            .service(
+                                web::resource("users/refresh")
+                                    .route(web::post().to(refresh))
+                            )
+                            .service(
+                                web::resource("users/logout")
+                                    .route(web::post().to(logout))
+                            )
+                            .service(
+                                web::resource("users/sessions")
+                                    .route(web::get().to(get_sessions))
+                            )
+                            .service(
+                                web::resource("users/sessions/{id}")
+                                    .route(web::delete().to(delete_session))
+                            )
+                            .service(
+                                web::resource("users/2fa")
+                                    .route(web::delete().to(disable_two_factor))
+                            )
+                            .service(
+                                web::resource("users/2fa/enroll")
+                                    .route(web::post().to(enroll_two_factor))
+                            )
+                            .service(
+                                web::resource("users/2fa/confirm")
+                                    .route(web::post().to(confirm_two_factor))
+                            )
+                            .service(
+                                web::resource("users/passkeys")
+                                    .route(web::get().to(get_passkeys))
+                            )
+                            .service(
+                                web::resource("users/passkeys/register/start")
+                                    .route(web::post().to(passkey_registration_options))
+                            )
+                            .service(
+                                web::resource("users/passkeys/register/finish")
+                                    .route(web::post().to(register_passkey))
+                            )
+                            .service(
+                                web::resource("users/passkeys/login/start")
+                                    .route(web::post().to(passkey_login_options))
+                            )
+                            .service(
+                                web::resource("users/passkeys/login/finish")
+                                    .route(web::post().to(login_with_passkey))
+                            )
+                            .service(
+                                web::resource("users/passkeys/{id}")
+                                    .route(web::delete().to(remove_passkey))
+                            )
+                            .service(
+                                web::resource("users/api-keys")
+                                    .route(web::get().to(get_api_keys))
+                                    .route(web::post().to(add_api_key))
+                            )
+                            .service(
+                                web::resource("users/api-keys/{id}")
+                                    .route(web::delete().to(delete_api_key))
+                            )
                             .service(
                                 web::resource("users/update")
                                     // .route(web::get().to(users::get_current))
@@ -159,13 +358,22 @@ pub fn start(
                             .service(
                                 web::resource("articles")
                                     .route(web::get().to(get_articles))
+                                    .route(web::post().to(create_article))
                             )
                             .service(
-                                web::resource("articles/{username}")
-                                    .route(web::post().to(create_article))
+                                web::resource("articles/search")
+                                    .route(web::get().to(search_articles))
                             )
                             .service(
-                                web::resource("articles/feed/{username}")
+                                web::resource("articles/trending")
+                                    .route(web::get().to(get_trending_articles))
+                            )
+                            .service(
+                                web::resource("articles/drafts")
+                                    .route(web::get().to(get_article_drafts))
+                            )
+                            .service(
+                                web::resource("articles/feed")
                                     .route(web::get().to(get_articles_feed))
                             )
                             .service(
@@ -174,6 +382,18 @@ pub fn start(
                                     .route(web::put().to(update_articles_by_slug))
                                     .route(web::delete().to(delete_articles_by_slug))
                             )
+                            .service(
+                                web::resource("articles/data/{slug}/revisions")
+                                    .route(web::get().to(get_article_revisions))
+                            )
+                            .service(
+                                web::resource("articles/data/{slug}/revisions/diff")
+                                    .route(web::get().to(get_article_revision_diff))
+                            )
+                            .service(
+                                web::resource("articles/data/{slug}/revisions/{revision}/restore")
+                                    .route(web::post().to(restore_article_revision))
+                            )
                             .service(
                                 web::resource("articles/favorite/{slug}")
                                     .route(web::post().to(favorite_articles_by_slug))
@@ -183,7 +403,10 @@ pub fn start(
                                 web::resource("articles/comments/{slug}")
                                     .route(web::get().to(get_articles_comments))
                                     .route(web::post().to(add_articles_comments))
-                                    .route(web::post().to(delete_articles_comments))
+                            )
+                            .service(
+                                web::resource("articles/comments/{slug}/{comment_id}")
+                                    .route(web::delete().to(delete_articles_comments))
                             )
 
                             // Tags routes ---------------------------------------------------------------
@@ -191,6 +414,21 @@ pub fn start(
                                 web::resource("tags")
                                     .route(web::get().to(get_tags))
                             )
+
+                            // Admin routes ---------------------------------------------------------------
+                            .service(
+                                web::resource("admin/users/{username}/roles")
+                                    .route(web::get().to(get_user_roles))
+                            )
+                            .service(
+                                web::resource("admin/users/{username}/roles/{role}")
+                                    .route(web::put().to(grant_user_role))
+                                    .route(web::delete().to(revoke_user_role))
+                            )
+                            .service(
+                                web::resource("admin/audit-events")
+                                    .route(web::get().to(get_audit_events))
+                            )
             )
     })
     .listen(listener)?
@@ -0,0 +1,54 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use uuid::Uuid;
+
+use crate::errors::Error as AppError;
+
+use super::SessionStore;
+
+/// Keeps the sessions in the process, every user is logged out on restart and they are not shared between instances
+#[derive(Default)]
+pub struct MemorySessionStore {
+    sessions: Mutex<HashMap<String, (Uuid, DateTime<Utc>)>>,
+}
+
+impl MemorySessionStore {
+    pub fn new() -> Self {
+        MemorySessionStore::default()
+    }
+}
+
+#[async_trait]
+impl SessionStore for MemorySessionStore {
+    async fn insert(&self, token_hash: &str, session_id: Uuid, lifetime_hours: i32) -> Result<(), AppError> {
+        let now = Utc::now();
+        let mut sessions = self.sessions.lock().map_err(|_| AppError::InternalServerError)?;
+
+        // Expired entries are never read again, clear them out on the way
+        sessions.retain(|_, (_, expires_at)| *expires_at > now);
+        sessions.insert(token_hash.to_owned(), (session_id, now + Duration::hours(lifetime_hours.into())));
+
+        Ok(())
+    }
+
+    async fn get(&self, token_hash: &str) -> Result<Option<Uuid>, AppError> {
+        let sessions = self.sessions.lock().map_err(|_| AppError::InternalServerError)?;
+
+        Ok(sessions
+            .get(token_hash)
+            .filter(|(_, expires_at)| *expires_at > Utc::now())
+            .map(|(session_id, _)| *session_id))
+    }
+
+    async fn remove(&self, token_hash: &str) -> Result<(), AppError> {
+        self.sessions
+            .lock()
+            .map_err(|_| AppError::InternalServerError)?
+            .remove(token_hash);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,7 @@
+mod store;
+mod postgres_store;
+mod memory_store;
+
+pub use store::*;
+pub use postgres_store::*;
+pub use memory_store::*;
\ No newline at end of file
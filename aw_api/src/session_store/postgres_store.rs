@@ -0,0 +1,60 @@
+use async_trait::async_trait;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::errors::Error as AppError;
+
+use super::SessionStore;
+
+/// Stores the sessions in the `cookie_sessions` table, they survive restarts and are shared between instances
+pub struct PostgresSessionStore {
+    pool: PgPool,
+}
+
+impl PostgresSessionStore {
+    pub fn new(pool: PgPool) -> Self {
+        PostgresSessionStore { pool }
+    }
+}
+
+#[async_trait]
+impl SessionStore for PostgresSessionStore {
+    async fn insert(&self, token_hash: &str, session_id: Uuid, lifetime_hours: i32) -> Result<(), AppError> {
+        // Expired rows are never read again, clear them out on the way
+        sqlx::query("DELETE FROM cookie_sessions WHERE expires_at <= CURRENT_TIMESTAMP")
+            .execute(&self.pool)
+            .await?;
+
+        sqlx::query(r#"
+            INSERT INTO cookie_sessions (token_hash, session_id, expires_at)
+            VALUES ($1, $2, CURRENT_TIMESTAMP + make_interval(hours => $3))
+        "#)
+        .bind(token_hash)
+        .bind(session_id)
+        .bind(lifetime_hours)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn get(&self, token_hash: &str) -> Result<Option<Uuid>, AppError> {
+        let session = sqlx::query_as::<_, (Uuid,)>(
+            "SELECT session_id FROM cookie_sessions WHERE token_hash = $1 AND expires_at > CURRENT_TIMESTAMP"
+        )
+        .bind(token_hash)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(session.map(|(session_id,)| session_id))
+    }
+
+    async fn remove(&self, token_hash: &str) -> Result<(), AppError> {
+        sqlx::query("DELETE FROM cookie_sessions WHERE token_hash = $1")
+            .bind(token_hash)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+}
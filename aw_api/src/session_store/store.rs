@@ -0,0 +1,30 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::errors::Error as AppError;
+use crate::settings::{SessionSettings, SessionStoreBackend};
+
+use super::{MemorySessionStore, PostgresSessionStore};
+
+/// Keeps the cookie sessions of browser clients, the backend is chosen in `settings.rs`
+///
+/// Only the hash of the cookie is stored, it points at the row in `sessions` used to list and revoke the login
+#[async_trait]
+pub trait SessionStore: Send + Sync {
+    async fn insert(&self, token_hash: &str, session_id: Uuid, lifetime_hours: i32) -> Result<(), AppError>;
+
+    /// The session of a cookie that has not expired yet
+    async fn get(&self, token_hash: &str) -> Result<Option<Uuid>, AppError>;
+
+    async fn remove(&self, token_hash: &str) -> Result<(), AppError>;
+}
+
+pub fn get_session_store(settings: &SessionSettings, pool: &PgPool) -> Arc<dyn SessionStore> {
+    match settings.store {
+        SessionStoreBackend::Postgres => Arc::new(PostgresSessionStore::new(pool.clone())),
+        SessionStoreBackend::Memory => Arc::new(MemorySessionStore::new()),
+    }
+}
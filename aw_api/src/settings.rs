@@ -7,6 +7,9 @@ use std::env;
 pub struct Settings {
     pub database: DatabaseSettings,
     pub application: ApplicationSettings,
+    pub email: EmailSettings,
+    pub session: SessionSettings,
+    pub trending: TrendingSettings,
     pub test_client: TestClientSettings
 }
 
@@ -19,6 +22,92 @@ pub struct  TestClientSettings {
 pub struct ApplicationSettings {
     pub port: u16,
     pub host: String,
+    // Public address of the API, used for the links sent by email
+    pub base_url: String,
+    pub jwt_secret: String,
+    pub jwt_expiration_minutes: i64,
+    pub refresh_token_expiration_days: i32,
+    pub email_verification_expiration_hours: i64,
+    pub password_reset_expiration_minutes: i32,
+    // Failed logins allowed per email address and per IP address before a lockout
+    pub login_max_failures: i32,
+    pub login_max_failures_per_ip: i32,
+    // Wait after the first failure, doubled by every following one
+    pub login_backoff_base_seconds: i32,
+    pub login_lockout_minutes: i32,
+    // Shown by authenticator apps next to the account name
+    pub totp_issuer: String,
+    pub two_factor_challenge_minutes: i64,
+    pub magic_link_expiration_minutes: i32,
+    // Links that can be requested for one address within an hour
+    pub magic_link_max_per_hour: i64,
+    // Relying party of the passkeys, the domain the frontend is served from, and its exact origin
+    pub webauthn_rp_id: String,
+    pub webauthn_rp_name: String,
+    pub webauthn_origin: String,
+    pub webauthn_challenge_minutes: i32,
+    // Postgres text search configurations articles can be written in, and the one used when none is given
+    pub search_languages: Vec<String>,
+    pub search_default_language: String,
+    // How often the drafts scheduled for publication are checked
+    pub scheduled_publish_interval_seconds: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MailerBackend {
+    Stdout,
+    File,
+    Smtp,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct EmailSettings {
+    pub backend: MailerBackend,
+    pub sender: String,
+    pub file_directory: Option<String>,
+    pub smtp_host: Option<String>,
+    pub smtp_port: Option<u16>,
+    pub smtp_username: Option<String>,
+    pub smtp_password: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuthMode {
+    Bearer,
+    Cookie,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SessionStoreBackend {
+    Postgres,
+    Memory,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SessionSettings {
+    // `bearer` answers logins with access and refresh tokens, `cookie` with an HttpOnly session cookie
+    pub mode: AuthMode,
+    pub store: SessionStoreBackend,
+    pub cookie_name: String,
+    // Readable by the frontend, which sends it back in `csrf_header_name` on every POST, PUT and DELETE
+    pub csrf_cookie_name: String,
+    pub csrf_header_name: String,
+    // Only send the cookies over HTTPS, turn it off for plain HTTP in development
+    pub cookie_secure: bool,
+    pub lifetime_hours: i32,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct TrendingSettings {
+    // Only the favorites, comments and views of the last `window_days` count
+    pub window_days: i32,
+    // Time after which an event weighs half as much
+    pub half_life_hours: f64,
+    // How often `article_trending_scores` is recomputed
+    pub refresh_seconds: u64,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -44,6 +133,25 @@ impl DatabaseSettings {
     }
 }
 
+/// `jwt_secret` of `config/development.toml`, anyone can sign tokens with it
+const DEVELOPMENT_JWT_SECRET: &str = "development-secret-please-change-me";
+
+impl Settings {
+    /// Refuse the settings that are only fit for development when running in `mode`
+    pub fn check_for(&self, mode: &Mode) -> Result<(), ConfigError> {
+        if let Mode::Production = mode {
+            let jwt_secret = self.application.jwt_secret.trim();
+            if jwt_secret.is_empty() || jwt_secret == DEVELOPMENT_JWT_SECRET {
+                return Err(ConfigError::Message(
+                    "application.jwt_secret has to be set in production, e.g. with APP__APPLICATION__JWT_SECRET".into(),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
 pub fn get_app_mode() -> Result<Settings, ConfigError> {
     let base_path = env::current_dir().expect("Failed to determine the current directory");
     let configuration_directory = base_path.join("config");
@@ -59,7 +167,10 @@ pub fn get_app_mode() -> Result<Settings, ConfigError> {
         .add_source(File::from(configuration_directory.join(run_mode.as_str())).required(true))
         .build()?;
 
-    settings.try_deserialize()
+    let settings: Settings = settings.try_deserialize()?;
+    settings.check_for(&run_mode)?;
+
+    Ok(settings)
 }
 
 pub enum Mode {
@@ -0,0 +1,92 @@
+use std::time::Duration;
+
+use sqlx::PgPool;
+
+use crate::errors::Error as AppError;
+use crate::settings::TrendingSettings;
+
+// What one event is worth before the time decay, a view counts far less than a favorite
+const FAVORITE_WEIGHT: f64 = 3.0;
+const COMMENT_WEIGHT: f64 = 2.0;
+const VIEW_WEIGHT: f64 = 0.1;
+
+/// Count a read of the article for today, the trending score uses the daily totals
+///
+/// Only published articles are counted, an author reading their own draft is not a view
+pub async fn record_view(slug: &str, pool: &PgPool) -> Result<(), AppError> {
+    sqlx::query(r#"
+        INSERT INTO article_daily_views (article_id, day, views)
+        SELECT id, CURRENT_DATE, 1 FROM articles WHERE slug = $1 AND status = 'published'
+        ON CONFLICT (article_id, day) DO UPDATE SET views = article_daily_views.views + 1
+    "#)
+    .bind(slug)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Recompute `article_trending_scores` from the favorites, comments and views within the window
+///
+/// Every event loses half of its weight each `half_life_hours`, articles without activity in the
+/// window are left out. The table is replaced in one transaction so readers never see it half done
+pub async fn refresh_trending(pool: &PgPool, settings: &TrendingSettings) -> Result<u64, AppError> {
+    let mut transaction = pool.begin().await?;
+
+    // Concurrent refreshes, e.g. from several instances, wait for each other
+    sqlx::query("LOCK TABLE article_trending_scores IN SHARE ROW EXCLUSIVE MODE")
+        .execute(&mut *transaction)
+        .await?;
+
+    sqlx::query("DELETE FROM article_trending_scores")
+        .execute(&mut *transaction)
+        .await?;
+
+    let result = sqlx::query(r#"
+        WITH activity (article_id, weight, happened_at) AS (
+            SELECT article_id, $3::DOUBLE PRECISION, created_at
+            FROM favorite_articles
+            WHERE created_at > CURRENT_TIMESTAMP - make_interval(days => $1)
+            UNION ALL
+            SELECT article_id, $4::DOUBLE PRECISION, created_at
+            FROM comments
+            WHERE created_at > CURRENT_TIMESTAMP - make_interval(days => $1)
+            UNION ALL
+            SELECT article_id, $5::DOUBLE PRECISION * views, day::TIMESTAMP
+            FROM article_daily_views
+            WHERE day > (CURRENT_TIMESTAMP - make_interval(days => $1))::DATE
+        )
+        INSERT INTO article_trending_scores (article_id, score)
+        SELECT
+            article_id,
+            SUM(weight * exp(-ln(2) * GREATEST(EXTRACT(EPOCH FROM CURRENT_TIMESTAMP - happened_at), 0) / 3600 / $2))
+        FROM activity
+        GROUP BY article_id
+    "#)
+    .bind(settings.window_days)
+    .bind(settings.half_life_hours)
+    .bind(FAVORITE_WEIGHT)
+    .bind(COMMENT_WEIGHT)
+    .bind(VIEW_WEIGHT)
+    .execute(&mut *transaction)
+    .await?;
+
+    transaction.commit().await?;
+
+    Ok(result.rows_affected())
+}
+
+/// Refresh the trending scores now and then every `refresh_seconds` for as long as the server runs
+pub fn spawn_trending_refresh(pool: PgPool, settings: TrendingSettings) {
+    actix_web::rt::spawn(async move {
+        let mut interval = actix_web::rt::time::interval(Duration::from_secs(settings.refresh_seconds.max(1)));
+
+        loop {
+            interval.tick().await;
+
+            if let Err(err) = refresh_trending(&pool, &settings).await {
+                eprintln!("Trending Error: failed to refresh the scores: {:?}", err);
+            }
+        }
+    });
+}
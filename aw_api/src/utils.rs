@@ -2,7 +2,7 @@ use serde_json::{Map as JsonMap, Value as JsonValue};
 use actix_web::HttpResponse;
 use validator::ValidationErrors;
 use argon2::{
-    password_hash::{rand_core::OsRng, PasswordHasher, SaltString},
+    password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
     Argon2
 };
 
@@ -44,4 +44,11 @@ pub fn hash_password(password: &[u8]) -> Result<String, AppError> {
         .map_err(| _ | AppError::InternalServerError)?;
 
     Ok(password_hash.to_string())
+}
+
+/// Whether `password` matches the stored `password_hash`, an unreadable hash matches nothing
+pub fn verify_password(password: &[u8], password_hash: &str) -> bool {
+    PasswordHash::new(password_hash)
+        .map(|parsed_hash| Argon2::default().verify_password(password, &parsed_hash).is_ok())
+        .unwrap_or(false)
 }
\ No newline at end of file
@@ -0,0 +1,186 @@
+use crate::test_utils::start_test_server;
+
+#[actix_web::test]
+async fn grant_role_returns_a_403_for_non_admin() {
+    // Arrange
+    let app = start_test_server().await;
+
+    let token = app.register_and_login("test_devactivity", "test@devactivity.com").await;
+
+    // Act
+    let response = app.payload_for_put_with_token(String::new(), "api/v1/admin/users/test_devactivity/roles/admin", &token).await;
+
+    // Assert
+    assert_eq!(403, response.status().as_u16());
+}
+
+#[actix_web::test]
+async fn admin_can_grant_and_revoke_roles() {
+    // Arrange
+    let app = start_test_server().await;
+
+    app.register_and_login("test_admin", "admin@devactivity.com").await;
+    app.register_and_login("test_devactivity", "test@devactivity.com").await;
+    app.grant_role("test_admin", "admin").await;
+
+    // The role is read on every request, so a fresh login is not needed
+    let token = app.login("admin@devactivity.com", "12345678").await["token"].as_str().unwrap().to_string();
+
+    // Act
+    let response = app.payload_for_put_with_token(String::new(), "api/v1/admin/users/test_devactivity/roles/moderator", &token).await;
+
+    // Assert
+    assert_eq!(200, response.status().as_u16());
+
+    let body: serde_json::Value = serde_json::from_str(&response.text().await.unwrap()).unwrap();
+    assert_eq!(serde_json::json!(["moderator"]), body["roles"]);
+
+    // Act
+    let response = app.payload_for_delete_with_token(String::new(), "api/v1/admin/users/test_devactivity/roles/moderator", &token).await;
+
+    // Assert
+    assert_eq!(200, response.status().as_u16());
+
+    let body: serde_json::Value = serde_json::from_str(&response.text().await.unwrap()).unwrap();
+    assert_eq!(serde_json::json!([]), body["roles"]);
+}
+
+#[actix_web::test]
+async fn moderator_can_delete_any_article_and_comment() {
+    // Arrange
+    let app = start_test_server().await;
+
+    let author_token = app.register_and_login("test_author", "author@devactivity.com").await;
+    let reader_token = app.register_and_login("test_reader", "reader@devactivity.com").await;
+    let moderator_token = app.register_and_login("test_moderator", "moderator@devactivity.com").await;
+    app.grant_role("test_moderator", "moderator").await;
+
+    let payload = serde_json::json!({
+        "body": "this is body article",
+        "description": "the most interesting topic",
+        "tagList": ["interest"],
+        "title": "the-interesting-topic"
+    });
+    let response = app.payload_for_post_with_token(payload.to_string(), "api/v1/articles", &author_token).await;
+    let body: serde_json::Value = serde_json::from_str(&response.text().await.unwrap()).unwrap();
+    let slug = body["article"]["slug"].as_str().unwrap().to_string();
+
+    let payload = serde_json::json!({ "body": "first!" });
+    let response = app.payload_for_post_with_token(payload.to_string(), format!("api/v1/articles/comments/{}", slug).as_str(), &reader_token).await;
+    let body: serde_json::Value = serde_json::from_str(&response.text().await.unwrap()).unwrap();
+    let comment_id = body["comment"]["id"].as_i64().unwrap();
+
+    // Act
+    let response = app.payload_for_delete_with_token(String::new(), format!("api/v1/articles/comments/{}/{}", slug, comment_id).as_str(), &author_token).await;
+
+    // Assert
+    assert_eq!(403, response.status().as_u16());
+
+    // Act
+    let response = app.payload_for_delete_with_token(String::new(), format!("api/v1/articles/comments/{}/{}", slug, comment_id).as_str(), &moderator_token).await;
+
+    // Assert
+    assert_eq!(200, response.status().as_u16());
+
+    // Act
+    let response = app.payload_for_delete_with_token(String::new(), format!("api/v1/articles/data/{}", slug).as_str(), &reader_token).await;
+
+    // Assert
+    assert_eq!(403, response.status().as_u16());
+
+    // Act
+    let response = app.payload_for_delete_with_token(String::new(), format!("api/v1/articles/data/{}", slug).as_str(), &moderator_token).await;
+
+    // Assert
+    assert_eq!(200, response.status().as_u16());
+}
+
+#[actix_web::test]
+async fn audit_log_records_authentication_and_role_events() {
+    // Arrange
+    let app = start_test_server().await;
+
+    app.register_and_login("test_admin", "admin@devactivity.com").await;
+    app.register_and_login("test_devactivity", "test@devactivity.com").await;
+    app.grant_role("test_admin", "admin").await;
+
+    let token = app.login("admin@devactivity.com", "12345678").await["token"].as_str().unwrap().to_string();
+
+    // A client supplied forwarded address is not trusted
+    let payload = serde_json::json!({"user": {"email": "test@devactivity.com", "password": "wrong-password"}});
+    reqwest::Client::new()
+        .post(format!("{}/api/v1/users/login", &app.address))
+        .header("Content-Type", "application/json")
+        .header("X-Forwarded-For", "203.0.113.7")
+        .body(payload.to_string())
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    app.payload_for_put_with_token(String::new(), "api/v1/admin/users/test_devactivity/roles/moderator", &token).await;
+
+    // Act
+    let failed_logins = app.payload_for_get_with_token("api/v1/admin/audit-events?kind=login_failed", &token).await;
+    let role_changes = app.payload_for_get_with_token("api/v1/admin/audit-events?kind=role_granted", &token).await;
+    let logins = app.payload_for_get_with_token("api/v1/admin/audit-events?kind=login&limit=1", &token).await;
+
+    // Assert
+    assert_eq!(200, failed_logins.status().as_u16());
+
+    let body: serde_json::Value = serde_json::from_str(&failed_logins.text().await.unwrap()).unwrap();
+    assert_eq!(1, body["total"]);
+    assert_eq!("test_devactivity", body["audit_events"][0]["actor_username"]);
+    assert_eq!("invalid_password", body["audit_events"][0]["details"]["reason"]);
+    assert_eq!("127.0.0.1", body["audit_events"][0]["ip_address"]);
+
+    let body: serde_json::Value = serde_json::from_str(&role_changes.text().await.unwrap()).unwrap();
+    assert_eq!(1, body["total"]);
+    assert_eq!("test_admin", body["audit_events"][0]["actor_username"]);
+    assert_eq!("moderator", body["audit_events"][0]["details"]["role"]);
+
+    // Three logins, one page of one
+    let body: serde_json::Value = serde_json::from_str(&logins.text().await.unwrap()).unwrap();
+    assert_eq!(3, body["total"]);
+    assert_eq!(1, body["audit_events"].as_array().unwrap().len());
+    assert_eq!("password", body["audit_events"][0]["details"]["method"]);
+}
+
+#[actix_web::test]
+async fn audit_log_records_forbidden_attempts_and_is_append_only() {
+    // Arrange
+    let app = start_test_server().await;
+
+    let author_token = app.register_and_login("test_author", "author@devactivity.com").await;
+    let reader_token = app.register_and_login("test_reader", "reader@devactivity.com").await;
+    app.register_and_login("test_admin", "admin@devactivity.com").await;
+    app.grant_role("test_admin", "admin").await;
+
+    let admin_token = app.login("admin@devactivity.com", "12345678").await["token"].as_str().unwrap().to_string();
+
+    let payload = serde_json::json!({
+        "body": "this is body article",
+        "description": "the most interesting topic",
+        "tagList": ["interest"],
+        "title": "the-interesting-topic"
+    });
+    let response = app.payload_for_post_with_token(payload.to_string(), "api/v1/articles", &author_token).await;
+    let body: serde_json::Value = serde_json::from_str(&response.text().await.unwrap()).unwrap();
+    let slug = body["article"]["slug"].as_str().unwrap().to_string();
+
+    let response = app.payload_for_delete_with_token(String::new(), format!("api/v1/articles/data/{}", slug).as_str(), &reader_token).await;
+    assert_eq!(403, response.status().as_u16());
+
+    // Act
+    let response = app.payload_for_get_with_token("api/v1/admin/audit-events?kind=forbidden", &admin_token).await;
+    let non_admin = app.payload_for_get_with_token("api/v1/admin/audit-events", &reader_token).await;
+
+    // Assert
+    let body: serde_json::Value = serde_json::from_str(&response.text().await.unwrap()).unwrap();
+    assert_eq!(1, body["total"]);
+    assert_eq!("test_reader", body["audit_events"][0]["actor_username"]);
+    assert_eq!("delete_article", body["audit_events"][0]["details"]["action"]);
+
+    assert_eq!(403, non_admin.status().as_u16());
+
+    let tampering = sqlx::query("DELETE FROM audit_events").execute(&app.db_pool).await;
+    assert!(tampering.is_err());
+}
@@ -0,0 +1,108 @@
+use crate::test_utils::start_test_server;
+
+fn article_payload() -> String {
+    serde_json::json!({
+        "body": "this is body article",
+        "description": "the most interesting topic",
+        "tagList": ["interest"],
+        "title": "the-interesting-topic"
+    })
+    .to_string()
+}
+
+#[actix_web::test]
+async fn api_key_with_the_scope_can_create_articles() {
+    // Arrange
+    let app = start_test_server().await;
+
+    let token = app.register_and_login("test_devactivity", "test@devactivity.com").await;
+    let created = app.create_api_key(&token, &["articles:write"]).await;
+    let key = created["key"].as_str().unwrap();
+
+    assert!(key.starts_with(&format!("aw_{}_", created["prefix"].as_str().unwrap())));
+
+    // Act
+    let response = app.payload_for_post_with_api_key(article_payload(), "api/v1/articles", key).await;
+
+    // Assert
+    assert_eq!(201, response.status().as_u16());
+
+    let response = app.payload_for_get_with_token("api/v1/users/api-keys", &token).await;
+    let body = response.text().await.unwrap();
+    let keys: serde_json::Value = serde_json::from_str(&body).unwrap();
+
+    assert!(!body.contains(key));
+    assert_eq!(created["prefix"], keys["api_keys"][0]["prefix"]);
+    assert_eq!("articles:write", keys["api_keys"][0]["scopes"][0]);
+    assert!(keys["api_keys"][0]["last_used_at"].is_string());
+}
+
+#[actix_web::test]
+async fn api_key_is_limited_to_its_scopes() {
+    // Arrange
+    let app = start_test_server().await;
+
+    let token = app.register_and_login("test_devactivity", "test@devactivity.com").await;
+    let created = app.create_api_key(&token, &["articles:read"]).await;
+    let key = created["key"].as_str().unwrap();
+
+    // Act
+    let create_article = app.payload_for_post_with_api_key(article_payload(), "api/v1/articles", key).await;
+    let feed = app.payload_for_get_with_api_key("api/v1/articles/feed", key).await;
+    let list_keys = app.payload_for_get_with_api_key("api/v1/users/api-keys", key).await;
+
+    // Assert
+    assert_eq!(403, create_article.status().as_u16());
+    assert_eq!(200, feed.status().as_u16());
+    assert_eq!(403, list_keys.status().as_u16());
+}
+
+#[actix_web::test]
+async fn revoked_api_key_returns_a_401() {
+    // Arrange
+    let app = start_test_server().await;
+
+    let token = app.register_and_login("test_devactivity", "test@devactivity.com").await;
+    let created = app.create_api_key(&token, &["articles:write"]).await;
+    let key = created["key"].as_str().unwrap();
+
+    let endpoint = format!("api/v1/users/api-keys/{}", created["id"].as_str().unwrap());
+    let response = app.payload_for_delete_with_token("".to_string(), &endpoint, &token).await;
+    assert_eq!(200, response.status().as_u16());
+
+    // Act
+    let response = app.payload_for_post_with_api_key(article_payload(), "api/v1/articles", key).await;
+
+    // Assert
+    assert_eq!(401, response.status().as_u16());
+}
+
+#[actix_web::test]
+async fn password_reset_revokes_the_api_keys() {
+    // Arrange
+    let app = start_test_server().await;
+
+    let token = app.register_and_login("test_devactivity", "test@devactivity.com").await;
+    let created = app.create_api_key(&token, &["articles:read"]).await;
+    let key = created["key"].as_str().unwrap();
+
+    let payload = serde_json::json!({ "user": { "email": "test@devactivity.com" } });
+    let response = app.payload_for_post(payload.to_string(), "api/v1/users/password/forgot").await;
+    assert_eq!(200, response.status().as_u16());
+
+    let payload = serde_json::json!({
+        "user": {
+            "token": app.token_from_last_email_to("test@devactivity.com"),
+            "password": "new-password"
+        }
+    });
+
+    // Act
+    let response = app.payload_for_post(payload.to_string(), "api/v1/users/password/reset").await;
+
+    // Assert
+    assert_eq!(200, response.status().as_u16());
+
+    let response = app.payload_for_get_with_api_key("api/v1/articles/feed", key).await;
+    assert_eq!(401, response.status().as_u16());
+}
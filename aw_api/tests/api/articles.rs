@@ -1,4 +1,11 @@
-use crate::test_utils::start_test_server;
+use aw_api::routes::get_article_list_response;
+use aw_api::schemas::Article;
+use aw_api::publishing::publish_due_articles;
+use aw_api::trending::refresh_trending;
+use aw_api::markdown::render_missing_html;
+use uuid::Uuid;
+
+use crate::test_utils::{count_queries, start_test_server, TestApp};
 
 #[actix_web::test]
 async fn get_article_list_returns_a_200_if_it_success() {
@@ -17,23 +24,71 @@ async fn create_article_returns_a_201_for_valid_payload_data() {
     // Arrange
     let app = start_test_server().await;
 
+    let token = app.register_and_login("test_devactivity", "test@devactivity.com").await;
+
     // Create a JSON payload as a serde_json::Value
     let payload = serde_json::json!({
-        "user": {
-            "username": "test_devactivity",
-            "email": "test@devactivity.com",
-            "password": "12345678"
-        }
+        "body": "this is body article",
+        "description": "the most interesting topic",
+        "tagList": [
+          "interest"
+        ],
+        "title": "the-interesting-topic"
     });
 
     // Serialize the JSON payload into a string
     let body_data = serde_json::to_string(&payload).unwrap();
 
     // Act
-    let response = app.payload_for_post(body_data.into(), "api/v1/users/register").await;
+    let response = app.payload_for_post_with_token(body_data, "api/v1/articles", &token).await;
 
     // Assert
     assert_eq!(201, response.status().as_u16());
+}
+
+#[actix_web::test]
+async fn create_article_returns_a_400_for_invalid_payload_data() {
+    // Arrange
+    let app = start_test_server().await;
+
+    let token = app.register_and_login("test_devactivity", "test@devactivity.com").await;
+
+    // Create a JSON payload as a serde_json::Value
+    let payload = serde_json::json!({
+        "body": "",
+        "description": "",
+        "tagList": [""],
+        "title": ""
+    });
+
+    // Serialize the JSON payload into a string
+    let body_data = serde_json::to_string(&payload).unwrap();
+
+    // Act
+    let response = app.payload_for_post_with_token(body_data, "api/v1/articles", &token).await;
+
+    // Assert
+    assert_eq!(400, response.status().as_u16());
+}
+
+#[actix_web::test]
+async fn get_article_feed_returns_a_200_for_invalid_payload_data() {
+    // Arrange
+    let app = start_test_server().await;
+
+    let token = app.register_and_login("test_devactivity", "test@devactivity.com").await;
+
+    // Act
+    let response = app.payload_for_get_with_token("api/v1/articles/feed?limit=20&offset=0", &token).await;
+
+    // Assert
+    assert_eq!(200, response.status().as_u16());
+}
+
+#[actix_web::test]
+async fn create_article_returns_a_401_without_token() {
+    // Arrange
+    let app = start_test_server().await;
 
     // Create a JSON payload as a serde_json::Value
     let payload = serde_json::json!({
@@ -49,18 +104,24 @@ async fn create_article_returns_a_201_for_valid_payload_data() {
     let body_data = serde_json::to_string(&payload).unwrap();
 
     // Act
-    let response = app.payload_for_post(body_data.into(), format!("api/v1/articles/{}", "test_devactivity").as_str()).await;
+    let response = app.payload_for_post(body_data, "api/v1/articles").await;
 
     // Assert
-    assert_eq!(201, response.status().as_u16());
+    assert_eq!(401, response.status().as_u16());
+
+    // Act
+    let response = app.payload_for_post_with_token(serde_json::to_string(&payload).unwrap(), "api/v1/articles", "not-a-token").await;
+
+    // Assert
+    assert_eq!(401, response.status().as_u16());
 }
 
+// NOTES: the rest is yours
 #[actix_web::test]
-async fn create_article_returns_a_400_for_invalid_payload_data() {
+async fn create_article_returns_a_403_for_an_unverified_email() {
     // Arrange
     let app = start_test_server().await;
 
-    // Create a JSON payload as a serde_json::Value
     let payload = serde_json::json!({
         "user": {
             "username": "test_devactivity",
@@ -69,61 +130,832 @@ async fn create_article_returns_a_400_for_invalid_payload_data() {
         }
     });
 
-    // Serialize the JSON payload into a string
-    let body_data = serde_json::to_string(&payload).unwrap();
+    let response = app.payload_for_post(payload.to_string(), "api/v1/users/register").await;
+    assert_eq!(201, response.status().as_u16());
+
+    let token = app.login("test@devactivity.com", "12345678").await["token"].as_str().unwrap().to_string();
+
+    let payload = serde_json::json!({
+        "body": "this is body article",
+        "description": "the most interesting topic",
+        "tagList": [
+          "interest"
+        ],
+        "title": "the-interesting-topic"
+    });
+
+    let mut draft = payload.clone();
+    draft["status"] = serde_json::json!("draft");
 
     // Act
-    let response = app.payload_for_post(body_data.into(), "api/v1/users/register").await;
+    let response = app.payload_for_post_with_token(payload.to_string(), "api/v1/articles", &token).await;
+    let draft_response = app.payload_for_post_with_token(draft.to_string(), "api/v1/articles", &token).await;
 
     // Assert
+    assert_eq!(403, response.status().as_u16());
+    assert_eq!(201, draft_response.status().as_u16());
+}
+
+fn article_payload(title: &str, tags: &[&str]) -> serde_json::Value {
+    serde_json::json!({
+        "body": "this is body article",
+        "description": "the most interesting topic",
+        "tagList": tags,
+        "title": title
+    })
+}
+
+fn draft_payload(title: &str, published_at: Option<&str>) -> serde_json::Value {
+    let mut payload = article_payload(title, &["drafts"]);
+    payload["status"] = serde_json::json!("draft");
+    payload["publishedAt"] = serde_json::json!(published_at);
+    payload
+}
+
+/// Create an article from `payload` and return its slug
+async fn post_article(app: &TestApp, token: &str, payload: serde_json::Value) -> String {
+    let response = app.payload_for_post_with_token(payload.to_string(), "api/v1/articles", token).await;
     assert_eq!(201, response.status().as_u16());
 
-    // Create a JSON payload as a serde_json::Value
-    let payload = serde_json::json!({
-        "body": "",
-        "description": "",
-        "tagList": [""],
-        "title": ""
-    });
+    let body: serde_json::Value = serde_json::from_str(&response.text().await.unwrap()).unwrap();
+    body["article"]["slug"].as_str().unwrap().to_string()
+}
 
-    // Serialize the JSON payload into a string
-    let body_data = serde_json::to_string(&payload).unwrap();
+async fn article_page(app: &TestApp, endpoint: &str) -> serde_json::Value {
+    let response = app.payload_for_get(endpoint).await;
+    assert_eq!(200, response.status().as_u16());
+
+    serde_json::from_str(&response.text().await.unwrap()).unwrap()
+}
+
+fn page_titles(page: &serde_json::Value) -> Vec<&str> {
+    page["articles"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|article| article["title"].as_str().unwrap())
+        .collect()
+}
+
+#[actix_web::test]
+async fn get_article_list_combines_filters() {
+    // Arrange
+    let app = start_test_server().await;
+
+    let alice = app.register_and_login("test_alice", "alice@devactivity.com").await;
+    let bob = app.register_and_login("test_bob", "bob@devactivity.com").await;
+    let carol = app.register_and_login("test_carol", "carol@devactivity.com").await;
+
+    let rust_web = post_article(&app, &alice, article_payload("rust web", &["rust", "web"])).await;
+    post_article(&app, &alice, article_payload("rust only", &["rust"])).await;
+    post_article(&app, &bob, article_payload("bob web", &["rust", "web"])).await;
+    post_article(&app, &carol, article_payload("carol web", &["web"])).await;
+
+    app.payload_for_post_with_token(String::new(), &format!("api/v1/articles/favorite/{}", rust_web), &bob).await;
+
+    // Act & Assert
+    assert_eq!(vec!["bob web", "rust web"], page_titles(&article_page(&app, "api/v1/articles?tag=rust,web").await));
+    assert_eq!(vec!["rust web"], page_titles(&article_page(&app, "api/v1/articles?tag=rust,web&author=test_alice").await));
+    assert_eq!(
+        vec!["carol web", "bob web", "rust web"],
+        page_titles(&article_page(&app, "api/v1/articles?tag=web&author=test_alice,test_bob,test_carol").await)
+    );
+    assert_eq!(vec!["rust web"], page_titles(&article_page(&app, "api/v1/articles?favorited=test_bob&author=test_alice&tag=web").await));
+    assert!(page_titles(&article_page(&app, "api/v1/articles?favorited=test_bob&author=test_bob").await).is_empty());
+}
+
+#[actix_web::test]
+async fn get_article_list_binds_filter_values() {
+    // Arrange
+    let app = start_test_server().await;
+
+    let token = app.register_and_login("test_devactivity", "test@devactivity.com").await;
+    post_article(&app, &token, article_payload("the-interesting-topic", &["interest"])).await;
+
+    // Act
+    let page = article_page(&app, "api/v1/articles?author=x%27%20OR%20%271%27%3D%271").await;
+
+    // Assert
+    assert!(page_titles(&page).is_empty());
+}
+
+#[actix_web::test]
+async fn search_articles_ranks_title_matches_first_and_highlights_snippets() {
+    // Arrange
+    let app = start_test_server().await;
+
+    let token = app.register_and_login("test_devactivity", "test@devactivity.com").await;
+    post_article(&app, &token, serde_json::json!({
+        "title": "gardening notes",
+        "description": "what grows in spring",
+        "body": "a short aside about <b>compilers</b> near the end",
+        "tagList": ["notes"]
+    })).await;
+    post_article(&app, &token, serde_json::json!({
+        "title": "writing compilers",
+        "description": "parsing and code generation",
+        "body": "lexers, parsers and a small virtual machine",
+        "tagList": ["notes"]
+    })).await;
+    post_article(&app, &token, serde_json::json!({
+        "title": "cooking pasta",
+        "description": "dinner for two",
+        "body": "boil the water first",
+        "tagList": ["notes"]
+    })).await;
+
+    // Act
+    let response = app.payload_for_get("api/v1/articles/search?q=compiler").await;
+
+    // Assert
+    assert_eq!(200, response.status().as_u16());
+
+    let body: serde_json::Value = serde_json::from_str(&response.text().await.unwrap()).unwrap();
+    let articles = body["articles"].as_array().unwrap();
+    assert_eq!(2, articles.len());
+    assert_eq!("writing compilers", articles[0]["title"]);
+    assert_eq!("gardening notes", articles[1]["title"]);
+
+    let snippet = articles[1]["snippet"].as_str().unwrap();
+    assert!(snippet.contains("<mark>compilers</mark>"));
+    assert!(snippet.contains("&lt;b&gt;"));
+}
+
+#[actix_web::test]
+async fn search_articles_pages_with_the_total_and_link_headers() {
+    // Arrange
+    let app = start_test_server().await;
+
+    let token = app.register_and_login("test_devactivity", "test@devactivity.com").await;
+    for title in ["compilers one", "compilers two", "compilers three"] {
+        post_article(&app, &token, serde_json::json!({
+            "title": title,
+            "description": "about compilers",
+            "body": "parsers and code generation",
+            "tagList": ["notes"]
+        })).await;
+    }
+
+    // Act
+    let first_page = app.payload_for_get("api/v1/articles/search?q=compiler&limit=2").await;
+    let second_page = app.payload_for_get("api/v1/articles/search?q=compiler&limit=2&offset=2").await;
+
+    // Assert
+    assert_eq!("3", first_page.headers()["X-Total-Count"]);
+    let link = first_page.headers()["Link"].to_str().unwrap().to_string();
+    assert!(link.contains("?q=compiler&limit=2&offset=2>; rel=\"next\""), "{}", link);
+
+    let body: serde_json::Value = serde_json::from_str(&first_page.text().await.unwrap()).unwrap();
+    assert_eq!(2, body["articles"].as_array().unwrap().len());
+    assert_eq!(3, body["articles_count"]);
+
+    assert_eq!(200, second_page.status().as_u16());
+    assert_eq!("3", second_page.headers()["X-Total-Count"]);
+    let link = second_page.headers()["Link"].to_str().unwrap().to_string();
+    assert!(link.contains("?q=compiler&limit=2&offset=0>; rel=\"prev\""), "{}", link);
+    assert!(!link.contains("rel=\"next\""), "{}", link);
+
+    let body: serde_json::Value = serde_json::from_str(&second_page.text().await.unwrap()).unwrap();
+    assert_eq!(1, body["articles"].as_array().unwrap().len());
+    assert_eq!(3, body["articles_count"]);
+    assert!(body["articles"][0]["snippet"].as_str().unwrap().contains("<mark>compilers</mark>"));
+}
+
+#[actix_web::test]
+async fn search_articles_stems_words_in_the_article_language() {
+    // Arrange
+    let app = start_test_server().await;
+
+    let token = app.register_and_login("test_devactivity", "test@devactivity.com").await;
+    post_article(&app, &token, serde_json::json!({
+        "title": "resep makanan tradisional",
+        "description": "masakan rumahan",
+        "body": "cara memasak nasi goreng",
+        "tagList": ["notes"],
+        "language": "indonesian"
+    })).await;
+
+    // Act
+    let indonesian = app.payload_for_get("api/v1/articles/search?q=makan&lang=indonesian").await;
+    let english = app.payload_for_get("api/v1/articles/search?q=makan").await;
+    let unsupported = app.payload_for_get("api/v1/articles/search?q=makan&lang=klingon").await;
+
+    // Assert
+    assert_eq!(200, indonesian.status().as_u16());
+    let body: serde_json::Value = serde_json::from_str(&indonesian.text().await.unwrap()).unwrap();
+    assert_eq!(1, body["articles_count"]);
+    assert_eq!("resep makanan tradisional", body["articles"][0]["title"]);
+
+    let body: serde_json::Value = serde_json::from_str(&english.text().await.unwrap()).unwrap();
+    assert_eq!(0, body["articles_count"]);
+
+    assert_eq!(400, unsupported.status().as_u16());
+}
+
+#[actix_web::test]
+async fn get_article_list_pages_by_cursor_without_repeating_new_articles() {
+    // Arrange
+    let app = start_test_server().await;
+
+    let token = app.register_and_login("test_devactivity", "test@devactivity.com").await;
+    for title in ["first", "second", "third", "fourth", "fifth"] {
+        post_article(&app, &token, article_payload(title, &["paging"])).await;
+    }
+
+    // Act
+    let first_page = article_page(&app, "api/v1/articles?limit=2").await;
+    post_article(&app, &token, article_payload("sixth", &["paging"])).await;
+
+    let next_cursor = first_page["next_cursor"].as_str().unwrap();
+    let second_page = article_page(&app, &format!("api/v1/articles?limit=2&cursor={}", next_cursor)).await;
+    let next_cursor = second_page["next_cursor"].as_str().unwrap();
+    let last_page = article_page(&app, &format!("api/v1/articles?limit=2&cursor={}", next_cursor)).await;
+    let prev_cursor = second_page["prev_cursor"].as_str().unwrap();
+    let back_page = article_page(&app, &format!("api/v1/articles?limit=2&cursor={}", prev_cursor)).await;
+
+    // Assert
+    assert_eq!(vec!["fifth", "fourth"], page_titles(&first_page));
+    assert!(first_page["prev_cursor"].is_null());
+    assert_eq!(vec!["third", "second"], page_titles(&second_page));
+    assert_eq!(vec!["first"], page_titles(&last_page));
+    assert!(last_page["next_cursor"].is_null());
+    assert_eq!(vec!["fifth", "fourth"], page_titles(&back_page));
+    assert!(back_page["prev_cursor"].is_string());
+}
+
+#[actix_web::test]
+async fn get_article_list_rejects_an_invalid_cursor() {
+    // Arrange
+    let app = start_test_server().await;
 
     // Act
-    let response = app.payload_for_post(body_data.into(), format!("api/v1/articles/{}", "test_devactivity").as_str()).await;
+    let response = app.payload_for_get("api/v1/articles?cursor=bm90LWEtY3Vyc29y").await;
 
     // Assert
     assert_eq!(400, response.status().as_u16());
 }
 
 #[actix_web::test]
-async fn get_article_feed_returns_a_200_for_invalid_payload_data() {
+async fn get_article_list_response_loads_a_page_in_one_query() {
     // Arrange
     let app = start_test_server().await;
 
-    // Create a JSON payload as a serde_json::Value
+    let alice = app.register_and_login("test_alice", "alice@devactivity.com").await;
+    let bob = app.register_and_login("test_bob", "bob@devactivity.com").await;
+
+    let mut slugs = Vec::new();
+    for index in 0..10 {
+        slugs.push(post_article(&app, &alice, article_payload(&format!("article {}", index), &["batch", "loading"])).await);
+    }
+    app.payload_for_post_with_token(String::new(), &format!("api/v1/articles/favorite/{}", slugs[3]), &bob).await;
+    app.payload_for_post_with_token(String::new(), "api/v1/profiles/test_alice/follow", &bob).await;
+
+    let bob_id: Uuid = sqlx::query_scalar("SELECT id FROM users WHERE username = 'test_bob'")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    let articles: Vec<Article> = sqlx::query_as("SELECT * FROM articles ORDER BY created_at DESC, id DESC")
+        .fetch_all(&app.db_pool)
+        .await
+        .unwrap();
+
+    // Act
+    let (response, query_count) = count_queries(get_article_list_response(articles, Some(bob_id), &app.db_pool)).await;
+
+    // Assert
+    let response = response.unwrap();
+    assert_eq!(1, query_count);
+    assert_eq!(10, response.articles_count);
+    assert_eq!("article 9", response.articles[0].title);
+
+    let favorited = response.articles.iter().find(|article| article.slug == slugs[3]).unwrap();
+    assert!(favorited.favorited);
+    assert_eq!(1, favorited.favorites_count);
+    assert!(favorited.author.following);
+    assert_eq!("test_alice", favorited.author.username);
+
+    let mut tags = favorited.tag_list.clone();
+    tags.sort();
+    assert_eq!(vec!["batch", "loading"], tags);
+}
+
+#[actix_web::test]
+async fn get_article_list_returns_the_total_count_and_page_links() {
+    // Arrange
+    let app = start_test_server().await;
+
+    let token = app.register_and_login("test_devactivity", "test@devactivity.com").await;
+    for index in 0..5 {
+        post_article(&app, &token, article_payload(&format!("article {}", index), &["counted"])).await;
+    }
+    post_article(&app, &token, article_payload("not counted", &["other"])).await;
+
+    // Act
+    let response = app.payload_for_get("api/v1/articles?tag=counted&limit=2&offset=2").await;
+
+    // Assert
+    assert_eq!(200, response.status().as_u16());
+    assert_eq!("5", response.headers()["X-Total-Count"].to_str().unwrap());
+
+    let link = response.headers()["Link"].to_str().unwrap().to_string();
+    let base = format!("<http://127.0.0.1:{}/api/v1/articles?tag=counted", app.port);
+    assert!(link.contains(&format!("{}&limit=2&offset=0>; rel=\"first\"", base)));
+    assert!(link.contains(&format!("{}&limit=2&offset=0>; rel=\"prev\"", base)));
+    assert!(link.contains(&format!("{}&limit=2&offset=4>; rel=\"next\"", base)));
+    assert!(link.contains(&format!("{}&limit=2&offset=4>; rel=\"last\"", base)));
+
+    let body: serde_json::Value = serde_json::from_str(&response.text().await.unwrap()).unwrap();
+    assert_eq!(5, body["articles_count"]);
+    assert_eq!(2, body["articles"].as_array().unwrap().len());
+}
+
+#[actix_web::test]
+async fn article_lists_return_an_empty_page_for_an_offset_past_i64() {
+    // Arrange
+    let app = start_test_server().await;
+
+    let token = app.register_and_login("test_devactivity", "test@devactivity.com").await;
+    post_article(&app, &token, article_payload("the-interesting-topic", &["interest"])).await;
+
+    let offset = u64::MAX;
+
+    // Act
+    let listed = article_page(&app, &format!("api/v1/articles?offset={}", offset)).await;
+    let searched = article_page(&app, &format!("api/v1/articles/search?q=interesting&offset={}", offset)).await;
+    let feed = app.payload_for_get_with_token(&format!("api/v1/articles/feed?offset={}", offset), &token).await;
+
+    // Assert
+    assert!(page_titles(&listed).is_empty());
+    assert_eq!(1, listed["articles_count"]);
+    assert!(page_titles(&searched).is_empty());
+    assert_eq!(1, searched["articles_count"]);
+    assert_eq!(200, feed.status().as_u16());
+}
+
+#[actix_web::test]
+async fn get_article_list_sorts_by_the_requested_order() {
+    // Arrange
+    let app = start_test_server().await;
+
+    let alice = app.register_and_login("test_alice", "alice@devactivity.com").await;
+    let bob = app.register_and_login("test_bob", "bob@devactivity.com").await;
+
+    let first = post_article(&app, &alice, article_payload("first", &["sorting"])).await;
+    let second = post_article(&app, &alice, article_payload("second", &["sorting"])).await;
+    post_article(&app, &alice, article_payload("third", &["sorting"])).await;
+
+    for token in [&alice, &bob] {
+        app.payload_for_post_with_token(String::new(), &format!("api/v1/articles/favorite/{}", first), token).await;
+    }
+    app.payload_for_post_with_token(String::new(), &format!("api/v1/articles/favorite/{}", second), &bob).await;
+    for _ in 0..3 {
+        let comment = serde_json::json!({ "body": "nice one" });
+        app.payload_for_post_with_token(comment.to_string(), &format!("api/v1/articles/comments/{}", second), &bob).await;
+    }
+
+    refresh_trending(&app.db_pool, &app.settings.trending).await.unwrap();
+
+    // Act & Assert
+    assert_eq!(vec!["third", "second", "first"], page_titles(&article_page(&app, "api/v1/articles").await));
+    assert_eq!(vec!["first", "second", "third"], page_titles(&article_page(&app, "api/v1/articles?sort=oldest").await));
+    assert_eq!(vec!["first", "second", "third"], page_titles(&article_page(&app, "api/v1/articles?sort=most_favorited").await));
+    assert_eq!(vec!["second", "third", "first"], page_titles(&article_page(&app, "api/v1/articles?sort=most_commented").await));
+    assert_eq!(vec!["second", "first", "third"], page_titles(&article_page(&app, "api/v1/articles?sort=trending").await));
+
+    let oldest = article_page(&app, "api/v1/articles?sort=oldest&limit=1").await;
+    let next_cursor = oldest["next_cursor"].as_str().unwrap();
+    assert_eq!(
+        vec!["second"],
+        page_titles(&article_page(&app, &format!("api/v1/articles?sort=oldest&limit=1&cursor={}", next_cursor)).await)
+    );
+
+    let by_count = app.payload_for_get(&format!("api/v1/articles?sort=most_favorited&cursor={}", next_cursor)).await;
+    assert_eq!(400, by_count.status().as_u16());
+    assert_eq!(400, app.payload_for_get("api/v1/articles?sort=random").await.status().as_u16());
+}
+
+#[actix_web::test]
+async fn get_trending_articles_ranks_recent_activity() {
+    // Arrange
+    let app = start_test_server().await;
+
+    let alice = app.register_and_login("test_alice", "alice@devactivity.com").await;
+    let bob = app.register_and_login("test_bob", "bob@devactivity.com").await;
+
+    let favorited = post_article(&app, &alice, article_payload("favorited", &["trending"])).await;
+    let commented = post_article(&app, &alice, article_payload("commented", &["trending"])).await;
+    let viewed = post_article(&app, &alice, article_payload("viewed", &["trending"])).await;
+    post_article(&app, &alice, article_payload("quiet", &["trending"])).await;
+
+    app.payload_for_post_with_token(String::new(), &format!("api/v1/articles/favorite/{}", favorited), &bob).await;
+    for _ in 0..2 {
+        let comment = serde_json::json!({ "body": "nice one" });
+        app.payload_for_post_with_token(comment.to_string(), &format!("api/v1/articles/comments/{}", commented), &bob).await;
+    }
+    for _ in 0..3 {
+        app.payload_for_get(&format!("api/v1/articles/data/{}", viewed)).await;
+    }
+
+    // Act
+    refresh_trending(&app.db_pool, &app.settings.trending).await.unwrap();
+    let ranked = article_page(&app, "api/v1/articles/trending").await;
+    let sorted = article_page(&app, "api/v1/articles?sort=trending").await;
+
+    sqlx::query("UPDATE comments SET created_at = CURRENT_TIMESTAMP - INTERVAL '30 days'")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+    refresh_trending(&app.db_pool, &app.settings.trending).await.unwrap();
+    let outside_window = article_page(&app, "api/v1/articles/trending").await;
+
+    // Assert
+    assert_eq!(vec!["commented", "favorited", "viewed"], page_titles(&ranked));
+    assert_eq!(3, ranked["articles_count"]);
+    assert_eq!(vec!["commented", "favorited", "viewed", "quiet"], page_titles(&sorted));
+    assert_eq!(vec!["favorited", "viewed"], page_titles(&outside_window));
+}
+
+#[actix_web::test]
+async fn drafts_are_only_shown_to_their_author_until_published() {
+    // Arrange
+    let app = start_test_server().await;
+
+    let alice = app.register_and_login("test_alice", "alice@devactivity.com").await;
+    let bob = app.register_and_login("test_bob", "bob@devactivity.com").await;
+    app.payload_for_post_with_token(String::new(), "api/v1/profiles/test_alice/follow", &bob).await;
+
+    post_article(&app, &alice, article_payload("published", &["drafts"])).await;
+    let draft = post_article(&app, &alice, draft_payload("draft", None)).await;
+
+    // Act
+    let listed = article_page(&app, "api/v1/articles").await;
+    let feed = app.payload_for_get_with_token("api/v1/articles/feed", &bob).await;
+    let drafts = app.payload_for_get_with_token("api/v1/articles/drafts", &alice).await;
+    let read_by_author = app.payload_for_get_with_token(&format!("api/v1/articles/data/{}", draft), &alice).await;
+    let read_by_other = app.payload_for_get_with_token(&format!("api/v1/articles/data/{}", draft), &bob).await;
+
+    let publish = serde_json::json!({ "slug": draft, "article": { "status": "published" } });
+    let published = app.payload_for_put_with_token(publish.to_string(), &format!("api/v1/articles/data/{}", draft), &alice).await;
+
+    // Assert
+    assert_eq!(vec!["published"], page_titles(&listed));
+
+    let feed: serde_json::Value = serde_json::from_str(&feed.text().await.unwrap()).unwrap();
+    assert_eq!(vec!["published"], page_titles(&feed));
+
+    let drafts: serde_json::Value = serde_json::from_str(&drafts.text().await.unwrap()).unwrap();
+    assert_eq!(vec!["draft"], page_titles(&drafts));
+
+    assert_eq!(200, read_by_author.status().as_u16());
+    assert_eq!(404, read_by_other.status().as_u16());
+
+    assert_eq!(200, published.status().as_u16());
+    let published: serde_json::Value = serde_json::from_str(&published.text().await.unwrap()).unwrap();
+    assert_eq!("published", published["article"]["status"]);
+    assert!(published["article"]["published_at"].is_string());
+    assert_eq!(vec!["draft", "published"], page_titles(&article_page(&app, "api/v1/articles").await));
+}
+
+#[actix_web::test]
+async fn drafts_are_hidden_from_comments_favorites_tags_and_views() {
+    // Arrange
+    let app = start_test_server().await;
+
+    let alice = app.register_and_login("test_alice", "alice@devactivity.com").await;
+    let bob = app.register_and_login("test_bob", "bob@devactivity.com").await;
+
+    post_article(&app, &alice, article_payload("published", &["rust"])).await;
+    let draft = post_article(&app, &alice, draft_payload("draft", None)).await;
+    let comment = serde_json::json!({ "body": "a comment" }).to_string();
+
+    // Act
+    let comments_by_other = app.payload_for_get_with_token(&format!("api/v1/articles/comments/{}", draft), &bob).await;
+    let comment_by_other = app.payload_for_post_with_token(comment.clone(), &format!("api/v1/articles/comments/{}", draft), &bob).await;
+    let comment_by_author = app.payload_for_post_with_token(comment, &format!("api/v1/articles/comments/{}", draft), &alice).await;
+    let comments_by_author = app.payload_for_get_with_token(&format!("api/v1/articles/comments/{}", draft), &alice).await;
+    let favorite_by_other = app.payload_for_post_with_token(String::new(), &format!("api/v1/articles/favorite/{}", draft), &bob).await;
+    let unfavorite_by_other = app.payload_for_delete_with_token(String::new(), &format!("api/v1/articles/favorite/{}", draft), &bob).await;
+    let tags = app.payload_for_get("api/v1/tags").await;
+    app.payload_for_get_with_token(&format!("api/v1/articles/data/{}", draft), &alice).await;
+
+    // Assert
+    assert_eq!(404, comments_by_other.status().as_u16());
+    assert_eq!(404, comment_by_other.status().as_u16());
+    assert_eq!(201, comment_by_author.status().as_u16());
+    let comments_by_author: serde_json::Value = serde_json::from_str(&comments_by_author.text().await.unwrap()).unwrap();
+    assert_eq!(1, comments_by_author["comments"].as_array().unwrap().len());
+
+    assert_eq!(404, favorite_by_other.status().as_u16());
+    assert_eq!(404, unfavorite_by_other.status().as_u16());
+    let favorites: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM favorite_articles").fetch_one(&app.db_pool).await.unwrap();
+    assert_eq!(0, favorites);
+
+    assert_eq!("1", tags.headers()["X-Total-Count"]);
+    let tags: serde_json::Value = serde_json::from_str(&tags.text().await.unwrap()).unwrap();
+    assert_eq!(serde_json::json!(["rust"]), tags["tags"]);
+
+    let views: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM article_daily_views").fetch_one(&app.db_pool).await.unwrap();
+    assert_eq!(0, views);
+}
+
+#[actix_web::test]
+async fn editing_someone_elses_draft_returns_a_404() {
+    // Arrange
+    let app = start_test_server().await;
+
+    let alice = app.register_and_login("test_alice", "alice@devactivity.com").await;
+    let bob = app.register_and_login("test_bob", "bob@devactivity.com").await;
+
+    let published = post_article(&app, &alice, article_payload("published", &["drafts"])).await;
+    let draft = post_article(&app, &alice, draft_payload("draft", None)).await;
+    let edit = |slug: &str| serde_json::json!({ "slug": slug, "article": { "body": "edited by bob" } }).to_string();
+
+    // Act
+    let draft_edited = app.payload_for_put_with_token(edit(&draft), &format!("api/v1/articles/data/{}", draft), &bob).await;
+    let draft_restored = app.payload_for_post_with_token(String::new(), &format!("api/v1/articles/data/{}/revisions/1/restore", draft), &bob).await;
+    let published_edited = app.payload_for_put_with_token(edit(&published), &format!("api/v1/articles/data/{}", published), &bob).await;
+    let published_restored = app.payload_for_post_with_token(String::new(), &format!("api/v1/articles/data/{}/revisions/1/restore", published), &bob).await;
+
+    // Assert
+    assert_eq!(404, draft_edited.status().as_u16());
+    assert_eq!(404, draft_restored.status().as_u16());
+    assert_eq!(403, published_edited.status().as_u16());
+    assert_eq!(403, published_restored.status().as_u16());
+}
+
+#[actix_web::test]
+async fn scheduled_drafts_are_published_once_their_time_has_passed() {
+    // Arrange
+    let app = start_test_server().await;
+
+    let token = app.register_and_login("test_devactivity", "test@devactivity.com").await;
+    let due = (chrono::Utc::now() - chrono::Duration::minutes(5)).to_rfc3339();
+    let later = (chrono::Utc::now() + chrono::Duration::days(1)).to_rfc3339();
+
+    post_article(&app, &token, draft_payload("due", Some(&due))).await;
+    post_article(&app, &token, draft_payload("later", Some(&later))).await;
+
+    // Act
+    let published_count = publish_due_articles(&app.db_pool).await.unwrap();
+
+    // Assert
+    assert_eq!(1, published_count);
+    assert_eq!(vec!["due"], page_titles(&article_page(&app, "api/v1/articles").await));
+
+    let drafts = app.payload_for_get_with_token("api/v1/articles/drafts", &token).await;
+    let drafts: serde_json::Value = serde_json::from_str(&drafts.text().await.unwrap()).unwrap();
+    assert_eq!(vec!["later"], page_titles(&drafts));
+}
+
+#[actix_web::test]
+async fn unverified_authors_cannot_publish_their_drafts() {
+    // Arrange
+    let app = start_test_server().await;
+
+    let token = app.register_and_login("test_devactivity", "test@devactivity.com").await;
+    let due = (chrono::Utc::now() - chrono::Duration::minutes(5)).to_rfc3339();
+
+    let draft = post_article(&app, &token, draft_payload("draft", None)).await;
+    post_article(&app, &token, draft_payload("due", Some(&due))).await;
+
+    // As after a change of email address
+    sqlx::query("UPDATE users SET email_verified_at = NULL").execute(&app.db_pool).await.unwrap();
+
+    // Act
+    let publish = serde_json::json!({ "slug": draft, "article": { "status": "published" } });
+    let published = app.payload_for_put_with_token(publish.to_string(), &format!("api/v1/articles/data/{}", draft), &token).await;
+
+    let schedule = serde_json::json!({ "slug": draft, "article": { "publishedAt": due } });
+    let scheduled = app.payload_for_put_with_token(schedule.to_string(), &format!("api/v1/articles/data/{}", draft), &token).await;
+
+    let edit = serde_json::json!({ "slug": draft, "article": { "body": "still a draft" } });
+    let edited = app.payload_for_put_with_token(edit.to_string(), &format!("api/v1/articles/data/{}", draft), &token).await;
+
+    let published_count = publish_due_articles(&app.db_pool).await.unwrap();
+
+    // Assert
+    assert_eq!(403, published.status().as_u16());
+    assert_eq!(403, scheduled.status().as_u16());
+    assert_eq!(200, edited.status().as_u16());
+    assert_eq!(0, published_count);
+    assert!(page_titles(&article_page(&app, "api/v1/articles").await).is_empty());
+}
+
+#[actix_web::test]
+async fn article_revisions_can_be_compared_and_restored() {
+    // Arrange
+    let app = start_test_server().await;
+
+    let alice = app.register_and_login("test_alice", "alice@devactivity.com").await;
+    let bob = app.register_and_login("test_bob", "bob@devactivity.com").await;
+
     let payload = serde_json::json!({
-        "user": {
-            "username": "test_devactivity",
-            "email": "test@devactivity.com",
-            "password": "12345678"
-        }
+        "body": "first line\nsecond line\nthird line",
+        "description": "the most interesting topic",
+        "tagList": ["history"],
+        "title": "versioned"
     });
+    let slug = post_article(&app, &alice, payload).await;
 
-    // Serialize the JSON payload into a string
-    let body_data = serde_json::to_string(&payload).unwrap();
+    for (index, new_body) in ["first line\nsecond line changed\nthird line", "first line\nthird line"].iter().enumerate() {
+        let update = serde_json::json!({ "slug": slug, "article": { "body": new_body } });
+        let response = app.payload_for_put_with_token(update.to_string(), &format!("api/v1/articles/data/{}", slug), &alice).await;
+        assert_eq!(200, response.status().as_u16(), "update {}", index);
+    }
+
+    // A change of status only is not a revision
+    let archive = serde_json::json!({ "slug": slug, "article": { "status": "archived" } });
+    app.payload_for_put_with_token(archive.to_string(), &format!("api/v1/articles/data/{}", slug), &alice).await;
 
     // Act
-    let response = app.payload_for_post(body_data.into(), "api/v1/users/register").await;
+    let revisions = app.payload_for_get_with_token(&format!("api/v1/articles/data/{}/revisions", slug), &alice).await;
+    let unified = app.payload_for_get_with_token(&format!("api/v1/articles/data/{}/revisions/diff?from=1&to=2", slug), &alice).await;
+    let words = app.payload_for_get_with_token(&format!("api/v1/articles/data/{}/revisions/diff?from=1&to=2&mode=word", slug), &alice).await;
+    let restored_by_other = app.payload_for_post_with_token(String::new(), &format!("api/v1/articles/data/{}/revisions/1/restore", slug), &bob).await;
+    let restored = app.payload_for_post_with_token(String::new(), &format!("api/v1/articles/data/{}/revisions/1/restore", slug), &alice).await;
+    let after_restore = app.payload_for_get_with_token(&format!("api/v1/articles/data/{}/revisions", slug), &alice).await;
 
     // Assert
-    assert_eq!(201, response.status().as_u16());
+    let revisions: serde_json::Value = serde_json::from_str(&revisions.text().await.unwrap()).unwrap();
+    assert_eq!(3, revisions["revisions_count"]);
+    assert_eq!(3, revisions["revisions"][0]["revision"]);
+    assert_eq!("test_alice", revisions["revisions"][0]["edited_by"]);
+
+    let unified: serde_json::Value = serde_json::from_str(&unified.text().await.unwrap()).unwrap();
+    assert_eq!(
+        "--- revision 1/body\n+++ revision 2/body\n@@ -1,3 +1,3 @@\n first line\n-second line\n+second line changed\n third line\n",
+        unified["body"]
+    );
+    assert_eq!("", unified["title"]);
+
+    let words: serde_json::Value = serde_json::from_str(&words.text().await.unwrap()).unwrap();
+    assert_eq!("first line\nsecond line{+ changed+}\nthird line", words["body"]);
+
+    // The archived article is hidden from anyone but its author
+    assert_eq!(404, restored_by_other.status().as_u16());
+
+    assert_eq!(200, restored.status().as_u16());
+    let restored: serde_json::Value = serde_json::from_str(&restored.text().await.unwrap()).unwrap();
+    assert_eq!("first line\nsecond line\nthird line", restored["article"]["body"]);
+
+    let after_restore: serde_json::Value = serde_json::from_str(&after_restore.text().await.unwrap()).unwrap();
+    assert_eq!(4, after_restore["revisions"][0]["revision"]);
+    assert_eq!(1, after_restore["revisions"][0]["restored_from"]);
+}
+
+#[actix_web::test]
+async fn article_and_comment_bodies_are_rendered_to_sanitized_html() {
+    // Arrange
+    let app = start_test_server().await;
+
+    let alice = app.register_and_login("test_alice", "alice@devactivity.com").await;
+
+    let markdown = "# Notes\n\n| a | b |\n|---|---|\n| 1 | 2 |\n\n```rust\nfn main() {}\n```\n\n<script>alert(1)</script>\n\n[click](javascript:alert(1)) [docs](https://example.com)";
+    let payload = serde_json::json!({
+        "body": markdown,
+        "description": "markdown",
+        "tagList": ["markdown"],
+        "title": "rendered"
+    });
+    let response = app.payload_for_post_with_token(payload.to_string(), "api/v1/articles", &alice).await;
+    let body: serde_json::Value = serde_json::from_str(&response.text().await.unwrap()).unwrap();
+    let slug = body["article"]["slug"].as_str().unwrap().to_string();
 
     // Act
-    let response = app.payload_for_get(format!("api/v1/articles/feed/{}?limit=20&offset=0", "test_devactivity").as_str()).await;
+    let comment = serde_json::json!({ "body": "**bold** <img src=x onerror=alert(1)>" });
+    let comment = app.payload_for_post_with_token(comment.to_string(), &format!("api/v1/articles/comments/{}", slug), &alice).await;
+
+    let update = serde_json::json!({ "slug": slug, "article": { "body": "~~gone~~" } });
+    let updated = app.payload_for_put_with_token(update.to_string(), &format!("api/v1/articles/data/{}", slug), &alice).await;
 
     // Assert
-    assert_eq!(200, response.status().as_u16());
+    let html = body["article"]["body_html"].as_str().unwrap();
+    assert_eq!(markdown, body["article"]["body"]);
+    assert!(html.contains("<h1>Notes</h1>"), "{}", html);
+    assert!(html.contains("<table>") && html.contains("<td>1</td>"), "{}", html);
+    assert!(html.contains("<code class=\"language-rust\">fn main() {}"), "{}", html);
+    assert!(html.contains("<a href=\"https://example.com\" rel=\"noopener noreferrer nofollow\">docs</a>"), "{}", html);
+    assert!(!html.contains("<script") && !html.contains("javascript:"), "{}", html);
+
+    let comment: serde_json::Value = serde_json::from_str(&comment.text().await.unwrap()).unwrap();
+    assert_eq!("<p><strong>bold</strong> <img src=\"x\"></p>\n", comment["comment"]["bodyHtml"]);
+
+    let updated: serde_json::Value = serde_json::from_str(&updated.text().await.unwrap()).unwrap();
+    assert_eq!("<p><del>gone</del></p>\n", updated["article"]["body_html"]);
+
+    // Rows from before the cache are rendered on read and then filled in
+    sqlx::query("UPDATE articles SET body_html = NULL").execute(&app.db_pool).await.unwrap();
+    let uncached = app.payload_for_get_with_token(&format!("api/v1/articles/data/{}", slug), &alice).await;
+    let uncached: serde_json::Value = serde_json::from_str(&uncached.text().await.unwrap()).unwrap();
+    assert_eq!("<p><del>gone</del></p>\n", uncached["article"]["body_html"]);
+
+    render_missing_html(&app.db_pool).await.unwrap();
+    let cached: Option<String> = sqlx::query_scalar("SELECT body_html FROM articles").fetch_one(&app.db_pool).await.unwrap();
+    assert_eq!(Some("<p><del>gone</del></p>\n".to_string()), cached);
+}
+
+#[actix_web::test]
+async fn old_slugs_redirect_to_the_current_one() {
+    // Arrange
+    let app = start_test_server().await;
+
+    let alice = app.register_and_login("test_alice", "alice@devactivity.com").await;
+
+    let payload = serde_json::json!({
+        "body": "renamed twice",
+        "description": "renamed twice",
+        "tagList": ["slugs"],
+        "title": "first title"
+    });
+    let first_slug = post_article(&app, &alice, payload).await;
+
+    let mut slug = first_slug.clone();
+    for title in ["second title", "third title"] {
+        let update = serde_json::json!({ "slug": slug, "article": { "title": title } });
+        let response = app.payload_for_put_with_token(update.to_string(), &format!("api/v1/articles/data/{}", slug), &alice).await;
+        let body: serde_json::Value = serde_json::from_str(&response.text().await.unwrap()).unwrap();
+        slug = body["article"]["slug"].as_str().unwrap().to_string();
+    }
+
+    let client = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .unwrap();
+
+    // Act
+    let redirect = client
+        .get(format!("{}/api/v1/articles/data/{}?ref=share", &app.address, first_slug))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    let followed = app.payload_for_get(&format!("api/v1/articles/data/{}", first_slug)).await;
+    let unknown = app.payload_for_get("api/v1/articles/data/never-existed").await;
+
+    // Assert
+    assert!(slug.ends_with("third-title"));
+    assert_eq!(301, redirect.status().as_u16());
+    assert_eq!(
+        format!("/api/v1/articles/data/{}?ref=share", slug),
+        redirect.headers()["location"].to_str().unwrap()
+    );
+
+    assert_eq!(200, followed.status().as_u16());
+    let followed: serde_json::Value = serde_json::from_str(&followed.text().await.unwrap()).unwrap();
+    assert_eq!(slug, followed["article"]["slug"]);
+
+    assert_eq!(404, unknown.status().as_u16());
 }
 
-// NOTES: the rest is yours
\ No newline at end of file
+#[actix_web::test]
+async fn authors_can_choose_slugs_and_titles_are_transliterated() {
+    // Arrange
+    let app = start_test_server().await;
+
+    let alice = app.register_and_login("test_alice", "alice@devactivity.com").await;
+    let bob = app.register_and_login("test_bob", "bob@devactivity.com").await;
+
+    let article = |title: &str, slug: Option<&str>| serde_json::json!({
+        "body": "slugs",
+        "description": "slugs",
+        "tagList": ["slugs"],
+        "title": title,
+        "slug": slug,
+    });
+
+    // Act
+    let cyrillic = app.payload_for_post_with_token(article("Привет мир", None).to_string(), "api/v1/articles", &alice).await;
+    let chinese = app.payload_for_post_with_token(article("北京", None).to_string(), "api/v1/articles", &alice).await;
+    let vanity = app.payload_for_post_with_token(article("My launch", Some("launch-day")).to_string(), "api/v1/articles", &alice).await;
+    let taken = app.payload_for_post_with_token(article("Copycat", Some("launch-day")).to_string(), "api/v1/articles", &bob).await;
+    let invalid = app.payload_for_post_with_token(article("Shouting", Some("Not A Slug")).to_string(), "api/v1/articles", &bob).await;
+
+    let retitle = serde_json::json!({ "slug": "launch-day", "article": { "title": "Launch recap" } });
+    let retitled = app.payload_for_put_with_token(retitle.to_string(), "api/v1/articles/data/launch-day", &alice).await;
+
+    let rename = serde_json::json!({ "slug": "launch-day", "article": { "slug": "launch-recap" } });
+    let renamed = app.payload_for_put_with_token(rename.to_string(), "api/v1/articles/data/launch-day", &alice).await;
+    let old_slug = app.payload_for_post_with_token(article("Copycat", Some("launch-day")).to_string(), "api/v1/articles", &bob).await;
+
+    // Assert
+    let cyrillic: serde_json::Value = serde_json::from_str(&cyrillic.text().await.unwrap()).unwrap();
+    assert!(cyrillic["article"]["slug"].as_str().unwrap().ends_with("-privet-mir"), "{}", cyrillic["article"]["slug"]);
+    let chinese: serde_json::Value = serde_json::from_str(&chinese.text().await.unwrap()).unwrap();
+    assert!(chinese["article"]["slug"].as_str().unwrap().ends_with("-bei-jing"), "{}", chinese["article"]["slug"]);
+
+    assert_eq!(201, vanity.status().as_u16());
+    let vanity: serde_json::Value = serde_json::from_str(&vanity.text().await.unwrap()).unwrap();
+    assert_eq!("launch-day", vanity["article"]["slug"]);
+
+    assert_eq!(422, taken.status().as_u16());
+    assert_eq!(400, invalid.status().as_u16());
+
+    let retitled: serde_json::Value = serde_json::from_str(&retitled.text().await.unwrap()).unwrap();
+    assert_eq!("launch-day", retitled["article"]["slug"]);
+    assert_eq!("Launch recap", retitled["article"]["title"]);
+
+    let renamed: serde_json::Value = serde_json::from_str(&renamed.text().await.unwrap()).unwrap();
+    assert_eq!("launch-recap", renamed["article"]["slug"]);
+
+    // The old slug still redirects, so it stays with its article
+    assert_eq!(422, old_slug.status().as_u16());
+}
@@ -0,0 +1,125 @@
+use aw_api::settings::{AuthMode, SessionStoreBackend};
+
+use crate::test_utils::{start_test_server_with, TestApp};
+
+async fn start_cookie_server(store: SessionStoreBackend) -> TestApp {
+    start_test_server_with(|cfg| {
+        cfg.session.mode = AuthMode::Cookie;
+        cfg.session.store = store;
+    })
+    .await
+}
+
+/// Log in and return the `Cookie` header to send back, with the CSRF token
+async fn cookie_login(app: &TestApp) -> (String, String) {
+    let payload = serde_json::json!({
+        "user": {
+            "username": "test_devactivity",
+            "email": "test@devactivity.com",
+            "password": "12345678",
+        }
+    });
+
+    app.payload_for_post(payload.to_string(), "api/v1/users/register").await;
+    app.verify_email("test@devactivity.com").await;
+
+    let payload = serde_json::json!({
+        "user": {
+            "email": "test@devactivity.com",
+            "password": "12345678",
+        }
+    });
+    let response = app.payload_for_post(payload.to_string(), "api/v1/users/login").await;
+    assert_eq!(201, response.status().as_u16());
+
+    let set_cookies: Vec<String> = response
+        .headers()
+        .get_all("set-cookie")
+        .iter()
+        .map(|value| value.to_str().unwrap().to_owned())
+        .collect();
+
+    let session_cookie = set_cookies.iter().find(|cookie| cookie.starts_with("aw_session=")).unwrap();
+    assert!(session_cookie.contains("HttpOnly"));
+
+    let body: serde_json::Value = serde_json::from_str(&response.text().await.unwrap()).unwrap();
+    assert!(body["token"].is_null());
+
+    let cookie_header = set_cookies
+        .iter()
+        .map(|cookie| cookie.split(';').next().unwrap())
+        .collect::<Vec<_>>()
+        .join("; ");
+
+    (cookie_header, body["csrf_token"].as_str().unwrap().to_owned())
+}
+
+async fn post_with_cookies(app: &TestApp, endpoint: &str, body: String, cookies: &str, csrf_token: Option<&str>) -> u16 {
+    let mut request = reqwest::Client::new()
+        .post(format!("{}/{}", &app.address, endpoint))
+        .header("Content-Type", "application/json")
+        .header("Cookie", cookies)
+        .body(body);
+
+    if let Some(csrf_token) = csrf_token {
+        request = request.header("X-CSRF-Token", csrf_token);
+    }
+
+    request.send().await.expect("Failed to execute request.").status().as_u16()
+}
+
+async fn get_with_cookies(app: &TestApp, endpoint: &str, cookies: &str) -> u16 {
+    reqwest::Client::new()
+        .get(format!("{}/{}", &app.address, endpoint))
+        .header("Cookie", cookies)
+        .send()
+        .await
+        .expect("Failed to execute request.")
+        .status()
+        .as_u16()
+}
+
+fn article_payload() -> String {
+    serde_json::json!({
+        "body": "this is body article",
+        "description": "the most interesting topic",
+        "tagList": ["interest"],
+        "title": "the-interesting-topic"
+    })
+    .to_string()
+}
+
+#[actix_web::test]
+async fn cookie_session_requires_the_csrf_token_for_changes() {
+    // Arrange
+    let app = start_cookie_server(SessionStoreBackend::Postgres).await;
+    let (cookies, csrf_token) = cookie_login(&app).await;
+
+    // Act
+    let read = get_with_cookies(&app, "api/v1/users/sessions", &cookies).await;
+    let without_csrf = post_with_cookies(&app, "api/v1/articles", article_payload(), &cookies, None).await;
+    let wrong_csrf = post_with_cookies(&app, "api/v1/articles", article_payload(), &cookies, Some("forged")).await;
+    let with_csrf = post_with_cookies(&app, "api/v1/articles", article_payload(), &cookies, Some(&csrf_token)).await;
+
+    // Assert
+    assert_eq!(200, read);
+    assert_eq!(403, without_csrf);
+    assert_eq!(403, wrong_csrf);
+    assert_eq!(201, with_csrf);
+}
+
+#[actix_web::test]
+async fn cookie_session_logout_ends_the_session() {
+    // Arrange
+    let app = start_cookie_server(SessionStoreBackend::Memory).await;
+    let (cookies, csrf_token) = cookie_login(&app).await;
+
+    assert_eq!(200, get_with_cookies(&app, "api/v1/users/sessions", &cookies).await);
+
+    // Act
+    let logout = post_with_cookies(&app, "api/v1/users/logout", "".to_string(), &cookies, Some(&csrf_token)).await;
+
+    // Assert
+    assert_eq!(200, logout);
+    assert_eq!(401, get_with_cookies(&app, "api/v1/users/sessions", &cookies).await);
+}
@@ -3,4 +3,10 @@ mod test_utils;
 mod users;
 mod tags;
 mod profile;
-mod articles;
\ No newline at end of file
+mod articles;
+mod admin;
+mod two_factor;
+mod passkeys;
+mod api_keys;
+mod cookie_sessions;
+mod settings;
\ No newline at end of file
@@ -0,0 +1,253 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use ciborium::value::Value as CborValue;
+use p256::ecdsa::{signature::Signer, Signature, SigningKey};
+use rand::{rngs::OsRng, RngCore};
+use sha2::{Digest, Sha256};
+
+use crate::test_utils::{start_test_server, TestApp};
+
+/// Plays the part of the browser and a platform authenticator holding one ES256 passkey
+struct SoftwareAuthenticator {
+    key: SigningKey,
+    credential_id: Vec<u8>,
+    sign_count: u32,
+    rp_id: String,
+    origin: String,
+}
+
+impl SoftwareAuthenticator {
+    fn new(app: &TestApp) -> Self {
+        let mut credential_id = vec![0u8; 16];
+        OsRng.fill_bytes(&mut credential_id);
+
+        SoftwareAuthenticator {
+            key: SigningKey::random(&mut OsRng),
+            credential_id,
+            sign_count: 0,
+            rp_id: app.settings.application.webauthn_rp_id.clone(),
+            origin: app.settings.application.webauthn_origin.clone(),
+        }
+    }
+
+    fn credential_id(&self) -> String {
+        URL_SAFE_NO_PAD.encode(&self.credential_id)
+    }
+
+    fn client_data(&self, ceremony_type: &str, options: &serde_json::Value) -> Vec<u8> {
+        serde_json::json!({
+            "type": ceremony_type,
+            "challenge": options["publicKey"]["challenge"],
+            "origin": self.origin,
+            "crossOrigin": false,
+        })
+        .to_string()
+        .into_bytes()
+    }
+
+    fn authenticator_data(&self, flags: u8, attested_credential: Option<Vec<u8>>) -> Vec<u8> {
+        let mut data = Sha256::digest(self.rp_id.as_bytes()).to_vec();
+        data.push(flags);
+        data.extend_from_slice(&self.sign_count.to_be_bytes());
+
+        if let Some(cose_key) = attested_credential {
+            data.extend_from_slice(&[0u8; 16]); // aaguid
+            data.extend_from_slice(&(self.credential_id.len() as u16).to_be_bytes());
+            data.extend_from_slice(&self.credential_id);
+            data.extend_from_slice(&cose_key);
+        }
+
+        data
+    }
+
+    fn cose_key(&self) -> Vec<u8> {
+        let point = self.key.verifying_key().to_encoded_point(false);
+        let key = CborValue::Map(vec![
+            (CborValue::Integer(1.into()), CborValue::Integer(2.into())),
+            (CborValue::Integer(3.into()), CborValue::Integer((-7).into())),
+            (CborValue::Integer((-1).into()), CborValue::Integer(1.into())),
+            (CborValue::Integer((-2).into()), CborValue::Bytes(point.x().unwrap().to_vec())),
+            (CborValue::Integer((-3).into()), CborValue::Bytes(point.y().unwrap().to_vec())),
+        ]);
+
+        let mut bytes = Vec::new();
+        ciborium::ser::into_writer(&key, &mut bytes).unwrap();
+        bytes
+    }
+
+    /// Answer `navigator.credentials.create()`
+    fn create(&mut self, options: &serde_json::Value) -> serde_json::Value {
+        let auth_data = self.authenticator_data(0x41, Some(self.cose_key()));
+        let attestation = CborValue::Map(vec![
+            (CborValue::Text("fmt".into()), CborValue::Text("none".into())),
+            (CborValue::Text("attStmt".into()), CborValue::Map(vec![])),
+            (CborValue::Text("authData".into()), CborValue::Bytes(auth_data)),
+        ]);
+
+        let mut attestation_object = Vec::new();
+        ciborium::ser::into_writer(&attestation, &mut attestation_object).unwrap();
+
+        serde_json::json!({
+            "id": self.credential_id(),
+            "type": "public-key",
+            "name": "Test authenticator",
+            "response": {
+                "clientDataJSON": URL_SAFE_NO_PAD.encode(self.client_data("webauthn.create", options)),
+                "attestationObject": URL_SAFE_NO_PAD.encode(attestation_object),
+            }
+        })
+    }
+
+    /// Answer `navigator.credentials.get()`
+    fn get(&mut self, options: &serde_json::Value) -> serde_json::Value {
+        self.sign_count += 1;
+
+        let client_data = self.client_data("webauthn.get", options);
+        let auth_data = self.authenticator_data(0x05, None);
+
+        let mut signed_data = auth_data.clone();
+        signed_data.extend_from_slice(&Sha256::digest(&client_data));
+        let signature: Signature = self.key.sign(&signed_data);
+
+        serde_json::json!({
+            "id": self.credential_id(),
+            "type": "public-key",
+            "response": {
+                "clientDataJSON": URL_SAFE_NO_PAD.encode(client_data),
+                "authenticatorData": URL_SAFE_NO_PAD.encode(auth_data),
+                "signature": URL_SAFE_NO_PAD.encode(signature.to_der().as_bytes()),
+            }
+        })
+    }
+}
+
+async fn post_for_json(app: &TestApp, endpoint: &str, body: String, token: Option<&str>) -> (u16, serde_json::Value) {
+    let response = match token {
+        Some(token) => app.payload_for_post_with_token(body, endpoint, token).await,
+        None => app.payload_for_post(body, endpoint).await,
+    };
+    let status = response.status().as_u16();
+    let text = response.text().await.unwrap();
+
+    (status, serde_json::from_str(&text).unwrap_or(serde_json::Value::Null))
+}
+
+async fn register_passkey(app: &TestApp, authenticator: &mut SoftwareAuthenticator, token: &str) {
+    let (status, options) = post_for_json(app, "api/v1/users/passkeys/register/start", "".to_string(), Some(token)).await;
+    assert_eq!(200, status);
+
+    let credential = authenticator.create(&options);
+    let (status, _) = post_for_json(app, "api/v1/users/passkeys/register/finish", credential.to_string(), Some(token)).await;
+    assert_eq!(201, status);
+}
+
+#[actix_web::test]
+async fn passkey_registration_and_login() {
+    // Arrange
+    let app = start_test_server().await;
+
+    let token = app.register_and_login("test_devactivity", "test@devactivity.com").await;
+    let mut authenticator = SoftwareAuthenticator::new(&app);
+    register_passkey(&app, &mut authenticator, &token).await;
+
+    let (status, options) = post_for_json(&app, "api/v1/users/passkeys/login/start", "".to_string(), None).await;
+    assert_eq!(200, status);
+
+    // Act
+    let assertion = authenticator.get(&options);
+    let (status, body) = post_for_json(&app, "api/v1/users/passkeys/login/finish", assertion.to_string(), None).await;
+
+    // Assert
+    assert_eq!(201, status);
+    assert!(body["token"].is_string());
+
+    let response = app.payload_for_get_with_token("api/v1/users/passkeys", body["token"].as_str().unwrap()).await;
+    assert_eq!(200, response.status().as_u16());
+
+    let body: serde_json::Value = serde_json::from_str(&response.text().await.unwrap()).unwrap();
+    assert_eq!("Test authenticator", body["passkeys"][0]["name"]);
+    assert!(body["passkeys"][0]["last_used_at"].is_string());
+}
+
+#[actix_web::test]
+async fn passkey_login_rejects_a_replayed_assertion() {
+    // Arrange
+    let app = start_test_server().await;
+
+    let token = app.register_and_login("test_devactivity", "test@devactivity.com").await;
+    let mut authenticator = SoftwareAuthenticator::new(&app);
+    register_passkey(&app, &mut authenticator, &token).await;
+
+    let (_, options) = post_for_json(&app, "api/v1/users/passkeys/login/start", "".to_string(), None).await;
+    let assertion = authenticator.get(&options);
+
+    let (status, _) = post_for_json(&app, "api/v1/users/passkeys/login/finish", assertion.to_string(), None).await;
+    assert_eq!(201, status);
+
+    // Act
+    let (status, _) = post_for_json(&app, "api/v1/users/passkeys/login/finish", assertion.to_string(), None).await;
+
+    // Assert
+    assert_eq!(400, status);
+}
+
+#[actix_web::test]
+async fn passkey_login_rejects_a_signature_from_another_key() {
+    // Arrange
+    let app = start_test_server().await;
+
+    let token = app.register_and_login("test_devactivity", "test@devactivity.com").await;
+    let mut authenticator = SoftwareAuthenticator::new(&app);
+    register_passkey(&app, &mut authenticator, &token).await;
+
+    // Same credential id, different private key
+    authenticator.key = SigningKey::random(&mut OsRng);
+
+    let (_, options) = post_for_json(&app, "api/v1/users/passkeys/login/start", "".to_string(), None).await;
+    let assertion = authenticator.get(&options);
+
+    // Act
+    let (status, _) = post_for_json(&app, "api/v1/users/passkeys/login/finish", assertion.to_string(), None).await;
+
+    // Assert
+    assert_eq!(401, status);
+}
+
+#[actix_web::test]
+async fn passkey_login_with_a_stale_counter_is_refused_and_audited() {
+    // Arrange
+    let app = start_test_server().await;
+
+    let token = app.register_and_login("test_devactivity", "test@devactivity.com").await;
+    let mut authenticator = SoftwareAuthenticator::new(&app);
+    register_passkey(&app, &mut authenticator, &token).await;
+
+    let (_, options) = post_for_json(&app, "api/v1/users/passkeys/login/start", "".to_string(), None).await;
+    let (status, _) = post_for_json(&app, "api/v1/users/passkeys/login/finish", authenticator.get(&options).to_string(), None).await;
+    assert_eq!(201, status);
+
+    // A clone of the authenticator still counts from where it was copied
+    authenticator.sign_count = 0;
+
+    let (_, options) = post_for_json(&app, "api/v1/users/passkeys/login/start", "".to_string(), None).await;
+    let assertion = authenticator.get(&options);
+
+    // Act
+    let (status, _) = post_for_json(&app, "api/v1/users/passkeys/login/finish", assertion.to_string(), None).await;
+
+    // Assert
+    assert_eq!(401, status);
+
+    let events: Vec<(Option<String>, serde_json::Value)> = sqlx::query_as(r#"
+        SELECT users.username, audit_events.details FROM audit_events
+        LEFT JOIN users ON users.id = audit_events.actor_id
+        WHERE audit_events.kind = 'passkey_counter_regressed'
+    "#)
+    .fetch_all(&app.db_pool)
+    .await
+    .unwrap();
+
+    assert_eq!(1, events.len());
+    assert_eq!(Some("test_devactivity".to_string()), events[0].0);
+    assert_eq!(1, events[0].1["stored_sign_count"]);
+    assert_eq!(1, events[0].1["received_sign_count"]);
+}
@@ -9,7 +9,7 @@ async fn test_get_ping() {
     let client = reqwest::Client::new();
 
     let response = client
-        .get(&format!("{}/ping", &app.address))
+        .get(format!("{}/ping", &app.address))
         .header("Content-Type", "text/plain")
         .send()
         .await
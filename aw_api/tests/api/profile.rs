@@ -18,7 +18,7 @@ async fn get_profile_returns_a_200_if_it_success() {
     let body_data = serde_json::to_string(&payload).unwrap();
 
     // Act
-    let response = app.payload_for_post(body_data.into(), "api/v1/users/register").await;
+    let response = app.payload_for_post(body_data, "api/v1/users/register").await;
 
     // Assert
     assert_eq!(201, response.status().as_u16());
@@ -49,59 +49,53 @@ async fn follow_a_profile_returns_a_201_if_it_success_and_unfollow_it_returns_a_
     // Arrange
     let app = start_test_server().await;
 
-    for index in 0..2 {
-        let payload = serde_json::json!({
-            "user": {
-                "username": format!("test_devactivity{}", index),
-                "email": format!("test{}@devactivity.com", index),
-                "password": "12345678"
-            }
-        });
+    let token = app.register_and_login("test_devactivity0", "test0@devactivity.com").await;
+    app.register_and_login("test_devactivity1", "test1@devactivity.com").await;
 
-        // Serialize the JSON payload into a string
-        let body_data = serde_json::to_string(&payload).unwrap();
+    // Act
+    let response = app.payload_for_post_with_token(String::new(), format!("api/v1/profiles/{}/follow", "test_devactivity1").as_str(), &token).await;
 
-        // Act
-        let response = app.payload_for_post(body_data.into(), "api/v1/users/register").await;
+    // Assert
+    assert_eq!(201, response.status().as_u16());
 
-        // Assert
-        assert_eq!(201, response.status().as_u16());
-    }
+    // Act
+    let response = app.payload_for_get_with_token(format!("api/v1/profiles/{}", "test_devactivity1").as_str(), &token).await;
+    let body: serde_json::Value = serde_json::from_str(&response.text().await.unwrap()).unwrap();
 
-    let payload = serde_json::json!({
-        "email": "test1@devactivity.com",
-    });
+    // Assert
+    assert_eq!(true, body["profile"]["is_following"]);
 
-    // Serialize the JSON payload into a string
-    let body_data = serde_json::to_string(&payload).unwrap();
     // Act
-    let response = app.payload_for_post(body_data.into(), format!("api/v1/profiles/{}/follow", "test_devactivity0").as_str()).await;
+    let response = app.payload_for_delete_with_token(String::new(), format!("api/v1/profiles/{}/follow", "test_devactivity1").as_str(), &token).await;
 
     // Assert
-    assert_eq!(201, response.status().as_u16());
+    assert_eq!(202, response.status().as_u16());
+}
+
+#[actix_web::test]
+async fn follow_a_profile_returns_a_404_if_user_does_not_exist() {
+    // Arrange
+    let app = start_test_server().await;
+
+    let token = app.register_and_login("test_devactivity0", "test0@devactivity.com").await;
 
-    let body_data = serde_json::to_string(&payload).unwrap();
     // Act
-    let response = app.payload_for_delete(body_data.into(), format!("api/v1/profiles/{}/follow", "test_devactivity1").as_str()).await;
+    let response = app.payload_for_post_with_token(String::new(), format!("api/v1/profiles/{}/follow", "unknown").as_str(), &token).await;
 
     // Assert
-    assert_eq!(202, response.status().as_u16());
+    assert_eq!(404, response.status().as_u16());
 }
 
 #[actix_web::test]
-async fn follow_a_profile_returns_a_500_if_user_does_not_exist() {
+async fn follow_a_profile_returns_a_401_without_token() {
     // Arrange
     let app = start_test_server().await;
 
-    let payload = serde_json::json!({
-        "email": "test1@devactivity.com",
-    });
+    app.register_and_login("test_devactivity1", "test1@devactivity.com").await;
 
-    // Serialize the JSON payload into a string
-    let body_data = serde_json::to_string(&payload).unwrap();
     // Act
-    let response = app.payload_for_post(body_data.into(), format!("api/v1/profiles/{}/follow", "test_devactivity").as_str()).await;
+    let response = app.payload_for_post(String::new(), format!("api/v1/profiles/{}/follow", "test_devactivity1").as_str()).await;
 
     // Assert
-    assert_eq!(500, response.status().as_u16());
+    assert_eq!(401, response.status().as_u16());
 }
@@ -0,0 +1,17 @@
+use aw_api::settings::{get_app_mode, Mode};
+
+#[test]
+fn production_refuses_the_development_jwt_secret() {
+    // Arrange
+    let mut settings = get_app_mode().expect("Failed to read configuration.");
+
+    // Act & Assert
+    assert!(settings.check_for(&Mode::Development).is_ok());
+    assert!(settings.check_for(&Mode::Production).is_err());
+
+    settings.application.jwt_secret = String::new();
+    assert!(settings.check_for(&Mode::Production).is_err());
+
+    settings.application.jwt_secret = "a-long-random-production-secret".to_string();
+    assert!(settings.check_for(&Mode::Production).is_ok());
+}
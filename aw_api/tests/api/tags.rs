@@ -11,3 +11,56 @@ async fn get_tags_returns_a_200_if_it_success() {
     // Assert
     assert_eq!(200, response.status().as_u16());
 }
+
+#[actix_web::test]
+async fn get_tags_pages_sorted_tags_with_a_total_count() {
+    // Arrange
+    let app = start_test_server().await;
+
+    let token = app.register_and_login("test_devactivity", "test@devactivity.com").await;
+    let payload = serde_json::json!({
+        "body": "this is body article",
+        "description": "the most interesting topic",
+        "tagList": ["rust", "actix", "sqlx"],
+        "title": "tagged"
+    });
+    app.payload_for_post_with_token(payload.to_string(), "api/v1/articles", &token).await;
+
+    // Act
+    let response = app.payload_for_get("api/v1/tags?limit=2").await;
+
+    // Assert
+    assert_eq!(200, response.status().as_u16());
+    assert_eq!("3", response.headers()["X-Total-Count"].to_str().unwrap());
+    assert!(response.headers()["Link"].to_str().unwrap().contains("/api/v1/tags?limit=2&offset=2>; rel=\"next\""));
+
+    let body: serde_json::Value = serde_json::from_str(&response.text().await.unwrap()).unwrap();
+    assert_eq!(serde_json::json!(["actix", "rust"]), body["tags"]);
+}
+
+#[actix_web::test]
+async fn get_tags_returns_every_tag_without_a_limit() {
+    // Arrange
+    let app = start_test_server().await;
+
+    let token = app.register_and_login("test_devactivity", "test@devactivity.com").await;
+    let tags: Vec<String> = (0..25).map(|i| format!("tag{:02}", i)).collect();
+    let payload = serde_json::json!({
+        "body": "this is body article",
+        "description": "the most interesting topic",
+        "tagList": tags,
+        "title": "tagged"
+    });
+    app.payload_for_post_with_token(payload.to_string(), "api/v1/articles", &token).await;
+
+    // Act
+    let response = app.payload_for_get("api/v1/tags").await;
+
+    // Assert
+    assert_eq!(200, response.status().as_u16());
+    assert_eq!("25", response.headers()["X-Total-Count"].to_str().unwrap());
+    assert!(!response.headers()["Link"].to_str().unwrap().contains("rel=\"next\""));
+
+    let body: serde_json::Value = serde_json::from_str(&response.text().await.unwrap()).unwrap();
+    assert_eq!(serde_json::json!(tags), body["tags"]);
+}
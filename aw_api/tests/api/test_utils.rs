@@ -1,21 +1,31 @@
+use std::future::Future;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
 use sqlx::{PgPool, Connection, Executor, PgConnection};
-use aw_api::settings::{get_app_mode, DatabaseSettings};
+use aw_api::settings::{get_app_mode, DatabaseSettings, MailerBackend, Settings};
 use aw_api::server::{Application, get_connection_pool};
-use chrono::Local;
+use aw_api::clock::{Clock, FixedClock};
+use aw_api::auth::totp_code;
+use chrono::{Local, Utc};
 use uuid::Uuid;
 use wiremock::MockServer;
 
+#[allow(dead_code)]
 pub struct TestApp {
     pub address: String,
     pub db_pool: PgPool,
     pub port: u16,
-    pub test_server: MockServer
+    pub test_server: MockServer,
+    pub mail_directory: PathBuf,
+    pub clock: Arc<FixedClock>,
+    pub settings: Settings
 }
 
 impl TestApp {
     pub async fn payload_for_get(&self, endpoint: &str) -> reqwest::Response {
         reqwest::Client::new()
-            .get(&format!("{}/{}", &self.address, endpoint.to_string()))
+            .get(format!("{}/{}", &self.address, endpoint))
             .header("Content-Type", "application/json") // Update the content type
             .send()
             .await
@@ -24,7 +34,7 @@ impl TestApp {
 
     pub async fn payload_for_post(&self, body: String, endpoint: &str) -> reqwest::Response {
         reqwest::Client::new()
-            .post(&format!("{}/{}", &self.address, endpoint.to_string()))
+            .post(format!("{}/{}", &self.address, endpoint))
             .header("Content-Type", "application/json") // Update the content type
             .body(body)
             .send()
@@ -34,7 +44,7 @@ impl TestApp {
 
     pub async fn payload_for_put(&self, body: String, endpoint: &str) -> reqwest::Response {
         reqwest::Client::new()
-            .put(&format!("{}/{}", &self.address, endpoint.to_string()))
+            .put(format!("{}/{}", &self.address, endpoint))
             .header("Content-Type", "application/json") // Update the content type
             .body(body)
             .send()
@@ -44,16 +54,222 @@ impl TestApp {
 
     pub async fn payload_for_delete(&self, body: String, endpoint: &str) -> reqwest::Response {
         reqwest::Client::new()
-            .delete(&format!("{}/{}", &self.address, endpoint.to_string()))
+            .delete(format!("{}/{}", &self.address, endpoint))
             .header("Content-Type", "application/json") // Update the content type
             .body(body)
             .send()
             .await
             .expect("Failed to execute request.")
     }
+
+    pub async fn payload_for_get_with_token(&self, endpoint: &str, token: &str) -> reqwest::Response {
+        reqwest::Client::new()
+            .get(format!("{}/{}", &self.address, endpoint))
+            .header("Content-Type", "application/json")
+            .bearer_auth(token)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn payload_for_post_with_token(&self, body: String, endpoint: &str, token: &str) -> reqwest::Response {
+        reqwest::Client::new()
+            .post(format!("{}/{}", &self.address, endpoint))
+            .header("Content-Type", "application/json")
+            .bearer_auth(token)
+            .body(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn payload_for_put_with_token(&self, body: String, endpoint: &str, token: &str) -> reqwest::Response {
+        reqwest::Client::new()
+            .put(format!("{}/{}", &self.address, endpoint))
+            .header("Content-Type", "application/json")
+            .bearer_auth(token)
+            .body(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn payload_for_delete_with_token(&self, body: String, endpoint: &str, token: &str) -> reqwest::Response {
+        reqwest::Client::new()
+            .delete(format!("{}/{}", &self.address, endpoint))
+            .header("Content-Type", "application/json")
+            .bearer_auth(token)
+            .body(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn payload_for_post_with_api_key(&self, body: String, endpoint: &str, key: &str) -> reqwest::Response {
+        reqwest::Client::new()
+            .post(format!("{}/{}", &self.address, endpoint))
+            .header("Content-Type", "application/json")
+            .header("X-Api-Key", key)
+            .body(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn payload_for_get_with_api_key(&self, endpoint: &str, key: &str) -> reqwest::Response {
+        reqwest::Client::new()
+            .get(format!("{}/{}", &self.address, endpoint))
+            .header("Content-Type", "application/json")
+            .header("X-Api-Key", key)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    /// Create an API key with the scopes for the logged in user and return the full key
+    pub async fn create_api_key(&self, token: &str, scopes: &[&str]) -> serde_json::Value {
+        let payload = serde_json::json!({
+            "name": "CI bot",
+            "scopes": scopes,
+        });
+
+        let response = self.payload_for_post_with_token(payload.to_string(), "api/v1/users/api-keys", token).await;
+        assert_eq!(201, response.status().as_u16());
+
+        serde_json::from_str(&response.text().await.unwrap()).unwrap()
+    }
+
+    /// Register a user with the default test password and return its access token
+    pub async fn register_and_login(&self, username: &str, email: &str) -> String {
+        let payload = serde_json::json!({
+            "user": {
+                "username": username,
+                "email": email,
+                "password": "12345678"
+            }
+        });
+
+        let response = self.payload_for_post(payload.to_string(), "api/v1/users/register").await;
+        assert_eq!(201, response.status().as_u16());
+
+        let response = self.verify_email(email).await;
+        assert_eq!(200, response.status().as_u16());
+
+        let body = self.login(email, "12345678").await;
+        body["token"].as_str().expect("Login response has no token").to_string()
+    }
+
+    /// The last mail sent to `to`, as written by the file mailer
+    pub fn last_email_to(&self, to: &str) -> Option<String> {
+        let mut files: Vec<PathBuf> = std::fs::read_dir(&self.mail_directory)
+            .map(|entries| entries.filter_map(|entry| entry.ok().map(|entry| entry.path())).collect())
+            .unwrap_or_default();
+        files.sort();
+
+        files
+            .into_iter()
+            .rev()
+            .map(|path| std::fs::read_to_string(path).expect("Failed to read email"))
+            .find(|email| email.lines().any(|line| line == format!("To: {}", to)))
+    }
+
+    /// Pull the first `token=...` value, e.g. of a link, out of the last mail sent to `to`
+    pub fn token_from_last_email_to(&self, to: &str) -> String {
+        let email = self.last_email_to(to).expect("No email was sent to this address");
+
+        email
+            .split_whitespace()
+            .find_map(|word| word.split_once("token=").map(|(_, token)| token.to_string()))
+            .expect("Email has no token")
+    }
+
+    /// Open the verification link of the last mail sent to `to`
+    pub async fn verify_email(&self, to: &str) -> reqwest::Response {
+        let token = self.token_from_last_email_to(to);
+
+        self.payload_for_get(&format!("api/v1/users/verify?token={}", token)).await
+    }
+
+    /// The code an authenticator app shows for `secret` at the time of the app clock
+    pub fn totp_code(&self, secret: &str) -> String {
+        totp_code(secret, self.clock.now(), &self.settings.application).expect("Failed to generate TOTP code")
+    }
+
+    /// Give a role straight in the database, e.g. to bootstrap the first admin
+    pub async fn grant_role(&self, username: &str, role: &str) {
+        sqlx::query("INSERT INTO user_roles (user_id, role) SELECT id, $2::user_role FROM users WHERE username = $1")
+            .bind(username)
+            .bind(role)
+            .execute(&self.db_pool)
+            .await
+            .expect("Failed to grant role");
+    }
+
+    /// Login and return the parsed response body
+    ///
+    /// Users with two-factor authentication get a challenge with 200 instead of the tokens with 201
+    pub async fn login(&self, email: &str, password: &str) -> serde_json::Value {
+        let payload = serde_json::json!({
+            "user": {
+                "email": email,
+                "password": password
+            }
+        });
+
+        let response = self.payload_for_post(payload.to_string(), "api/v1/users/login").await;
+        assert!(matches!(response.status().as_u16(), 200 | 201));
+
+        serde_json::from_str(&response.text().await.unwrap())
+            .expect("Failed to parse login response")
+    }
+}
+
+/// Run `future` and count the statements sqlx executes for it on this thread
+///
+/// Only works for code awaited directly by the test, requests to the server run on its own workers
+pub async fn count_queries<F: Future>(future: F) -> (F::Output, usize) {
+    let count = Arc::new(AtomicUsize::new(0));
+    let _guard = tracing::subscriber::set_default(QueryCounter { count: count.clone() });
+
+    let output = future.await;
+
+    (output, count.load(Ordering::SeqCst))
+}
+
+struct QueryCounter {
+    count: Arc<AtomicUsize>,
+}
+
+impl tracing::Subscriber for QueryCounter {
+    fn enabled(&self, metadata: &tracing::Metadata<'_>) -> bool {
+        metadata.target() == "sqlx::query"
+    }
+
+    fn new_span(&self, _span: &tracing::span::Attributes<'_>) -> tracing::span::Id {
+        tracing::span::Id::from_u64(1)
+    }
+
+    fn record(&self, _span: &tracing::span::Id, _values: &tracing::span::Record<'_>) {}
+
+    fn record_follows_from(&self, _span: &tracing::span::Id, _follows: &tracing::span::Id) {}
+
+    fn event(&self, event: &tracing::Event<'_>) {
+        if event.metadata().target() == "sqlx::query" {
+            self.count.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    fn enter(&self, _span: &tracing::span::Id) {}
+
+    fn exit(&self, _span: &tracing::span::Id) {}
 }
 
 pub async fn start_test_server() -> TestApp {
+    start_test_server_with(|_| {}).await
+}
+
+/// Same as `start_test_server` with the configuration adjusted by `configure`, e.g. to switch to cookie sessions
+pub async fn start_test_server_with(configure: impl FnOnce(&mut Settings)) -> TestApp {
     let test_server = MockServer::start().await;
     let current_time = Local::now();
     let time_prefix = current_time.format("%Y%m%d%H%M%S").to_string();
@@ -61,9 +277,18 @@ pub async fn start_test_server() -> TestApp {
     let configuration = {
         let mut cfg = get_app_mode().expect("Failed to read configuration.");
 
-        cfg.database.database_name = format!("{}_{}_{}", "aw_api_test".to_string(), time_prefix, Uuid::new_v4().to_string());
+        cfg.database.database_name = format!("{}_{}_{}", "aw_api_test", time_prefix, Uuid::new_v4());
         cfg.application.port = 0;
         cfg.test_client.base_url = test_server.uri();
+        cfg.email.backend = MailerBackend::File;
+        cfg.email.file_directory = Some(
+            std::env::temp_dir()
+                .join(format!("aw_api_test_mail_{}", Uuid::new_v4()))
+                .to_string_lossy()
+                .into_owned(),
+        );
+
+        configure(&mut cfg);
 
         cfg
     };
@@ -71,7 +296,9 @@ pub async fn start_test_server() -> TestApp {
     // create and migrate the database
     configure_database(&configuration.database).await;
 
-    let app = Application::build_app(configuration.clone())
+    let clock = Arc::new(FixedClock::new(Utc::now()));
+
+    let app = Application::build_app_with_clock(configuration.clone(), clock.clone())
         .await
         .expect("Failed to build application");
 
@@ -79,13 +306,16 @@ pub async fn start_test_server() -> TestApp {
 
     let address = format!("http://127.0.0.1:{}", application_port);
 
-    let _ = actix_web::rt::spawn(app.run_app());
+    actix_web::rt::spawn(app.run_app());
 
     TestApp {
         address,
         db_pool: get_connection_pool(&configuration.database),
         port: application_port,
-        test_server
+        test_server,
+        mail_directory: PathBuf::from(configuration.email.file_directory.clone().unwrap()),
+        clock,
+        settings: configuration
     }
 }
 
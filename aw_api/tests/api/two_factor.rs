@@ -0,0 +1,143 @@
+use chrono::Duration;
+
+use crate::test_utils::{start_test_server, TestApp};
+
+/// Enroll and confirm 2FA for the user of `token`, returning the secret and the recovery codes
+async fn enable_two_factor(app: &TestApp, token: &str) -> (String, Vec<String>) {
+    let response = app.payload_for_post_with_token("".to_string(), "api/v1/users/2fa/enroll", token).await;
+    assert_eq!(200, response.status().as_u16());
+
+    let body: serde_json::Value = serde_json::from_str(&response.text().await.unwrap()).unwrap();
+    let secret = body["secret"].as_str().unwrap().to_string();
+    assert!(body["otpauth_uri"].as_str().unwrap().starts_with("otpauth://totp/"));
+
+    let payload = serde_json::json!({ "code": app.totp_code(&secret) });
+    let response = app.payload_for_post_with_token(payload.to_string(), "api/v1/users/2fa/confirm", token).await;
+    assert_eq!(200, response.status().as_u16());
+
+    let body: serde_json::Value = serde_json::from_str(&response.text().await.unwrap()).unwrap();
+    let recovery_codes = body["recovery_codes"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|code| code.as_str().unwrap().to_string())
+        .collect();
+
+    (secret, recovery_codes)
+}
+
+async fn challenge_token(app: &TestApp) -> String {
+    let body = app.login("test@devactivity.com", "12345678").await;
+    assert_eq!(true, body["two_factor_required"]);
+    assert!(body["token"].is_null());
+
+    body["challenge_token"].as_str().unwrap().to_string()
+}
+
+#[actix_web::test]
+async fn login_with_two_factor_needs_a_fresh_code() {
+    // Arrange
+    let app = start_test_server().await;
+
+    let token = app.register_and_login("test_devactivity", "test@devactivity.com").await;
+    let (secret, recovery_codes) = enable_two_factor(&app, &token).await;
+    assert_eq!(10, recovery_codes.len());
+
+    // Act
+    let challenge_token = challenge_token(&app).await;
+
+    // Assert
+    // The code used to confirm the enrollment cannot be replayed
+    let payload = serde_json::json!({ "challenge_token": challenge_token, "code": app.totp_code(&secret) });
+    let response = app.payload_for_post(payload.to_string(), "api/v1/users/login/2fa").await;
+    assert_eq!(401, response.status().as_u16());
+
+    sqlx::query("UPDATE login_throttles SET locked_until = NULL")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+    app.clock.advance(Duration::seconds(30));
+
+    let payload = serde_json::json!({ "challenge_token": challenge_token, "code": app.totp_code(&secret) });
+    let response = app.payload_for_post(payload.to_string(), "api/v1/users/login/2fa").await;
+    assert_eq!(201, response.status().as_u16());
+
+    let body: serde_json::Value = serde_json::from_str(&response.text().await.unwrap()).unwrap();
+    assert!(body["token"].is_string());
+}
+
+#[actix_web::test]
+async fn recovery_codes_can_be_used_once() {
+    // Arrange
+    let app = start_test_server().await;
+
+    let token = app.register_and_login("test_devactivity", "test@devactivity.com").await;
+    let (_, recovery_codes) = enable_two_factor(&app, &token).await;
+
+    let challenge_token = challenge_token(&app).await;
+    let payload = serde_json::json!({ "challenge_token": challenge_token, "code": recovery_codes[0] });
+
+    // Act
+    let response = app.payload_for_post(payload.to_string(), "api/v1/users/login/2fa").await;
+
+    // Assert
+    assert_eq!(201, response.status().as_u16());
+
+    let response = app.payload_for_post(payload.to_string(), "api/v1/users/login/2fa").await;
+    assert_eq!(401, response.status().as_u16());
+}
+
+#[actix_web::test]
+async fn confirm_two_factor_returns_a_422_for_a_wrong_code() {
+    // Arrange
+    let app = start_test_server().await;
+
+    let token = app.register_and_login("test_devactivity", "test@devactivity.com").await;
+
+    let response = app.payload_for_post_with_token("".to_string(), "api/v1/users/2fa/enroll", &token).await;
+    assert_eq!(200, response.status().as_u16());
+
+    let payload = serde_json::json!({ "code": "000000x" });
+
+    // Act
+    let response = app.payload_for_post_with_token(payload.to_string(), "api/v1/users/2fa/confirm", &token).await;
+
+    // Assert
+    assert_eq!(422, response.status().as_u16());
+}
+
+#[actix_web::test]
+async fn disable_two_factor_locks_out_after_repeated_wrong_codes() {
+    // Arrange
+    let app = start_test_server().await;
+
+    let token = app.register_and_login("test_devactivity", "test@devactivity.com").await;
+    let (secret, _) = enable_two_factor(&app, &token).await;
+
+    let wrong_code = serde_json::json!({ "code": "000000" });
+    for _ in 0..5 {
+        // Skip the backoff between the attempts
+        sqlx::query("UPDATE login_throttles SET locked_until = NULL")
+            .execute(&app.db_pool)
+            .await
+            .unwrap();
+
+        let response = app.payload_for_delete_with_token(wrong_code.to_string(), "api/v1/users/2fa", &token).await;
+        assert_eq!(422, response.status().as_u16());
+    }
+
+    app.clock.advance(Duration::seconds(30));
+    let payload = serde_json::json!({ "code": app.totp_code(&secret) });
+
+    // Act
+    let response = app.payload_for_delete_with_token(payload.to_string(), "api/v1/users/2fa", &token).await;
+
+    // Assert
+    assert_eq!(429, response.status().as_u16());
+
+    let retry_after: u64 = response.headers()["Retry-After"].to_str().unwrap().parse().unwrap();
+    assert!(retry_after > 60);
+
+    // Two-factor authentication is still on
+    challenge_token(&app).await;
+}
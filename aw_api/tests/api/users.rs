@@ -18,7 +18,7 @@ async fn register_user_returns_a_201_for_valid_payload_data_and_login_should_be_
     let body_data = serde_json::to_string(&payload).unwrap();
 
     // Act
-    let response = app.payload_for_post(body_data.into(), "api/v1/users/register").await;
+    let response = app.payload_for_post(body_data, "api/v1/users/register").await;
 
     // Assert
     assert_eq!(201, response.status().as_u16());
@@ -35,10 +35,13 @@ async fn register_user_returns_a_201_for_valid_payload_data_and_login_should_be_
     let body_data = serde_json::to_string(&payload).unwrap();
 
     // Act
-    let response = app.payload_for_post(body_data.into(), "api/v1/users/login").await;
+    let response = app.payload_for_post(body_data, "api/v1/users/login").await;
 
     // Assert
     assert_eq!(201, response.status().as_u16());
+
+    let body: serde_json::Value = serde_json::from_str(&response.text().await.unwrap()).unwrap();
+    assert!(body["token"].is_string());
 }
 
 #[actix_web::test]
@@ -58,7 +61,7 @@ async fn login_user_returns_a_500_if_user_does_not_exist() {
     let body_data = serde_json::to_string(&payload).unwrap();
 
     // Act
-    let response = app.payload_for_post(body_data.into(), "api/v1/users/login").await;
+    let response = app.payload_for_post(body_data, "api/v1/users/login").await;
 
     // Assert
     assert_eq!(500, response.status().as_u16());
@@ -82,7 +85,7 @@ async fn register_user_returns_a_400_for_invalid_payload_data() {
     let body_data = serde_json::to_string(&payload).unwrap();
 
     // Act
-    let response = app.payload_for_post(body_data.into(), "api/v1/users/register").await;
+    let response = app.payload_for_post(body_data, "api/v1/users/register").await;
 
     // Assert
     assert_eq!(400, response.status().as_u16());
@@ -93,12 +96,16 @@ async fn update_user_returns_a_201_for_valid_payload_data() {
     // Arrange
     let app = start_test_server().await;
 
+    let token = app.register_and_login("test_devactivity", "test@devactivity.com").await;
+
     // Create a JSON payload as a serde_json::Value
     let payload = serde_json::json!({
         "user": {
             "username": "test_devactivity",
-            "email": "test@devactivity.com",
-            "password": "12345678"
+            "email": "new@devactivity.com",
+            "password": "12345678",
+            "current_password": "12345678",
+            "bio": "i am a human"
         }
     });
 
@@ -106,33 +113,92 @@ async fn update_user_returns_a_201_for_valid_payload_data() {
     let body_data = serde_json::to_string(&payload).unwrap();
 
     // Act
-    let response = app.payload_for_post(body_data.into(), "api/v1/users/register").await;
+    let response = app.payload_for_put_with_token(body_data, "api/v1/users/update", &token).await;
 
     // Assert
     assert_eq!(201, response.status().as_u16());
+}
+
+#[actix_web::test]
+async fn update_user_password_or_email_requires_the_current_password() {
+    // Arrange
+    let app = start_test_server().await;
+
+    let token = app.register_and_login("test_devactivity", "test@devactivity.com").await;
+
+    let without_current = serde_json::json!({ "user": { "password": "new-password" } });
+    let email_without_current = serde_json::json!({ "user": { "email": "new@devactivity.com" } });
+    let same_email = serde_json::json!({ "user": { "email": "test@devactivity.com", "bio": "i am a human" } });
+    let wrong_current = serde_json::json!({ "user": { "email": "new@devactivity.com", "current_password": "wrong-password" } });
+
+    // Act
+    let missing = app.payload_for_put_with_token(without_current.to_string(), "api/v1/users/update", &token).await;
+    let email_missing = app.payload_for_put_with_token(email_without_current.to_string(), "api/v1/users/update", &token).await;
+    let unchanged_email = app.payload_for_put_with_token(same_email.to_string(), "api/v1/users/update", &token).await;
+    let wrong = app.payload_for_put_with_token(wrong_current.to_string(), "api/v1/users/update", &token).await;
+    let guessed_again = app.payload_for_put_with_token(wrong_current.to_string(), "api/v1/users/update", &token).await;
+
+    // Assert
+    assert_eq!(400, missing.status().as_u16());
+    assert_eq!(400, email_missing.status().as_u16());
+    assert_eq!(201, unchanged_email.status().as_u16());
+    assert_eq!(403, wrong.status().as_u16());
+
+    // Guesses back off like failed logins
+    assert_eq!(429, guessed_again.status().as_u16());
+    assert!(guessed_again.headers().contains_key("Retry-After"));
+
+    // The password and the address are unchanged
+    let body = app.login("test@devactivity.com", "12345678").await;
+    assert!(body["token"].is_string());
+}
+
+#[actix_web::test]
+async fn update_user_password_revokes_the_other_sessions() {
+    // Arrange
+    let app = start_test_server().await;
+
+    let token = app.register_and_login("test_devactivity", "test@devactivity.com").await;
+    let current_login = app.login("test@devactivity.com", "12345678").await;
+    let other_login = app.login("test@devactivity.com", "12345678").await;
+    let current_token = current_login["token"].as_str().unwrap();
 
-    // Create a JSON payload as a serde_json::Value
     let payload = serde_json::json!({
         "user": {
-            "username": "test_devactivity",
-            "email": "new@devactivity.com",
-            "password": "12345678",
-            "bio": "i am a human"
+            "email": "test@devactivity.com",
+            "password": "new-password",
+            "current_password": "12345678"
         }
     });
 
-    // Serialize the JSON payload into a string
-    let body_data = serde_json::to_string(&payload).unwrap();
-
     // Act
-    let response = app.payload_for_put(body_data.into(), "api/v1/users/update").await;
+    let response = app.payload_for_put_with_token(payload.to_string(), "api/v1/users/update", current_token).await;
 
     // Assert
     assert_eq!(201, response.status().as_u16());
+
+    // The session that changed the password stays signed in, with its refresh token
+    let response = app.payload_for_get_with_token("api/v1/users/sessions", current_token).await;
+    assert_eq!(200, response.status().as_u16());
+
+    let refresh = serde_json::json!({ "refresh_token": current_login["refresh_token"] });
+    let response = app.payload_for_post(refresh.to_string(), "api/v1/users/refresh").await;
+    assert_eq!(200, response.status().as_u16());
+
+    // Every other one is signed out
+    let response = app.payload_for_get_with_token("api/v1/users/sessions", &token).await;
+    assert_eq!(401, response.status().as_u16());
+
+    let refresh = serde_json::json!({ "refresh_token": other_login["refresh_token"] });
+    let response = app.payload_for_post(refresh.to_string(), "api/v1/users/refresh").await;
+    assert_eq!(401, response.status().as_u16());
+
+    let body = app.login("test@devactivity.com", "new-password").await;
+    assert!(body["token"].is_string());
 }
 
 #[actix_web::test]
-async fn update_user_returns_a_404_for_not_found_data() {
+async fn update_user_returns_a_401_without_token() {
     // Arrange
     let app = start_test_server().await;
 
@@ -150,10 +216,10 @@ async fn update_user_returns_a_404_for_not_found_data() {
     let body_data = serde_json::to_string(&payload).unwrap();
 
     // Act
-    let response = app.payload_for_put(body_data.into(), "api/v1/users/update").await;
+    let response = app.payload_for_put(body_data, "api/v1/users/update").await;
 
     // Assert
-    assert_eq!(404, response.status().as_u16());
+    assert_eq!(401, response.status().as_u16());
 }
 
 #[actix_web::test]
@@ -161,6 +227,8 @@ async fn update_user_returns_a_400_for_invalid_payload_data() {
     // Arrange
     let app = start_test_server().await;
 
+    let token = app.register_and_login("test_devactivity", "test@devactivity.com").await;
+
     // Create a JSON payload as a serde_json::Value
     let payload = serde_json::json!({
         "user": {
@@ -174,7 +242,7 @@ async fn update_user_returns_a_400_for_invalid_payload_data() {
     let body_data = serde_json::to_string(&payload).unwrap();
 
     // Act
-    let response = app.payload_for_put(body_data.into(), "api/v1/users/update").await;
+    let response = app.payload_for_put_with_token(body_data, "api/v1/users/update", &token).await;
 
     // Assert
     assert_eq!(400, response.status().as_u16());
@@ -185,7 +253,148 @@ async fn delete_user_returns_a_200_for_success() {
     // Arrange
     let app = start_test_server().await;
 
-    // Create a JSON payload as a serde_json::Value
+    let token = app.register_and_login("test_devactivity", "test@devactivity.com").await;
+
+    // Act
+    let response = app.payload_for_delete_with_token(String::new(), "api/v1/users/delete", &token).await;
+
+    // Assert
+    assert_eq!(200, response.status().as_u16());
+
+    // Act
+    let response = app.payload_for_delete_with_token(String::new(), "api/v1/users/delete", &token).await;
+
+    // Assert
+    assert_eq!(401, response.status().as_u16());
+}
+
+#[actix_web::test]
+async fn delete_user_returns_a_401_without_token() {
+    // Arrange
+    let app = start_test_server().await;
+
+    // Act
+    let response = app.payload_for_delete(String::new(), "api/v1/users/delete").await;
+
+    // Assert
+    assert_eq!(401, response.status().as_u16());
+}
+
+#[actix_web::test]
+async fn refresh_token_rotates_and_reuse_revokes_the_family() {
+    // Arrange
+    let app = start_test_server().await;
+
+    app.register_and_login("test_devactivity", "test@devactivity.com").await;
+    let login = app.login("test@devactivity.com", "12345678").await;
+    let first_refresh_token = login["refresh_token"].as_str().unwrap();
+
+    let payload = serde_json::json!({ "refresh_token": first_refresh_token });
+
+    // Act
+    let response = app.payload_for_post(payload.to_string(), "api/v1/users/refresh").await;
+
+    // Assert
+    assert_eq!(200, response.status().as_u16());
+
+    let body: serde_json::Value = serde_json::from_str(&response.text().await.unwrap()).unwrap();
+    let second_refresh_token = body["refresh_token"].as_str().unwrap().to_string();
+    assert!(body["token"].is_string());
+    assert_ne!(first_refresh_token, second_refresh_token);
+
+    // Act: the rotated token is replayed
+    let response = app.payload_for_post(payload.to_string(), "api/v1/users/refresh").await;
+
+    // Assert
+    assert_eq!(401, response.status().as_u16());
+
+    // Act: the latest token of the family is revoked as well
+    let payload = serde_json::json!({ "refresh_token": second_refresh_token });
+    let response = app.payload_for_post(payload.to_string(), "api/v1/users/refresh").await;
+
+    // Assert
+    assert_eq!(401, response.status().as_u16());
+}
+
+#[actix_web::test]
+async fn logout_revokes_the_refresh_token() {
+    // Arrange
+    let app = start_test_server().await;
+
+    app.register_and_login("test_devactivity", "test@devactivity.com").await;
+    let login = app.login("test@devactivity.com", "12345678").await;
+
+    let payload = serde_json::json!({ "refresh_token": login["refresh_token"] });
+
+    // Act
+    let response = app.payload_for_post(payload.to_string(), "api/v1/users/logout").await;
+
+    // Assert
+    assert_eq!(200, response.status().as_u16());
+
+    // Act
+    let response = app.payload_for_post(payload.to_string(), "api/v1/users/refresh").await;
+
+    // Assert
+    assert_eq!(401, response.status().as_u16());
+
+    // Act
+    let response = app.payload_for_get_with_token("api/v1/users/sessions", login["token"].as_str().unwrap()).await;
+
+    // Assert
+    assert_eq!(401, response.status().as_u16());
+}
+
+#[actix_web::test]
+async fn revoking_a_session_rejects_its_tokens() {
+    // Arrange
+    let app = start_test_server().await;
+
+    let token = app.register_and_login("test_devactivity", "test@devactivity.com").await;
+    let other_login = app.login("test@devactivity.com", "12345678").await;
+    let other_token = other_login["token"].as_str().unwrap();
+
+    // Act
+    let response = app.payload_for_get_with_token("api/v1/users/sessions", &token).await;
+
+    // Assert
+    assert_eq!(200, response.status().as_u16());
+
+    let body: serde_json::Value = serde_json::from_str(&response.text().await.unwrap()).unwrap();
+    let sessions = body["sessions"].as_array().unwrap();
+    assert_eq!(2, sessions.len());
+
+    let other_session = sessions
+        .iter()
+        .find(|session| session["current"] == false)
+        .unwrap();
+
+    // Act
+    let response = app.payload_for_delete_with_token(
+        String::new(),
+        format!("api/v1/users/sessions/{}", other_session["id"].as_str().unwrap()).as_str(),
+        &token,
+    ).await;
+
+    // Assert
+    assert_eq!(200, response.status().as_u16());
+
+    let response = app.payload_for_get_with_token("api/v1/users/sessions", other_token).await;
+    assert_eq!(401, response.status().as_u16());
+
+    let payload = serde_json::json!({ "refresh_token": other_login["refresh_token"] });
+    let response = app.payload_for_post(payload.to_string(), "api/v1/users/refresh").await;
+    assert_eq!(401, response.status().as_u16());
+
+    let response = app.payload_for_get_with_token("api/v1/users/sessions", &token).await;
+    assert_eq!(200, response.status().as_u16());
+}
+
+#[actix_web::test]
+async fn register_sends_a_verification_email_that_verifies_the_user() {
+    // Arrange
+    let app = start_test_server().await;
+
     let payload = serde_json::json!({
         "user": {
             "username": "test_devactivity",
@@ -194,51 +403,265 @@ async fn delete_user_returns_a_200_for_success() {
         }
     });
 
-    // Serialize the JSON payload into a string
-    let body_data = serde_json::to_string(&payload).unwrap();
+    let response = app.payload_for_post(payload.to_string(), "api/v1/users/register").await;
+    assert_eq!(201, response.status().as_u16());
+
+    // Act
+    let response = app.verify_email("test@devactivity.com").await;
+
+    // Assert
+    assert_eq!(200, response.status().as_u16());
+
+    let (verified,): (bool,) = sqlx::query_as("SELECT email_verified_at IS NOT NULL FROM users WHERE username = $1")
+        .bind("test_devactivity")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert!(verified);
+}
+
+#[actix_web::test]
+async fn verify_email_returns_a_400_for_an_invalid_token() {
+    // Arrange
+    let app = start_test_server().await;
+
+    // Act
+    let response = app.payload_for_get("api/v1/users/verify?token=not-a-token").await;
+
+    // Assert
+    assert_eq!(400, response.status().as_u16());
+}
+
+#[actix_web::test]
+async fn changing_the_email_requires_verifying_it_again() {
+    // Arrange
+    let app = start_test_server().await;
+
+    let token = app.register_and_login("test_devactivity", "test@devactivity.com").await;
+    let old_link_token = app.token_from_last_email_to("test@devactivity.com");
+
+    let payload = serde_json::json!({
+        "user": {
+            "email": "new@devactivity.com",
+            "password": "12345678",
+            "current_password": "12345678"
+        }
+    });
 
     // Act
-    let response = app.payload_for_post(body_data.into(), "api/v1/users/register").await;
+    let response = app.payload_for_put_with_token(payload.to_string(), "api/v1/users/update", &token).await;
 
     // Assert
     assert_eq!(201, response.status().as_u16());
 
-    // Create a JSON payload as a serde_json::Value
+    // The link sent to the previous address no longer verifies the account
+    let response = app.payload_for_get(&format!("api/v1/users/verify?token={}", old_link_token)).await;
+    assert_eq!(400, response.status().as_u16());
+
+    let response = app.verify_email("new@devactivity.com").await;
+    assert_eq!(200, response.status().as_u16());
+}
+
+#[actix_web::test]
+async fn password_reset_sets_a_new_password_and_revokes_the_sessions() {
+    // Arrange
+    let app = start_test_server().await;
+
+    let token = app.register_and_login("test_devactivity", "test@devactivity.com").await;
+
     let payload = serde_json::json!({
         "user": {
-            "username": "test_devactivity"
+            "email": "test@devactivity.com"
         }
     });
 
-    // Serialize the JSON payload into a string
-    let body_data = serde_json::to_string(&payload).unwrap();
+    let response = app.payload_for_post(payload.to_string(), "api/v1/users/password/forgot").await;
+    assert_eq!(200, response.status().as_u16());
+
+    let reset_token = app.token_from_last_email_to("test@devactivity.com");
+
+    let payload = serde_json::json!({
+        "user": {
+            "token": reset_token,
+            "password": "new-password"
+        }
+    });
 
     // Act
-    let response = app.payload_for_delete(body_data.into(), "api/v1/users/delete").await;
+    let response = app.payload_for_post(payload.to_string(), "api/v1/users/password/reset").await;
 
     // Assert
     assert_eq!(200, response.status().as_u16());
+
+    // The sessions opened with the old password are gone
+    let response = app.payload_for_get_with_token("api/v1/users/sessions", &token).await;
+    assert_eq!(401, response.status().as_u16());
+
+    let body = app.login("test@devactivity.com", "new-password").await;
+    assert!(body["token"].is_string());
+
+    // The token is single use
+    let response = app.payload_for_post(payload.to_string(), "api/v1/users/password/reset").await;
+    assert_eq!(400, response.status().as_u16());
 }
 
 #[actix_web::test]
-async fn delete_user_returns_a_404_for_not_found_data() {
+async fn forgot_password_does_not_reveal_unknown_emails() {
     // Arrange
     let app = start_test_server().await;
 
-    // Create a JSON payload as a serde_json::Value
     let payload = serde_json::json!({
         "user": {
-            "username": "test_devactivity"
+            "email": "unknown@devactivity.com"
         }
     });
 
-    // Serialize the JSON payload into a string
-    let body_data = serde_json::to_string(&payload).unwrap();
+    // Act
+    let response = app.payload_for_post(payload.to_string(), "api/v1/users/password/forgot").await;
+
+    // Assert
+    assert_eq!(200, response.status().as_u16());
+    assert!(app.last_email_to("unknown@devactivity.com").is_none());
+}
+
+#[actix_web::test]
+async fn failed_login_makes_the_next_attempt_wait() {
+    // Arrange
+    let app = start_test_server().await;
+
+    app.register_and_login("test_devactivity", "test@devactivity.com").await;
+
+    let payload = serde_json::json!({
+        "user": {
+            "email": "test@devactivity.com",
+            "password": "wrong-password"
+        }
+    });
+
+    let response = app.payload_for_post(payload.to_string(), "api/v1/users/login").await;
+    assert_eq!(401, response.status().as_u16());
+
+    // Act
+    let response = app.payload_for_post(payload.to_string(), "api/v1/users/login").await;
+
+    // Assert
+    assert_eq!(429, response.status().as_u16());
+    assert!(response.headers().contains_key("Retry-After"));
+}
+
+#[actix_web::test]
+async fn repeated_failed_logins_lock_the_account_out() {
+    // Arrange
+    let app = start_test_server().await;
+
+    app.register_and_login("test_devactivity", "test@devactivity.com").await;
+
+    let payload = serde_json::json!({
+        "user": {
+            "email": "test@devactivity.com",
+            "password": "wrong-password"
+        }
+    });
+
+    for _ in 0..5 {
+        // Skip the backoff between the attempts
+        sqlx::query("UPDATE login_throttles SET locked_until = NULL")
+            .execute(&app.db_pool)
+            .await
+            .unwrap();
+
+        let response = app.payload_for_post(payload.to_string(), "api/v1/users/login").await;
+        assert_eq!(401, response.status().as_u16());
+    }
+
+    let payload = serde_json::json!({
+        "user": {
+            "email": "test@devactivity.com",
+            "password": "12345678"
+        }
+    });
+
+    // Act
+    let response = app.payload_for_post(payload.to_string(), "api/v1/users/login").await;
+
+    // Assert
+    assert_eq!(429, response.status().as_u16());
+
+    let retry_after: u64 = response.headers()["Retry-After"].to_str().unwrap().parse().unwrap();
+    assert!(retry_after > 60);
+
+    // The lockout is in the audit log, against the account
+    let lockouts: Vec<(Option<String>, serde_json::Value)> = sqlx::query_as(r#"
+        SELECT users.username, audit_events.details FROM audit_events
+        LEFT JOIN users ON users.id = audit_events.actor_id
+        WHERE audit_events.kind = 'account_locked'
+    "#)
+    .fetch_all(&app.db_pool)
+    .await
+    .unwrap();
+
+    assert_eq!(1, lockouts.len());
+    assert_eq!(Some("test_devactivity".to_string()), lockouts[0].0);
+    assert_eq!("email:test@devactivity.com", lockouts[0].1["key"]);
+    assert_eq!(5, lockouts[0].1["failures"]);
+}
+
+#[actix_web::test]
+async fn magic_link_logs_in_once() {
+    // Arrange
+    let app = start_test_server().await;
+
+    app.register_and_login("test_devactivity", "test@devactivity.com").await;
+
+    let payload = serde_json::json!({
+        "user": {
+            "email": "test@devactivity.com"
+        }
+    });
+
+    let response = app.payload_for_post(payload.to_string(), "api/v1/users/magic-link").await;
+    assert_eq!(200, response.status().as_u16());
+
+    let payload = serde_json::json!({
+        "token": app.token_from_last_email_to("test@devactivity.com")
+    });
 
     // Act
-    let response = app.payload_for_delete(body_data.into(), "api/v1/users/delete").await;
+    let response = app.payload_for_post(payload.to_string(), "api/v1/users/magic-link/consume").await;
 
     // Assert
-    assert_eq!(404, response.status().as_u16());
+    assert_eq!(201, response.status().as_u16());
+
+    let body: serde_json::Value = serde_json::from_str(&response.text().await.unwrap()).unwrap();
+    assert!(body["token"].is_string());
+    assert!(body["refresh_token"].is_string());
+
+    let response = app.payload_for_post(payload.to_string(), "api/v1/users/magic-link/consume").await;
+    assert_eq!(401, response.status().as_u16());
 }
 
+#[actix_web::test]
+async fn magic_link_requests_are_rate_limited_per_email() {
+    // Arrange
+    let app = start_test_server().await;
+
+    app.register_and_login("test_devactivity", "test@devactivity.com").await;
+
+    let payload = serde_json::json!({
+        "user": {
+            "email": "test@devactivity.com"
+        }
+    });
+
+    for _ in 0..5 {
+        let response = app.payload_for_post(payload.to_string(), "api/v1/users/magic-link").await;
+        assert_eq!(200, response.status().as_u16());
+    }
+
+    // Act
+    let response = app.payload_for_post(payload.to_string(), "api/v1/users/magic-link").await;
+
+    // Assert
+    assert_eq!(429, response.status().as_u16());
+    assert!(response.headers().contains_key("Retry-After"));
+}